@@ -0,0 +1,432 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+declare_id!("4QcgKBn5rjex5RPS6a77pYzN9HEJvLKowrgJ5Y7Y3JXT");
+
+/// Bumped whenever an event's layout changes.
+const EVENT_VERSION: u8 = 1;
+/// Fixed-point scale of `StakePool::reward_per_share`.
+const REWARD_SCALE: u128 = 1_000_000_000_000;
+
+/// Holders stake the buyback token and earn whatever lands in the pool's
+/// reward ATA, pro-rata to stake. Point a vault's
+/// `stake_rewards_token_account` at that ATA, or use it as the escrow of
+/// `UnlockMode::StakingRewards`, and the vault's rewards or matured locks
+/// stream to stakers with no CPI between the programs: new reward-vault
+/// balance is folded in on every call.
+#[program]
+pub mod burnflip_staking {
+    use super::*;
+
+    /// Opens the pool for `mint`, with its stake vault and reward ATA.
+    pub fn init_pool(ctx: Context<InitPool>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.mint = ctx.accounts.mint.key();
+        pool.bump = ctx.bumps.pool;
+        pool.stake_vault_bump = ctx.bumps.stake_vault;
+        emit_cpi!(PoolInitializedEvent {
+            version: EVENT_VERSION,
+            seq: pool.next_event_seq(),
+            pool: pool.key(),
+            mint: pool.mint,
+            reward_vault: ctx.accounts.reward_vault.key(),
+        });
+        Ok(())
+    }
+
+    /// Adds `amount` to the owner's stake, settling rewards earned so far.
+    pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+        require!(amount > 0, StakingError::ZeroAmount);
+        let pool = &mut ctx.accounts.pool;
+        pool.accrue(ctx.accounts.reward_vault.amount)?;
+        let position = &mut ctx.accounts.position;
+        if position.owner == Pubkey::default() {
+            position.pool = pool.key();
+            position.owner = ctx.accounts.owner.key();
+            position.bump = ctx.bumps.position;
+        }
+        position.settle(pool)?;
+
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.owner_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.stake_vault.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+        position.amount = position
+            .amount
+            .checked_add(amount)
+            .ok_or(StakingError::MathOverflow)?;
+        pool.total_staked = pool
+            .total_staked
+            .checked_add(amount)
+            .ok_or(StakingError::MathOverflow)?;
+        position.reward_debt = position.accrued(pool)?;
+
+        emit_cpi!(StakeEvent {
+            version: EVENT_VERSION,
+            seq: pool.next_event_seq(),
+            pool: pool.key(),
+            owner: position.owner,
+            amount,
+            position_amount: position.amount,
+            total_staked: pool.total_staked,
+        });
+        Ok(())
+    }
+
+    /// Withdraws `amount` of the owner's stake. Earned rewards stay in the
+    /// position until `claim`.
+    pub fn unstake(ctx: Context<Unstake>, amount: u64) -> Result<()> {
+        require!(amount > 0, StakingError::ZeroAmount);
+        let pool = &mut ctx.accounts.pool;
+        pool.accrue(ctx.accounts.reward_vault.amount)?;
+        let position = &mut ctx.accounts.position;
+        require!(amount <= position.amount, StakingError::InsufficientStake);
+        position.settle(pool)?;
+
+        let mint = pool.mint;
+        let seeds: &[&[u8]] = &[b"pool", mint.as_ref(), &[pool.bump]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.stake_vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.owner_token_account.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+        position.amount -= amount;
+        pool.total_staked -= amount;
+        position.reward_debt = position.accrued(pool)?;
+
+        emit_cpi!(UnstakeEvent {
+            version: EVENT_VERSION,
+            seq: pool.next_event_seq(),
+            pool: pool.key(),
+            owner: position.owner,
+            amount,
+            position_amount: position.amount,
+            total_staked: pool.total_staked,
+        });
+        Ok(())
+    }
+
+    /// Pays out the position's earned rewards from the reward ATA.
+    pub fn claim(ctx: Context<Claim>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.accrue(ctx.accounts.reward_vault.amount)?;
+        let position = &mut ctx.accounts.position;
+        position.settle(pool)?;
+        position.reward_debt = position.accrued(pool)?;
+        let amount = position.pending_rewards;
+        require!(amount > 0, StakingError::NothingToClaim);
+
+        let mint = pool.mint;
+        let seeds: &[&[u8]] = &[b"pool", mint.as_ref(), &[pool.bump]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.reward_vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.owner_token_account.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+        position.pending_rewards = 0;
+        position.total_claimed = position.total_claimed.saturating_add(amount);
+        pool.accounted_rewards = pool.accounted_rewards.saturating_sub(amount);
+        pool.total_claimed = pool.total_claimed.saturating_add(amount);
+
+        emit_cpi!(ClaimEvent {
+            version: EVENT_VERSION,
+            seq: pool.next_event_seq(),
+            pool: pool.key(),
+            owner: position.owner,
+            amount,
+        });
+        Ok(())
+    }
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct InitPool<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + StakePool::SIZE,
+        seeds = [b"pool", mint.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, StakePool>,
+    /// Holds staked tokens, apart from rewards of the same mint.
+    #[account(
+        init,
+        payer = payer,
+        token::mint = mint,
+        token::authority = pool,
+        seeds = [b"stake_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    /// The address vaults send rewards to.
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [b"pool", pool.mint.as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, StakePool>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + StakePosition::SIZE,
+        seeds = [b"position", pool.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, StakePosition>,
+    #[account(address = pool.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint, token::authority = owner)]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"stake_vault", pool.key().as_ref()],
+        bump = pool.stake_vault_bump
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        associated_token::mint = mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Unstake<'info> {
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [b"pool", pool.mint.as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, StakePool>,
+    #[account(
+        mut,
+        has_one = pool,
+        has_one = owner,
+        seeds = [b"position", pool.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, StakePosition>,
+    #[account(address = pool.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint)]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"stake_vault", pool.key().as_ref()],
+        bump = pool.stake_vault_bump
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        associated_token::mint = mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Claim<'info> {
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [b"pool", pool.mint.as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, StakePool>,
+    #[account(
+        mut,
+        has_one = pool,
+        has_one = owner,
+        seeds = [b"position", pool.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, StakePosition>,
+    #[account(address = pool.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint)]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[account]
+pub struct StakePool {
+    pub mint: Pubkey,
+    pub total_staked: u64,
+    /// Rewards earned per staked base unit, scaled by `REWARD_SCALE`.
+    pub reward_per_share: u128,
+    /// Reward-vault balance already folded into `reward_per_share` and not
+    /// yet claimed. Anything above it is new rewards.
+    pub accounted_rewards: u64,
+    pub total_claimed: u64,
+    pub event_seq: u64,
+    pub bump: u8,
+    pub stake_vault_bump: u8,
+}
+
+impl StakePool {
+    pub const SIZE: usize = 32 + 8 + 16 + 8 + 8 + 8 + 1 + 1;
+
+    pub fn next_event_seq(&mut self) -> u64 {
+        let seq = self.event_seq;
+        self.event_seq = seq.wrapping_add(1);
+        seq
+    }
+
+    /// Spreads reward-vault growth since the last call over current stakes.
+    /// Rewards that arrive while nothing is staked wait for the first
+    /// staker, and the rounding remainder carries over to the next call.
+    fn accrue(&mut self, reward_balance: u64) -> Result<()> {
+        let new_rewards = reward_balance.saturating_sub(self.accounted_rewards);
+        if new_rewards == 0 || self.total_staked == 0 {
+            return Ok(());
+        }
+        let total_staked = self.total_staked as u128;
+        let increment = new_rewards as u128 * REWARD_SCALE / total_staked;
+        self.reward_per_share = self
+            .reward_per_share
+            .checked_add(increment)
+            .ok_or(StakingError::MathOverflow)?;
+        // At most `new_rewards`, so this fits in a u64.
+        self.accounted_rewards += (increment * total_staked / REWARD_SCALE) as u64;
+        Ok(())
+    }
+}
+
+#[account]
+pub struct StakePosition {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    /// `amount * reward_per_share` at the last settlement.
+    pub reward_debt: u128,
+    /// Settled rewards not yet claimed.
+    pub pending_rewards: u64,
+    pub total_claimed: u64,
+    pub bump: u8,
+}
+
+impl StakePosition {
+    pub const SIZE: usize = 32 + 32 + 8 + 16 + 8 + 8 + 1;
+
+    fn accrued(&self, pool: &StakePool) -> Result<u128> {
+        (self.amount as u128)
+            .checked_mul(pool.reward_per_share)
+            .ok_or_else(|| error!(StakingError::MathOverflow))
+    }
+
+    /// Moves rewards earned since the last settlement into `pending_rewards`.
+    /// Callers reset `reward_debt` once `amount` is final.
+    fn settle(&mut self, pool: &StakePool) -> Result<()> {
+        let earned = (self.accrued(pool)?.saturating_sub(self.reward_debt) / REWARD_SCALE) as u64;
+        self.pending_rewards = self
+            .pending_rewards
+            .checked_add(earned)
+            .ok_or(StakingError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+#[event]
+pub struct PoolInitializedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub pool: Pubkey,
+    pub mint: Pubkey,
+    pub reward_vault: Pubkey,
+}
+
+#[event]
+pub struct StakeEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub position_amount: u64,
+    pub total_staked: u64,
+}
+
+#[event]
+pub struct UnstakeEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub position_amount: u64,
+    pub total_staked: u64,
+}
+
+#[event]
+pub struct ClaimEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+#[error_code]
+pub enum StakingError {
+    #[msg("Amount must be greater than zero.")]
+    ZeroAmount,
+    #[msg("Unstake exceeds the staked amount.")]
+    InsufficientStake,
+    #[msg("No rewards to claim.")]
+    NothingToClaim,
+    #[msg("Arithmetic overflow.")]
+    MathOverflow,
+}
@@ -1,31 +1,231 @@
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::{instruction::Instruction, program::invoke, system_instruction};
-use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
-use anchor_spl::associated_token::AssociatedToken;
+use anchor_lang::solana_program::{
+    hash::hashv,
+    instruction::{AccountMeta, Instruction},
+    program::{invoke, invoke_signed},
+    pubkey, stake, system_instruction, sysvar,
+};
+use anchor_spl::token::{self, Token};
+use anchor_spl::associated_token::{
+    self, get_associated_token_address_with_program_id, AssociatedToken,
+};
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{
+    self,
+    spl_token_2022::{
+        self,
+        extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+        instruction::AuthorityType,
+        onchain,
+    },
+    Burn, CloseAccount, Mint, MintTo, SetAuthority, TokenAccount, TokenInterface, TransferChecked,
+};
 
 declare_id!("5mCQoqpbQAZa7KVP2VvjnisTT8yPuv28d3545g1Tiaib");
 
-const CRANK_INTERVAL_SECS: i64 = 150;
+/// Floor for `ProtocolSettings::crank_interval_secs`.
+const MIN_CRANK_INTERVAL_SECS: i64 = 150;
+const JUPITER_PROGRAM_ID: Pubkey = pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+const DFLOW_PROGRAM_ID: Pubkey = pubkey!("DF1ow4tspfHX9JwWJsAb9epbkA8hmpSEAtxXy1V27QBH");
+const RAYDIUM_CPMM_PROGRAM_ID: Pubkey = pubkey!("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C");
+const RAYDIUM_SWAP_BASE_INPUT: [u8; 8] = [143, 190, 90, 218, 196, 30, 51, 222];
+const RAYDIUM_DEPOSIT: [u8; 8] = [242, 35, 198, 137, 82, 225, 242, 182];
+const RAYDIUM_INITIALIZE: [u8; 8] = [175, 175, 109, 31, 13, 152, 155, 237];
+const RAYDIUM_CLMM_PROGRAM_ID: Pubkey = pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+const RAYDIUM_CLMM_DECREASE_LIQUIDITY_V2: [u8; 8] = [58, 127, 188, 62, 79, 82, 196, 96];
+const SPL_MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+const METEORA_DLMM_PROGRAM_ID: Pubkey = pubkey!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo");
+const METEORA_DLMM_CLAIM_FEE: [u8; 8] = [169, 32, 79, 137, 136, 232, 70, 137];
+/// Meteora stake-for-fee (M3M3): stakers of a token share its pool's trading
+/// fees.
+const M3M3_PROGRAM_ID: Pubkey = pubkey!("FEESngU3neckdwib9X3KWqdL7Mjmqk9XNp3uh5JbP4KP");
+const M3M3_INITIALIZE_STAKE_ESCROW: [u8; 8] = [67, 237, 111, 110, 218, 214, 29, 153];
+const M3M3_STAKE: [u8; 8] = [206, 176, 202, 18, 200, 209, 179, 108];
+const M3M3_CLAIM_FEE: [u8; 8] = [169, 32, 79, 137, 136, 232, 70, 137];
+const WHIRLPOOL_PROGRAM_ID: Pubkey = pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+const WHIRLPOOL_SWAP: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+const WHIRLPOOL_OPEN_POSITION: [u8; 8] = [135, 128, 47, 77, 15, 152, 240, 49];
+const WHIRLPOOL_INCREASE_LIQUIDITY: [u8; 8] = [46, 156, 243, 118, 13, 205, 251, 178];
+const WHIRLPOOL_UPDATE_FEES_AND_REWARDS: [u8; 8] = [154, 230, 250, 13, 236, 209, 75, 223];
+const WHIRLPOOL_COLLECT_FEES: [u8; 8] = [164, 152, 207, 99, 30, 186, 19, 182];
+const WHIRLPOOL_MIN_SQRT_PRICE: u128 = 4_295_048_016;
+const WHIRLPOOL_MAX_SQRT_PRICE: u128 = 79_226_673_515_401_279_992_447_579_055;
+const PUMP_AMM_PROGRAM_ID: Pubkey = pubkey!("pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA");
+const PUMP_AMM_BUY: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
+const JUPITER_DCA_PROGRAM_ID: Pubkey = pubkey!("DCA265Vj8a9CEuX1eb1LWRnDT7uK6q1xMipnNyatn23M");
+const JUPITER_DCA_OPEN_V2: [u8; 8] = [142, 119, 43, 109, 162, 52, 11, 177];
+const OPENBOOK_V2_PROGRAM_ID: Pubkey = pubkey!("opnb2LAfJYbRMAHHvqjCwQxanZn7ReEHp1k81EohpZb");
+const OPENBOOK_CREATE_OPEN_ORDERS_INDEXER: [u8; 8] = [64, 64, 153, 255, 217, 71, 249, 133];
+const OPENBOOK_CREATE_OPEN_ORDERS_ACCOUNT: [u8; 8] = [204, 181, 175, 222, 40, 125, 188, 71];
+const OPENBOOK_PLACE_ORDER: [u8; 8] = [51, 194, 155, 175, 109, 130, 96, 106];
+const OPENBOOK_CANCEL_ALL_ORDERS: [u8; 8] = [196, 83, 243, 171, 17, 100, 160, 143];
+const OPENBOOK_SETTLE_FUNDS: [u8; 8] = [238, 64, 163, 96, 75, 171, 16, 33];
+/// The vault PDA's one open orders account, the first its indexer creates.
+const OPENBOOK_ACCOUNT_NUM: u32 = 1;
+/// `Market.quote_lot_size`, followed by `base_lot_size`, after the
+/// discriminator.
+const OPENBOOK_LOT_SIZES_OFFSET: usize = 8 + 440;
+const OPENBOOK_POST_ONLY: u8 = 2;
+const OPENBOOK_MAX_ORDERS: u8 = 24;
+const TOKEN_METADATA_PROGRAM_ID: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+const CREATE_METADATA_ACCOUNT_V3: u8 = 33;
+const METADATA_V1_KEY: u8 = 4;
+/// Address, verified flag and share.
+const METADATA_CREATOR_LEN: usize = 32 + 1 + 1;
+const MAX_METADATA_URI_LEN: usize = 200;
+const MAX_ROUTE_DATA_LEN: usize = 512;
+/// Trailing `in_amount`, `quoted_out_amount`, `slippage_bps` and
+/// `platform_fee_bps` of Jupiter's `route` and `shared_accounts_route`.
+const JUPITER_ROUTE_TAIL_LEN: usize = 8 + 8 + 2 + 1;
+const MPL_CORE_PROGRAM_ID: Pubkey = pubkey!("CoREENxT6tW1HoK8ypY1SxRMZTcVPm7R94rH4PZNhX7d");
+const MPL_CORE_CREATE_V1: u8 = 0;
+const MPL_CORE_PERMANENT_FREEZE_PLUGIN: u8 = 5;
+const MPL_CORE_ATTRIBUTES_PLUGIN: u8 = 6;
+const PUMP_FUN_PROGRAM_ID: Pubkey = pubkey!("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P");
+const PUMP_FUN_COLLECT_CREATOR_FEE: [u8; 8] = [20, 22, 86, 123, 198, 28, 219, 132];
+const SPL_STAKE_POOL_PROGRAM_ID: Pubkey = pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+const STAKE_POOL_DEPOSIT_SOL: u8 = 14;
+const STAKE_POOL_WITHDRAW_SOL: u8 = 16;
+const MARGINFI_PROGRAM_ID: Pubkey = pubkey!("MFv2hWf31Z9kbCa1snEPYctwafyhdvnV7FZnsebVacA");
+const MARGINFI_ACCOUNT_INITIALIZE: [u8; 8] = [43, 78, 61, 255, 148, 52, 249, 154];
+const MARGINFI_DEPOSIT: [u8; 8] = [171, 94, 235, 103, 82, 64, 212, 140];
+const MARGINFI_WITHDRAW: [u8; 8] = [36, 72, 74, 19, 210, 210, 192, 192];
+const STREAMFLOW_PROGRAM_ID: Pubkey = pubkey!("strmRqUCoQUgGUan5YhzUZa6KqdzwX5L6FpUxfmKg5m");
+const STREAMFLOW_CREATE: [u8; 8] = [24, 30, 200, 40, 5, 28, 7, 119];
+const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+const PYTH_PRICE_UPDATE_V2: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey =
+    pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+const SWITCHBOARD_PULL_FEED: [u8; 8] = [196, 27, 108, 196, 10, 215, 219, 40];
+const CHAINLINK_STORE_PROGRAM_ID: Pubkey = pubkey!("HEvSKofvBgfaexv23kMabbYqxasxU3mQ4ibBMEmJWHny");
+const CHAINLINK_TRANSMISSIONS: [u8; 8] = [96, 179, 69, 66, 128, 129, 73, 117];
+/// Transmissions header, after which the live ring buffer starts.
+const CHAINLINK_HEADER_LEN: usize = 192;
+const CHAINLINK_TRANSMISSION_LEN: usize = 48;
+/// Light Protocol programs `crank` stores compressed `BuybackRecord`s
+/// through, and the seed of the PDA that signs for this program.
+const LIGHT_SYSTEM_PROGRAM_ID: Pubkey = pubkey!("SySTEM1eSU2p4BGQfQpimFEWWSC1XDFeun3Nqzz3rT7");
+const ACCOUNT_COMPRESSION_PROGRAM_ID: Pubkey =
+    pubkey!("compr6CUsB5m2jS4Y3831ztGSTnDpnKJTKS95d64XVq");
+const NOOP_PROGRAM_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+const LIGHT_INVOKE_CPI: [u8; 8] = [49, 212, 191, 129, 39, 194, 43, 196];
+const LIGHT_CPI_AUTHORITY_SEED: &[u8] = b"cpi_authority";
+const WORMHOLE_CORE_PROGRAM_ID: Pubkey = pubkey!("worm2ZoG2kUd4vFXhvjh93UUH596ayRfgQ2MgjNMTth");
+const WORMHOLE_POST_MESSAGE: u8 = 1;
+const WORMHOLE_CONSISTENCY_FINALIZED: u8 = 1;
+/// Bumped whenever an event's layout changes.
+const EVENT_VERSION: u8 = 1;
+const PRICE_SCALE: u64 = 1_000_000_000;
+/// Fixed-point scale of the USD amounts in `OracleGuard`.
+const USD_SCALE: u64 = 1_000_000;
 const TIMELOCK_SECS: i64 = 7 * 24 * 60 * 60;
-const BURN_BPS: u64 = 8000;
-const LOCK_BPS: u64 = 2000;
+const ROLLING_7D_SECS: i64 = 7 * 24 * 60 * 60;
+const ROLLING_30D_SECS: i64 = 30 * 24 * 60 * 60;
+const MAX_STRATEGIES: usize = 5;
+const MAX_AMM_PROGRAMS: usize = 8;
+const MAX_LOCK_BUCKETS: usize = 3;
+const MAX_COUNCIL: usize = 7;
+const MAX_VETO_COUNCIL: usize = 5;
+/// Keeps `lock_schedule` under the 1 KiB return data limit.
+const MAX_SCHEDULE_TRANCHES: usize = 9;
+const EARLY_UNLOCK_DELAY_SECS: i64 = 48 * 60 * 60;
+const MINT_MIGRATION_DELAY_SECS: i64 = 7 * 24 * 60 * 60;
+const CONFIG_CHANGE_DELAY_SECS: i64 = 7 * 24 * 60 * 60;
+const MAX_BUYBACK_MINTS: usize = 4;
+const MAX_DONOR_BADGES: usize = 4;
+const MAX_PAYMENT_REFERENCES: usize = 4;
+const HISTORY_LEN: usize = 64;
+const LEADERBOARD_LEN: usize = 10;
+const REGISTRY_PAGE_LEN: usize = 64;
+const MAX_SPLIT_LEGS: usize = 8;
+const EPOCH_HISTORY_LEN: usize = 16;
+const MULTI_LEG_ACCOUNTS: usize = 6;
+const CRANK_MANY_VAULT_ACCOUNTS: usize = 4;
+const MAX_KEEPER_REWARD_BPS: u16 = 500;
+const MAX_PROTOCOL_FEE_BPS: u16 = 100;
+const MAX_CLOCK_TOLERANCE_SECS: i64 = 30;
 
 #[program]
 pub mod burnflip_vault {
     use super::*;
 
-    pub fn initialize(
-        ctx: Context<Initialize>,
+    /// Sets up the program-wide `ProtocolConfig`. Only the program's upgrade
+    /// authority can call it, and it becomes the protocol admin.
+    pub fn init_protocol(ctx: Context<InitProtocol>, settings: ProtocolSettings) -> Result<()> {
+        settings.validate()?;
+        let protocol = &mut ctx.accounts.protocol_config;
+        protocol.admin = ctx.accounts.admin.key();
+        protocol.settings = settings;
+        protocol.bump = ctx.bumps.protocol_config;
+        ctx.accounts.keeper_pool.bump = ctx.bumps.keeper_pool;
+        Ok(())
+    }
+
+    /// New fees and the default interval apply to vaults created afterwards;
+    /// the bounds apply to every later `create_vault` and `update_config`.
+    pub fn update_protocol(ctx: Context<UpdateProtocol>, settings: ProtocolSettings) -> Result<()> {
+        settings.validate()?;
+        ctx.accounts.protocol_config.settings = settings;
+        Ok(())
+    }
+
+    /// Halts (or resumes) cranks on every vault, e.g. during an aggregator
+    /// exploit. Per-vault pauses are unaffected, and unlocks keep working.
+    pub fn set_protocol_paused(ctx: Context<UpdateProtocol>, paused: bool) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_config;
+        protocol.paused = paused;
+        emit_cpi!(ProtocolPausedEvent {
+            version: EVENT_VERSION,
+            seq: protocol.next_event_seq(),
+            admin: protocol.admin,
+            paused,
+        });
+        Ok(())
+    }
+
+    /// Factory entry point for new vaults. Charges the protocol's one-time
+    /// creation fee, clamps `config` to the protocol bounds and fixes the
+    /// ongoing `protocol_fee_bps` and crank interval on the vault.
+    pub fn create_vault(
+        ctx: Context<CreateVault>,
         starting_balance_lamports: u64,
         burn_address: Pubkey,
+        mut config: VaultConfig,
     ) -> Result<()> {
+        let protocol = &ctx.accounts.protocol_config.settings;
+        protocol.clamp(&mut config)?;
+        config.validate()?;
+        check_mint_extensions(&ctx.accounts.mint.to_account_info())?;
         let state_key = ctx.accounts.state.key();
         let vault_key = ctx.accounts.vault.key();
-        let authority_key = ctx.accounts.authority.key();
+        let payer_key = ctx.accounts.payer.key();
         let system_key = ctx.accounts.system_program.key();
+        pay_protocol_fee(
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.keeper_pool.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &[],
+            protocol.creation_fee_lamports,
+            protocol.keeper_pool_bps,
+        )?;
+        let protocol_fee_bps = protocol.protocol_fee_bps;
+        let crank_interval_secs = protocol.crank_interval_secs;
+        let registry = &mut ctx.accounts.registry;
+        let vault_id = registry.vault_count;
+        let page = &mut ctx.accounts.registry_page;
+        page.page = vault_id / REGISTRY_PAGE_LEN as u64;
+        page.vaults.push(state_key);
+        registry.vault_count = registry
+            .vault_count
+            .checked_add(1)
+            .ok_or(VaultError::MathOverflow)?;
+
         let state = &mut ctx.accounts.state;
         state.authority = ctx.accounts.authority.key();
+        state.vault_id = vault_id;
         state.mint = ctx.accounts.mint.key();
+        state.seed_mint = state.mint;
         state.burn_address = burn_address;
         state.starting_balance_lamports = starting_balance_lamports;
         state.last_crank_ts = 0;
@@ -33,12 +233,16 @@ pub mod burnflip_vault {
         state.bump = ctx.bumps.state;
         state.vault_bump = ctx.bumps.vault;
         state.timelock_bump = ctx.bumps.timelock_authority;
+        state.config = config;
+        state.initial_supply = ctx.accounts.mint.supply;
+        state.protocol_fee_bps = protocol_fee_bps;
+        state.crank_interval_secs = crank_interval_secs;
 
         if ctx.accounts.vault.lamports() == 0 {
             let rent = Rent::get()?;
             let lamports = rent.minimum_balance(0);
             let ix = system_instruction::create_account(
-                &authority_key,
+                &payer_key,
                 &vault_key,
                 lamports,
                 0,
@@ -48,17 +252,378 @@ pub mod burnflip_vault {
             anchor_lang::solana_program::program::invoke_signed(
                 &ix,
                 &[
-                    ctx.accounts.authority.to_account_info(),
+                    ctx.accounts.payer.to_account_info(),
                     ctx.accounts.vault.to_account_info(),
                     ctx.accounts.system_program.to_account_info(),
                 ],
                 &[seeds],
             )?;
         }
+
+        emit_cpi!(VaultInitializedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state_key,
+            vault_id,
+            authority: ctx.accounts.authority.key(),
+            mint: state.mint,
+            burn_address,
+            starting_balance_lamports,
+        });
+        Ok(())
+    }
+
+    /// Pre-factory name of `create_vault`. It takes `create_vault`'s
+    /// accounts and goes through the factory, so vaults created this way are
+    /// registered and pay the creation fee too.
+    pub fn initialize(
+        ctx: Context<CreateVault>,
+        starting_balance_lamports: u64,
+        burn_address: Pubkey,
+        config: VaultConfig,
+    ) -> Result<()> {
+        create_vault(ctx, starting_balance_lamports, burn_address, config)
+    }
+
+    /// Applies `config` at once. Fields that could redirect or release the
+    /// vault's tokens (see `VaultConfig::timelocked_eq`) must stay as they
+    /// are; those change through `propose_config`.
+    pub fn update_config(ctx: Context<UpdateConfig>, mut config: VaultConfig) -> Result<()> {
+        ctx.accounts.protocol_config.settings.clamp(&mut config)?;
+        config.validate()?;
+        let state = &mut ctx.accounts.state;
+        check_config_change(state, &config)?;
+        require!(
+            config.timelocked_eq(&state.config),
+            VaultError::ConfigChangeTimelocked
+        );
+        state.config = config;
+        state.twap_budget_lamports = 0;
+        state.twap_tranches_left = 0;
+
+        emit_cpi!(ConfigUpdatedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            authority: state.authority,
+        });
+        Ok(())
+    }
+
+    /// Queues `config` to replace the whole config once
+    /// `CONFIG_CHANGE_DELAY_SECS` have passed, giving the veto council time
+    /// to `veto_config_change`; `None` cancels. Needed for any change to the
+    /// fields `update_config` can't touch.
+    pub fn propose_config(ctx: Context<UpdateConfig>, config: Option<VaultConfig>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let config = match config {
+            Some(mut config) => {
+                ctx.accounts.protocol_config.settings.clamp(&mut config)?;
+                config.validate()?;
+                check_config_change(&ctx.accounts.state, &config)?;
+                Some(config)
+            }
+            None => None,
+        };
+        let state = &mut ctx.accounts.state;
+        state.config_change_ts = match config {
+            Some(_) => now
+                .checked_add(CONFIG_CHANGE_DELAY_SECS)
+                .ok_or(VaultError::MathOverflow)?,
+            None => 0,
+        };
+        state.pending_config = config;
+        emit_cpi!(ConfigChangeProposedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            ready_ts: state.config_change_ts,
+        });
+        Ok(())
+    }
+
+    /// Replaces the config with the one `propose_config` queued, once its
+    /// delay has passed. Changes `update_config` made in the meantime are
+    /// overwritten with the proposal's values.
+    pub fn apply_config(ctx: Context<UpdateConfig>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let state = &mut ctx.accounts.state;
+        require!(now >= state.config_change_ts, VaultError::TimelockActive);
+        let mut config = state
+            .pending_config
+            .take()
+            .ok_or(VaultError::NoConfigChange)?;
+        // Protocol limits or the council may have moved since the proposal.
+        ctx.accounts.protocol_config.settings.clamp(&mut config)?;
+        config.validate()?;
+        check_config_change(state, &config)?;
+        state.config = config;
+        state.config_change_ts = 0;
+        state.twap_budget_lamports = 0;
+        state.twap_tranches_left = 0;
+
+        emit_cpi!(ConfigUpdatedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            authority: state.authority,
+        });
+        Ok(())
+    }
+
+    /// Starts the `MINT_MIGRATION_DELAY_SECS` countdown for moving the vault
+    /// to `new_mint` (e.g. a v1 -> v2 token migration); `None` cancels.
+    pub fn propose_mint_migration(
+        ctx: Context<UpdateConfig>,
+        new_mint: Option<Pubkey>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let state = &mut ctx.accounts.state;
+        require!(new_mint != Some(state.mint), VaultError::InvalidConfig);
+        state.pending_mint = new_mint;
+        state.mint_migration_ts = match new_mint {
+            Some(_) => now
+                .checked_add(MINT_MIGRATION_DELAY_SECS)
+                .ok_or(VaultError::MathOverflow)?,
+            None => 0,
+        };
+        emit_cpi!(MintMigrationProposedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            new_mint,
+            ready_ts: state.mint_migration_ts,
+        });
+        Ok(())
+    }
+
+    /// Redirects future buybacks to the proposed mint once its delay has
+    /// passed. Missing vault and timelock ATAs for it are created when a
+    /// `payer` signs; without one they must already exist, which keeps this
+    /// executable from a governance proposal that has no rent payer.
+    /// Retained, holder-reward and fee-stake balances must be released first.
+    /// Open tranches keep their own mint and stay unlockable from the old
+    /// timelock ATA; what they still hold moves to `legacy_locked`, and a vault
+    /// can migrate again only once that is unlocked. Burn and lock statistics
+    /// restart against the new mint. Mint-specific config accounts (treasury,
+    /// stake rewards, pools, oracle) must be repointed with `update_config`
+    /// before the next crank.
+    pub fn migrate_mint(ctx: Context<MigrateMint>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let state = &mut ctx.accounts.state;
+        require!(state.pending_mint.is_some(), VaultError::NoMintMigration);
+        require!(now >= state.mint_migration_ts, VaultError::TimelockActive);
+        check_mint_extensions(&ctx.accounts.new_mint.to_account_info())?;
+        // Pending and retained tokens are denominated in the old mint.
+        require!(!state.swap_pending, VaultError::SwapPending);
+        require!(
+            state.retained_tokens == 0
+                && state.holder_reward_tokens == 0
+                && state.fee_stake_tokens == 0
+                && state.config.buyback_mints.is_empty(),
+            VaultError::InvalidConfig
+        );
+        // Only one generation of old-mint tranches is tracked.
+        require!(state.legacy_locked == 0, VaultError::InvalidConfig);
+        ctx.accounts.create_token_accounts()?;
+
+        let state = &mut ctx.accounts.state;
+        let old_mint = state.mint;
+        state.legacy_mint = Some(old_mint);
+        state.legacy_locked = state.total_locked.saturating_sub(state.total_unlocked);
+        state.mint = ctx.accounts.new_mint.key();
+        state.pending_mint = None;
+        state.mint_migration_ts = 0;
+        state.initial_supply = ctx.accounts.new_mint.supply;
+        state.total_burned = 0;
+        state.burned_bps_of_supply = 0;
+        state.total_locked = 0;
+        state.total_unlocked = 0;
+        state.lock_cost_basis = 0;
+
+        emit_cpi!(MintMigratedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            old_mint,
+            new_mint: state.mint,
+        });
+        Ok(())
+    }
+
+    /// Installs the vault's veto council, e.g. 3-of-5 community members who
+    /// can cancel a pending mint migration with `veto_mint_migration` or a
+    /// pending config change with `veto_config_change`. The authority can
+    /// only install it once; afterwards the council alone changes itself,
+    /// with `rotate_veto_council`.
+    pub fn init_veto_council(
+        ctx: Context<UpdateConfig>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.state.veto_council.is_empty() && !members.is_empty(),
+            VaultError::InvalidConfig
+        );
+        validate_veto_council(&members, threshold)?;
+        let state = &mut ctx.accounts.state;
+        state.veto_council = members;
+        state.veto_threshold = threshold;
+        emit_cpi!(VetoCouncilSetEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            members: state.veto_council.clone(),
+            threshold,
+        });
+        Ok(())
+    }
+
+    /// Replaces the veto council, signed by `veto_threshold` current members
+    /// in `remaining_accounts`. An empty `members` dissolves it, after which
+    /// the authority may install a new one.
+    pub fn rotate_veto_council(
+        ctx: Context<VetoCouncil>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        ctx.accounts.check_approved(ctx.remaining_accounts)?;
+        validate_veto_council(&members, threshold)?;
+        let state = &mut ctx.accounts.state;
+        state.veto_council = members;
+        state.veto_threshold = threshold;
+        emit_cpi!(VetoCouncilSetEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            members: state.veto_council.clone(),
+            threshold,
+        });
+        Ok(())
+    }
+
+    /// Cancels the pending `propose_mint_migration` before `migrate_mint`
+    /// can run, signed by `veto_threshold` veto council members in
+    /// `remaining_accounts`.
+    pub fn veto_mint_migration(ctx: Context<VetoCouncil>) -> Result<()> {
+        let approvals = ctx.accounts.check_approved(ctx.remaining_accounts)?;
+        let state = &mut ctx.accounts.state;
+        let new_mint = state.pending_mint.ok_or(VaultError::NoMintMigration)?;
+        state.pending_mint = None;
+        state.mint_migration_ts = 0;
+        emit_cpi!(MintMigrationVetoedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            new_mint,
+            approvals,
+        });
+        Ok(())
+    }
+
+    /// Cancels the pending `propose_config` before `apply_config` can run,
+    /// signed by `veto_threshold` veto council members in
+    /// `remaining_accounts`.
+    pub fn veto_config_change(ctx: Context<VetoCouncil>) -> Result<()> {
+        let approvals = ctx.accounts.check_approved(ctx.remaining_accounts)?;
+        let state = &mut ctx.accounts.state;
+        require!(
+            state.pending_config.take().is_some(),
+            VaultError::NoConfigChange
+        );
+        state.config_change_ts = 0;
+        emit_cpi!(ConfigChangeVetoedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            approvals,
+        });
+        Ok(())
+    }
+
+    /// Halts (or resumes) every crank path for this vault. Unlocks and
+    /// deposits keep working while paused.
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        state.paused = paused;
+        emit_cpi!(PausedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            paused,
+        });
+        Ok(())
+    }
+
+    /// Creates the vault's `ApprovedRoute`. Anyone may pay for it; it stays
+    /// unusable until the authority sets a route.
+    pub fn init_approved_route(ctx: Context<InitApprovedRoute>) -> Result<()> {
+        ctx.accounts.approved_route.state = ctx.accounts.state.key();
+        ctx.accounts.approved_route.bump = ctx.bumps.approved_route;
+        Ok(())
+    }
+
+    /// Stores a Jupiter route for `crank` to replay until `expires_ts`, for
+    /// automation threads that can't fetch quotes. `accounts_hash` commits
+    /// to the route's account list (see `route_accounts_hash`); an
+    /// `expires_ts` of zero withdraws the route. Only Jupiter vaults can
+    /// replay routes, as the rescaling relies on its data layout.
+    pub fn set_approved_route(
+        ctx: Context<SetApprovedRoute>,
+        ix_data: Vec<u8>,
+        accounts_hash: [u8; 32],
+        expires_ts: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.state.config.aggregator == Aggregator::Jupiter
+                && ix_data.len() <= MAX_ROUTE_DATA_LEN
+                && jupiter_route_amounts(&ix_data).is_some_and(|(quoted_in, _)| quoted_in > 0),
+            VaultError::InvalidRoute
+        );
+        let route = &mut ctx.accounts.approved_route;
+        route.ix_data = ix_data;
+        route.accounts_hash = accounts_hash;
+        route.expires_ts = expires_ts;
+        let state = &mut ctx.accounts.state;
+        emit_cpi!(ApprovedRouteSetEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            accounts_hash,
+            expires_ts,
+        });
         Ok(())
     }
 
-    pub fn deposit(ctx: Context<Deposit>, lamports: u64) -> Result<()> {
+    /// `referrer` credits the integrator that routed the deposit; pass its
+    /// `ReferrerRecord` to accumulate totals on-chain. A deposit that takes
+    /// the `DepositorRecord` total across a `config.donor_badges` threshold
+    /// mints the depositor that tier's badge. Read-only remaining accounts
+    /// are Solana Pay references: they only let a payment link find the
+    /// transaction, and are echoed in the event.
+    pub fn deposit(ctx: Context<Deposit>, lamports: u64, referrer: Option<Pubkey>) -> Result<()> {
+        require!(
+            ctx.accounts.state.config.base_mint.is_none(),
+            VaultError::BaseMintVault
+        );
+        require!(
+            ctx.remaining_accounts.len() <= MAX_PAYMENT_REFERENCES
+                && ctx
+                    .remaining_accounts
+                    .iter()
+                    .all(|a| !a.is_writable && !a.is_signer),
+            VaultError::InvalidReference
+        );
+        let references: Vec<Pubkey> = ctx.remaining_accounts.iter().map(|a| a.key()).collect();
+        if let Some(cap) = ctx.accounts.state.config.max_vault_lamports {
+            let new_balance = ctx
+                .accounts
+                .vault
+                .lamports()
+                .checked_add(lamports)
+                .ok_or(VaultError::MathOverflow)?;
+            require!(new_balance <= cap, VaultError::VaultCapExceeded);
+        }
         let ix = anchor_lang::solana_program::system_instruction::transfer(
             &ctx.accounts.authority.key(),
             &ctx.accounts.vault.key(),
@@ -72,317 +637,11322 @@ pub mod burnflip_vault {
                 ctx.accounts.system_program.to_account_info(),
             ],
         )?;
+        let total_before = ctx
+            .accounts
+            .depositor_record
+            .as_ref()
+            .map(|r| r.total_lamports);
+        record_deposit(
+            ctx.accounts.state.key(),
+            ctx.accounts.authority.key(),
+            ctx.accounts.depositor_record.as_deref_mut(),
+            ctx.accounts.leaderboard.as_deref_mut(),
+            lamports,
+        )?;
+        record_referral(
+            ctx.accounts.state.key(),
+            referrer,
+            ctx.accounts.referrer_record.as_deref_mut(),
+            lamports,
+        )?;
+        if let (Some(before), Some(record)) = (total_before, &ctx.accounts.depositor_record) {
+            let thresholds = &ctx.accounts.state.config.donor_badges;
+            if let Some(tier) = crossed_badge_tier(thresholds, before, record.total_lamports) {
+                let threshold_lamports = thresholds[tier];
+                let asset = ctx
+                    .accounts
+                    .mint_donor_badge(tier as u8, threshold_lamports)?;
+                emit_cpi!(DonorBadgeMintedEvent {
+                    version: EVENT_VERSION,
+                    seq: ctx.accounts.state.next_event_seq(),
+                    state: ctx.accounts.state.key(),
+                    depositor: ctx.accounts.authority.key(),
+                    asset,
+                    tier: tier as u8,
+                    threshold_lamports,
+                });
+            }
+        }
+
+        emit_cpi!(DepositEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            state: ctx.accounts.state.key(),
+            depositor: ctx.accounts.authority.key(),
+            lamports,
+            vault_lamports: ctx.accounts.vault.lamports(),
+            referrer,
+            references,
+        });
         Ok(())
     }
 
-    pub fn crank(ctx: Context<Crank>, jupiter_ix_data: Vec<u8>) -> Result<()> {
+    /// Aggregator crank. `swap_ix_data` is a fresh quote's swap data for
+    /// `config.aggregator`; with `approved_route` passed it must be empty,
+    /// and the stored route is replayed with its amounts rescaled to this
+    /// crank's input. The first `route_account_count` remaining accounts are
+    /// the route; any after them are the mint's transfer hook accounts.
+    pub fn crank<'info>(
+        ctx: Context<'_, '_, '_, 'info, Crank<'info>>,
+        swap_ix_data: Vec<u8>,
+        route_account_count: u8,
+    ) -> Result<()> {
+        ctx.accounts.operator_pass.check(
+            &ctx.accounts.distribution.state.config,
+            ctx.accounts.distribution.payer.key(),
+        )?;
         let clock = Clock::get()?;
-        let state_key = ctx.accounts.state.key();
-        let mint_key = ctx.accounts.mint.key();
-        let state_bump = ctx.accounts.state.bump;
-        let vault_bump = ctx.accounts.state.vault_bump;
-        let timelock_bump = ctx.accounts.state.timelock_bump;
-        let state_account = ctx.accounts.state.to_account_info();
-        let state = &mut ctx.accounts.state;
+        let (route_accounts, hook_accounts) =
+            split_route_accounts(ctx.remaining_accounts, route_account_count)?;
+        let state_key = ctx.accounts.distribution.state.key();
+        let vault_bump = ctx.accounts.distribution.state.vault_bump;
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[vault_bump]];
 
-        require!(
-            clock.unix_timestamp - state.last_crank_ts >= CRANK_INTERVAL_SECS,
-            VaultError::CrankTooSoon
-        );
+        let max_in_lamports = crank_profit(
+            &mut ctx.accounts.distribution.state,
+            &ctx.accounts.distribution.vault.to_account_info(),
+            clock.unix_timestamp,
+        )?;
+        let wsol_before = ctx.accounts.vault_wsol_ata.amount;
+        let tokens_before = ctx.accounts.distribution.vault_token_account.amount;
 
-        let vault_balance = ctx.accounts.vault.to_account_info().lamports();
-        require!(
-            vault_balance > state.starting_balance_lamports,
-            VaultError::NoProfit
-        );
-        let profit_lamports = vault_balance - state.starting_balance_lamports;
-        require!(profit_lamports > 0, VaultError::NoProfit);
+        wrap_sol(
+            &ctx.accounts.distribution.vault.to_account_info(),
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            vault_seeds,
+            max_in_lamports,
+        )?;
+        let swap_ix_data = match &ctx.accounts.approved_route {
+            Some(route) => {
+                require!(
+                    swap_ix_data.is_empty()
+                        && ctx.accounts.distribution.state.config.aggregator == Aggregator::Jupiter,
+                    VaultError::InvalidRoute
+                );
+                route.replay(route_accounts, max_in_lamports, clock.unix_timestamp)?
+            }
+            None => swap_ix_data,
+        };
+        aggregator_swap(
+            &ctx.accounts.aggregator_program.to_account_info(),
+            route_accounts,
+            swap_ix_data,
+            ctx.accounts.distribution.vault.key(),
+            vault_seeds,
+            &ctx.accounts.distribution.state.config,
+        )?;
 
-        // Wrap SOL into WSOL (profit amount) in the vault WSOL ATA.
-        let wsol_ata = &ctx.accounts.vault_wsol_ata;
-        let wrap_ix = system_instruction::transfer(
-            &ctx.accounts.vault.key(),
-            &wsol_ata.key(),
-            profit_lamports,
+        ctx.accounts.vault_wsol_ata.reload()?;
+        ctx.accounts.distribution.vault_token_account.reload()?;
+        let tokens_bought = swap_output(
+            tokens_before,
+            ctx.accounts.distribution.vault_token_account.amount,
+        )?;
+        let profit_lamports = settle_swap_input(
+            &mut ctx.accounts.distribution.state,
+            max_in_lamports,
+            ctx.accounts
+                .vault_wsol_ata
+                .amount
+                .saturating_sub(wsol_before),
+            tokens_bought,
+        )?;
+
+        let accounts = ctx.accounts.distribution.accounts(
+            Some(ctx.accounts.vault_wsol_ata.to_account_info()),
+            token::spl_token::native_mint::DECIMALS,
+            ctx.accounts.token_program.to_account_info(),
+            hook_accounts,
         );
-        anchor_lang::solana_program::program::invoke_signed(
-            &wrap_ix,
-            &[
-                ctx.accounts.vault.to_account_info(),
-                wsol_ata.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-            &[&[
-                b"vault",
-                state_key.as_ref(),
-                &[vault_bump],
-            ]],
+        let light = ctx.accounts.light_record_accounts();
+        let record = match ctx.accounts.buyback_record.as_mut() {
+            Some(record) => RecordSink::Account(record),
+            None => RecordSink::Compressed(light.ok_or(VaultError::MissingBuybackRecord)?),
+        };
+        let event = finish_crank(
+            &mut ctx.accounts.distribution.state,
+            &accounts,
+            record,
+            ctx.accounts.buyback_history.as_ref(),
+            ctx.accounts.distribution.lock_tranche.as_mut(),
+            profit_lamports,
+            tokens_bought,
+            clock.unix_timestamp,
         )?;
+        emit_cpi!(event);
+        if ctx.accounts.distribution.state.config.auto_unlock {
+            if let Some(event) = ctx
+                .accounts
+                .auto_unlock(clock.unix_timestamp, hook_accounts)?
+            {
+                emit_cpi!(event);
+            }
+        }
+        Ok(())
+    }
+
+    /// Aggregator crank for vaults with `config.buyback_mints` set. The
+    /// profit is split by mint weight and each leg's route runs in turn. Per
+    /// leg, `remaining_accounts` holds that mint's vault, burn and timelock
+    /// token accounts, the `LockTranche` PDA for its lock leg, the mint and
+    /// its token program, followed by `account_count` route accounts and
+    /// `hook_account_count` transfer hook accounts. A timelock ATA closed by
+    /// `unlock` must be recreated (e.g. idempotent ATA create) earlier in the
+    /// transaction.
+    pub fn crank_multi<'info>(
+        ctx: Context<'_, '_, '_, 'info, CrankMulti<'info>>,
+        routes: Vec<MultiMintRoute>,
+    ) -> Result<()> {
+        ctx.accounts
+            .operator_pass
+            .check(&ctx.accounts.state.config, ctx.accounts.payer.key())?;
+        let clock = Clock::get()?;
+        let legs = ctx.accounts.state.config.buyback_mints.clone();
+        require!(!legs.is_empty(), VaultError::NotMultiMint);
+        require!(routes.len() == legs.len(), VaultError::InvalidRoute);
+        let state_key = ctx.accounts.state.key();
+        let vault_bump = ctx.accounts.state.vault_bump;
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[vault_bump]];
+        let seed_mint = ctx.accounts.state.seed_mint;
+        let authority_key = ctx.accounts.state.authority;
+        let state_seeds: &[&[u8]] = &[
+            b"state",
+            seed_mint.as_ref(),
+            authority_key.as_ref(),
+            &[ctx.accounts.state.bump],
+        ];
+        let burn_bps = ctx
+            .accounts
+            .state
+            .config
+            .weight_of(DistributionStrategy::Burn) as u64;
 
-        // Sync native WSOL balance.
-        let sync_ix = anchor_spl::token::spl_token::instruction::sync_native(
-            &ctx.accounts.token_program.key(),
-            &wsol_ata.key(),
+        let max_in_lamports = crank_spend(
+            &mut ctx.accounts.state,
+            &ctx.accounts.vault.to_account_info(),
+            clock.unix_timestamp,
         )?;
-        invoke(
-            &sync_ix,
-            &[
-                ctx.accounts.token_program.to_account_info(),
-                wsol_ata.to_account_info(),
-            ],
+        wrap_sol(
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            vault_seeds,
+            max_in_lamports,
         )?;
 
-        // Jupiter CPI swap (WSOL -> BurnFlip token)
-        let ix = Instruction {
-            program_id: ctx.accounts.jupiter_program.key(),
-            accounts: ctx
-                .remaining_accounts
-                .iter()
-                .flat_map(|a| a.to_account_metas(None))
-                .collect(),
-            data: jupiter_ix_data,
-        };
-        invoke(&ix, &ctx.remaining_accounts)?;
+        let mut offset = 0usize;
+        let mut spent_lamports = 0u64;
+        let mut own_bought = 0u64;
+        for (leg, route) in legs.iter().zip(routes) {
+            let route_end = MULTI_LEG_ACCOUNTS + route.account_count as usize;
+            let end = offset + route_end + route.hook_account_count as usize;
+            require!(
+                end <= ctx.remaining_accounts.len(),
+                VaultError::InvalidRoute
+            );
+            let leg_accounts = &ctx.remaining_accounts[offset..end];
+            offset = end;
 
-        let vault_amount = ctx.accounts.vault_token_account.amount;
-        require!(vault_amount > 0, VaultError::NoTokens);
-        let burn_amount = vault_amount * BURN_BPS / 10_000;
-        let lock_amount = vault_amount * LOCK_BPS / 10_000;
+            let vault_token = InterfaceAccount::<TokenAccount>::try_from(&leg_accounts[0])?;
+            let burn_token = InterfaceAccount::<TokenAccount>::try_from(&leg_accounts[1])?;
+            let timelock_token = InterfaceAccount::<TokenAccount>::try_from(&leg_accounts[2])?;
+            let leg_mint = InterfaceAccount::<Mint>::try_from(&leg_accounts[4])?;
+            let leg_token_program = &leg_accounts[5];
+            require!(
+                leg_mint.key() == leg.mint && leg_token_program.key() == *leg_accounts[4].owner,
+                VaultError::InvalidRoute
+            );
+            check_mint_extensions(&leg_accounts[4])?;
+            require!(
+                vault_token.mint == leg.mint && vault_token.owner == state_key,
+                VaultError::InvalidRoute
+            );
+            require!(
+                burn_token.mint == leg.mint && burn_token.owner == ctx.accounts.state.burn_address,
+                VaultError::InvalidRoute
+            );
+            require!(
+                timelock_token.mint == leg.mint
+                    && timelock_token.owner == ctx.accounts.timelock_authority.key(),
+                VaultError::InvalidRoute
+            );
 
-        let state_seeds = &[
-            b"state".as_ref(),
-            mint_key.as_ref(),
-            &[state_bump],
-        ];
-        let state_signer = &[&state_seeds[..]];
+            let leg_lamports = bps_of(max_in_lamports, leg.bps as u64)?;
+            let wsol_before = ctx.accounts.vault_wsol_ata.amount;
+            aggregator_swap(
+                &ctx.accounts.aggregator_program.to_account_info(),
+                &leg_accounts[MULTI_LEG_ACCOUNTS..route_end],
+                route.data,
+                ctx.accounts.vault.key(),
+                vault_seeds,
+                &ctx.accounts.state.config,
+            )?;
+            ctx.accounts.vault_wsol_ata.reload()?;
+            let leg_spent = wsol_before.saturating_sub(ctx.accounts.vault_wsol_ata.amount);
+            require!(leg_spent <= leg_lamports, VaultError::LegOverspent);
+            spent_lamports += leg_spent;
 
-        let cpi_ctx_burn = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.vault_token_account.to_account_info(),
-                to: ctx.accounts.burn_token_account.to_account_info(),
-                authority: state_account.clone(),
-            },
-            state_signer,
+            let bought =
+                token::accessor::amount(&leg_accounts[0])?.saturating_sub(vault_token.amount);
+            require!(bought > 0, VaultError::NoTokens);
+            let burn_amount = bps_of(bought, burn_bps)?;
+            let lock_amount = bought - burn_amount;
+            for (to, amount) in [
+                (&leg_accounts[1], burn_amount),
+                (&leg_accounts[2], lock_amount),
+            ] {
+                if amount == 0 {
+                    continue;
+                }
+                let cpi_ctx = CpiContext::new_with_signer(
+                    leg_token_program.clone(),
+                    TransferChecked {
+                        from: leg_accounts[0].clone(),
+                        mint: leg_accounts[4].clone(),
+                        to: to.clone(),
+                        authority: ctx.accounts.state.to_account_info(),
+                    },
+                    &[state_seeds],
+                )
+                .with_remaining_accounts(leg_accounts[route_end..].to_vec());
+                transfer_checked_with_hook(cpi_ctx, amount, leg_mint.decimals)?;
+            }
+            if leg.mint == ctx.accounts.state.mint {
+                let state = &mut ctx.accounts.state;
+                own_bought = own_bought.saturating_add(bought);
+                state.add_burned(burn_amount);
+                state.total_locked = state.total_locked.saturating_add(lock_amount);
+                state.add_lock_cost(lock_amount, execution_price(leg_spent, bought)?);
+            }
+            if lock_amount > 0 {
+                let index = ctx.accounts.state.next_lock_index()?;
+                create_lock_tranche(
+                    &ctx.accounts.payer.to_account_info(),
+                    &leg_accounts[3],
+                    &ctx.accounts.system_program.to_account_info(),
+                    &LockTranche::new(
+                        state_key,
+                        index,
+                        leg.mint,
+                        lock_amount,
+                        ctx.accounts.payer.key(),
+                        clock.unix_timestamp,
+                        &ctx.accounts.state.config,
+                    )?,
+                )?;
+            }
+
+            emit_cpi!(BuybackEvent {
+                version: EVENT_VERSION,
+                seq: ctx.accounts.state.next_event_seq(),
+                vault_id: ctx.accounts.state.vault_id,
+                crank_index: Some(ctx.accounts.state.crank_count),
+                profit_lamports: leg_spent,
+                burn_amount,
+                lock_amount,
+                liquidity_amount: 0,
+                stake_rewards_amount: 0,
+                treasury_amount: 0,
+                retained_amount: 0,
+                holder_rewards_amount: 0,
+                fee_stake_amount: 0,
+                keeper_reward_amount: 0,
+                keeper: ctx.accounts.payer.key(),
+                burn_address: burn_token.key(),
+                timelock_account: timelock_token.key(),
+                execution_price: execution_price(leg_spent, bought)?,
+                oracle_price: None,
+                burned_bps_of_supply: ctx.accounts.state.burned_bps_of_supply,
+            });
+        }
+        require!(
+            offset == ctx.remaining_accounts.len(),
+            VaultError::InvalidRoute
         );
-        token::transfer(cpi_ctx_burn, burn_amount)?;
 
-        let cpi_ctx_lock = CpiContext::new_with_signer(
+        settle_swap_input(
+            &mut ctx.accounts.state,
+            max_in_lamports,
+            max_in_lamports - spent_lamports,
+            0,
+        )?;
+        close_vault_wsol(
+            &ctx.accounts.state,
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+        ctx.accounts.state.last_keeper = ctx.accounts.payer.key();
+        record_crank(
+            &mut ctx.accounts.state,
+            spent_lamports,
+            own_bought,
+            clock.unix_timestamp,
+        )
+    }
+
+    /// `deposit` for vaults with `config.base_mint`: moves `amount` of the
+    /// base token into the vault PDA's ATA, where `crank_spl_profit` finds it.
+    pub fn deposit_token(
+        ctx: Context<DepositToken>,
+        amount: u64,
+        referrer: Option<Pubkey>,
+    ) -> Result<()> {
+        let vault_amount = ctx
+            .accounts
+            .vault_base_token_account
+            .amount
+            .checked_add(amount)
+            .ok_or(VaultError::MathOverflow)?;
+        if let Some(cap) = ctx.accounts.state.config.max_vault_lamports {
+            require!(vault_amount <= cap, VaultError::VaultCapExceeded);
+        }
+        let cpi_ctx = CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.vault_token_account.to_account_info(),
-                to: ctx.accounts.timelock_token_account.to_account_info(),
-                authority: state_account,
+            TransferChecked {
+                from: ctx.accounts.depositor_token_account.to_account_info(),
+                mint: ctx.accounts.base_mint.to_account_info(),
+                to: ctx.accounts.vault_base_token_account.to_account_info(),
+                authority: ctx.accounts.authority.to_account_info(),
             },
-            state_signer,
         );
-        token::transfer(cpi_ctx_lock, lock_amount)?;
+        token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.base_mint.decimals)?;
+        record_deposit(
+            ctx.accounts.state.key(),
+            ctx.accounts.authority.key(),
+            ctx.accounts.depositor_record.as_deref_mut(),
+            ctx.accounts.leaderboard.as_deref_mut(),
+            amount,
+        )?;
+        record_referral(
+            ctx.accounts.state.key(),
+            referrer,
+            ctx.accounts.referrer_record.as_deref_mut(),
+            amount,
+        )?;
 
-        // Close WSOL ATA back to vault to reclaim rent + remaining SOL.
-        let cpi_close = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            CloseAccount {
-                account: ctx.accounts.vault_wsol_ata.to_account_info(),
-                destination: ctx.accounts.vault.to_account_info(),
-                authority: ctx.accounts.vault.to_account_info(),
-            },
-            state_signer,
+        emit_cpi!(DepositEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            state: ctx.accounts.state.key(),
+            depositor: ctx.accounts.authority.key(),
+            lamports: amount,
+            vault_lamports: vault_amount,
+            referrer,
+            references: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Creates the authority's revenue splitter. Revenue sent to the splitter
+    /// PDA is forwarded to each leg's vault by `split_revenue`.
+    pub fn init_splitter(ctx: Context<InitSplitter>, legs: Vec<SplitLeg>) -> Result<()> {
+        validate_split_legs(&legs)?;
+        let splitter = &mut ctx.accounts.splitter;
+        splitter.authority = ctx.accounts.authority.key();
+        splitter.legs = legs;
+        splitter.bump = ctx.bumps.splitter;
+        Ok(())
+    }
+
+    pub fn update_splitter(ctx: Context<UpdateSplitter>, legs: Vec<SplitLeg>) -> Result<()> {
+        validate_split_legs(&legs)?;
+        ctx.accounts.splitter.legs = legs;
+        Ok(())
+    }
+
+    /// Forwards the splitter's balance above rent to its legs' vaults by
+    /// weight, as a `deposit` from the splitter. Permissionless, like the
+    /// crank. `remaining_accounts` holds each leg's state and vault PDA, in
+    /// leg order.
+    pub fn split_revenue<'info>(
+        ctx: Context<'_, '_, '_, 'info, SplitRevenue<'info>>,
+    ) -> Result<()> {
+        let splitter = &ctx.accounts.splitter;
+        let rent = Rent::get()?.minimum_balance(8 + RevenueSplitter::SIZE);
+        let available = splitter.to_account_info().lamports().saturating_sub(rent);
+        require!(available > 0, VaultError::NoProfit);
+        require!(
+            ctx.remaining_accounts.len() == splitter.legs.len() * 2,
+            VaultError::InvalidRoute
         );
-        token::close_account(cpi_close)?;
 
-        state.last_crank_ts = clock.unix_timestamp;
-        state.timelock_unlock_ts = clock.unix_timestamp + TIMELOCK_SECS;
+        let legs = splitter.legs.clone();
+        for (leg, accounts) in legs.iter().zip(ctx.remaining_accounts.chunks(2)) {
+            let mut state = Account::<VaultState>::try_from(&accounts[0])?;
+            require_keys_eq!(state.key(), leg.state, VaultError::InvalidRoute);
+            require!(state.config.base_mint.is_none(), VaultError::BaseMintVault);
+            let vault = &accounts[1];
+            require_keys_eq!(
+                Pubkey::create_program_address(
+                    &[b"vault", leg.state.as_ref(), &[state.vault_bump]],
+                    ctx.program_id,
+                )
+                .map_err(|_| VaultError::InvalidRoute)?,
+                vault.key(),
+                VaultError::InvalidRoute
+            );
 
-        emit!(BuybackEvent {
-            profit_lamports,
-            burn_amount,
-            lock_amount,
-            burn_address: ctx.accounts.burn_token_account.key(),
-            timelock_account: ctx.accounts.timelock_token_account.key(),
-        });
+            let lamports = bps_of(available, leg.bps as u64)?;
+            let vault_lamports = vault
+                .lamports()
+                .checked_add(lamports)
+                .ok_or(VaultError::MathOverflow)?;
+            if let Some(cap) = state.config.max_vault_lamports {
+                require!(vault_lamports <= cap, VaultError::VaultCapExceeded);
+            }
+            ctx.accounts.splitter.sub_lamports(lamports)?;
+            **vault.try_borrow_mut_lamports()? = vault_lamports;
 
+            emit_cpi!(DepositEvent {
+                version: EVENT_VERSION,
+                seq: state.next_event_seq(),
+                state: leg.state,
+                depositor: ctx.accounts.splitter.key(),
+                lamports,
+                vault_lamports,
+                referrer: None,
+                references: Vec::new(),
+            });
+            state.exit(ctx.program_id)?;
+        }
         Ok(())
     }
 
-    pub fn unlock(ctx: Context<Unlock>) -> Result<()> {
+    /// Crank for profit that arrives as an SPL token (e.g. USDC creator fees)
+    /// in a vault-owned ATA. The whole ATA balance is swapped to the buyback
+    /// mint through `config.aggregator` and distributed like a SOL crank.
+    /// `remaining_accounts` is split as for `crank`.
+    pub fn crank_spl_profit<'info>(
+        ctx: Context<'_, '_, '_, 'info, CrankSplProfit<'info>>,
+        swap_ix_data: Vec<u8>,
+        route_account_count: u8,
+    ) -> Result<()> {
+        ctx.accounts.operator_pass.check(
+            &ctx.accounts.distribution.state.config,
+            ctx.accounts.distribution.payer.key(),
+        )?;
         let clock = Clock::get()?;
-        let state = &ctx.accounts.state;
+        let (route_accounts, hook_accounts) =
+            split_route_accounts(ctx.remaining_accounts, route_account_count)?;
+        let base_vault = ctx.accounts.distribution.state.config.base_mint
+            == Some(ctx.accounts.profit_mint.key());
+        let state = &mut ctx.accounts.distribution.state;
         require!(
-            clock.unix_timestamp >= state.timelock_unlock_ts,
-            VaultError::TimelockActive
+            state.config.buyback_mints.is_empty(),
+            VaultError::MultiMintVault
         );
+        require!(!state.swap_pending, VaultError::SwapPending);
+        state.check_crank_interval(clock.unix_timestamp)?;
 
-        let state_key = ctx.accounts.state.key();
-        let seeds = &[
-            b"timelock".as_ref(),
-            state_key.as_ref(),
-            &[state.timelock_bump],
-        ];
-        let signer = &[&seeds[..]];
+        let amount_in = ctx.accounts.vault_profit_token_account.amount;
+        // A base-mint vault keeps its principal and may stage profit through
+        // the TWAP tranche; other profit tokens are swapped whole.
+        let max_in = if base_vault {
+            let profit = amount_in.saturating_sub(state.starting_balance_lamports);
+            state.take_tranche(profit)
+        } else {
+            amount_in
+        };
+        require!(max_in > 0, VaultError::NoProfit);
+        let tokens_before = ctx.accounts.distribution.vault_token_account.amount;
 
-        let amount = ctx.accounts.timelock_token_account.amount;
-        let cpi_ctx = CpiContext::new_with_signer(
+        let state_key = state.key();
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[state.vault_bump]];
+        aggregator_swap(
+            &ctx.accounts.aggregator_program.to_account_info(),
+            route_accounts,
+            swap_ix_data,
+            ctx.accounts.distribution.vault.key(),
+            vault_seeds,
+            &ctx.accounts.distribution.state.config,
+        )?;
+
+        ctx.accounts.vault_profit_token_account.reload()?;
+        ctx.accounts.distribution.vault_token_account.reload()?;
+        let amount_spent = amount_in.saturating_sub(ctx.accounts.vault_profit_token_account.amount);
+        let tokens_bought = swap_output(
+            tokens_before,
+            ctx.accounts.distribution.vault_token_account.amount,
+        )?;
+        require!(amount_spent <= max_in, VaultError::ProfitOverspent);
+        let profit = if base_vault {
+            settle_swap_input(
+                &mut ctx.accounts.distribution.state,
+                max_in,
+                max_in - amount_spent,
+                tokens_bought,
+            )?
+        } else {
+            0
+        };
+
+        emit_cpi!(SplProfitSwappedEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.distribution.state.next_event_seq(),
+            profit_mint: ctx.accounts.profit_mint.key(),
+            amount_in: amount_spent,
+        });
+
+        let accounts = ctx.accounts.distribution.accounts(
+            None,
+            ctx.accounts.profit_mint.decimals,
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.timelock_token_account.to_account_info(),
-                to: ctx.accounts.destination_token_account.to_account_info(),
-                authority: ctx.accounts.timelock_authority.to_account_info(),
-            },
-            signer,
+            hook_accounts,
         );
-        token::transfer(cpi_ctx, amount)?;
+        let event = finish_crank(
+            &mut ctx.accounts.distribution.state,
+            &accounts,
+            RecordSink::Account(&mut ctx.accounts.buyback_record),
+            ctx.accounts.buyback_history.as_ref(),
+            ctx.accounts.distribution.lock_tranche.as_mut(),
+            profit,
+            tokens_bought,
+            clock.unix_timestamp,
+        )?;
+        emit_cpi!(event);
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-#[instruction(starting_balance_lamports: u64, burn_address: Pubkey)]
-pub struct Initialize<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub mint: Account<'info, Mint>,
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + VaultState::SIZE,
-        seeds = [b"state", mint.key().as_ref()],
-        bump
-    )]
-    pub state: Account<'info, VaultState>,
-    #[account(
-        mut,
-        seeds = [b"vault", state.key().as_ref()],
-        bump
-    )]
-    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
-    pub vault: UncheckedAccount<'info>,
-    /// CHECK: Timelock PDA authority
-    #[account(
-        seeds = [b"timelock", state.key().as_ref()],
-        bump
-    )]
-    pub timelock_authority: UncheckedAccount<'info>,
-    pub system_program: Program<'info, System>,
-}
+    /// Two-leg aggregator crank, SOL -> `config.intermediate_mint` -> token,
+    /// for tokens whose deepest liquidity is against e.g. USDC. The first
+    /// `first_leg.account_count` remaining accounts are the first route, the
+    /// next `second_leg_account_count` the second, and any after them the
+    /// mint's transfer hook accounts. The first leg must deliver into the
+    /// vault's intermediate ATA and the second may spend no more than that
+    /// leg delivered; any remainder stays in the ATA.
+    pub fn crank_two_hop<'info>(
+        ctx: Context<'_, '_, '_, 'info, CrankTwoHop<'info>>,
+        first_leg: MultiMintRoute,
+        second_leg_data: Vec<u8>,
+        second_leg_account_count: u8,
+    ) -> Result<()> {
+        ctx.accounts.operator_pass.check(
+            &ctx.accounts.distribution.state.config,
+            ctx.accounts.distribution.payer.key(),
+        )?;
+        let clock = Clock::get()?;
+        let (first_route, rest) =
+            split_route_accounts(ctx.remaining_accounts, first_leg.account_count)?;
+        let (second_route, hook_accounts) = split_route_accounts(rest, second_leg_account_count)?;
+        let state_key = ctx.accounts.distribution.state.key();
+        let vault_bump = ctx.accounts.distribution.state.vault_bump;
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[vault_bump]];
 
-#[derive(Accounts)]
+        let max_in_lamports = crank_profit(
+            &mut ctx.accounts.distribution.state,
+            &ctx.accounts.distribution.vault.to_account_info(),
+            clock.unix_timestamp,
+        )?;
+        let wsol_before = ctx.accounts.vault_wsol_ata.amount;
+        let intermediate_before = ctx.accounts.vault_intermediate_account.amount;
+        let tokens_before = ctx.accounts.distribution.vault_token_account.amount;
+
+        wrap_sol(
+            &ctx.accounts.distribution.vault.to_account_info(),
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            vault_seeds,
+            max_in_lamports,
+        )?;
+        aggregator_swap(
+            &ctx.accounts.aggregator_program.to_account_info(),
+            first_route,
+            first_leg.data,
+            ctx.accounts.distribution.vault.key(),
+            vault_seeds,
+            &ctx.accounts.distribution.state.config,
+        )?;
+        ctx.accounts.vault_intermediate_account.reload()?;
+        swap_output(
+            intermediate_before,
+            ctx.accounts.vault_intermediate_account.amount,
+        )?;
+
+        aggregator_swap(
+            &ctx.accounts.aggregator_program.to_account_info(),
+            second_route,
+            second_leg_data,
+            ctx.accounts.distribution.vault.key(),
+            vault_seeds,
+            &ctx.accounts.distribution.state.config,
+        )?;
+        ctx.accounts.vault_intermediate_account.reload()?;
+        require!(
+            ctx.accounts.vault_intermediate_account.amount >= intermediate_before,
+            VaultError::LegOverspent
+        );
+
+        ctx.accounts.vault_wsol_ata.reload()?;
+        ctx.accounts.distribution.vault_token_account.reload()?;
+        let tokens_bought = swap_output(
+            tokens_before,
+            ctx.accounts.distribution.vault_token_account.amount,
+        )?;
+        let profit_lamports = settle_swap_input(
+            &mut ctx.accounts.distribution.state,
+            max_in_lamports,
+            ctx.accounts
+                .vault_wsol_ata
+                .amount
+                .saturating_sub(wsol_before),
+            tokens_bought,
+        )?;
+
+        let accounts = ctx.accounts.distribution.accounts(
+            Some(ctx.accounts.vault_wsol_ata.to_account_info()),
+            token::spl_token::native_mint::DECIMALS,
+            ctx.accounts.token_program.to_account_info(),
+            hook_accounts,
+        );
+        let event = finish_crank(
+            &mut ctx.accounts.distribution.state,
+            &accounts,
+            RecordSink::Account(&mut ctx.accounts.buyback_record),
+            ctx.accounts.buyback_history.as_ref(),
+            ctx.accounts.distribution.lock_tranche.as_mut(),
+            profit_lamports,
+            tokens_bought,
+            clock.unix_timestamp,
+        )?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    /// First half of a split crank: wraps the profit and runs the aggregator
+    /// route, leaving the bought tokens in the vault token account until
+    /// `finalize_distribution` runs in the same or a later transaction.
+    /// `remaining_accounts` is the route alone; the mint's transfer hook
+    /// accounts go to `finalize_distribution`.
+    pub fn prepare_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, PrepareSwap<'info>>,
+        swap_ix_data: Vec<u8>,
+    ) -> Result<()> {
+        ctx.accounts
+            .operator_pass
+            .check(&ctx.accounts.state.config, ctx.accounts.payer.key())?;
+        let clock = Clock::get()?;
+        let state_key = ctx.accounts.state.key();
+        let vault_bump = ctx.accounts.state.vault_bump;
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[vault_bump]];
+
+        let max_in_lamports = crank_profit(
+            &mut ctx.accounts.state,
+            &ctx.accounts.vault.to_account_info(),
+            clock.unix_timestamp,
+        )?;
+        let wsol_before = ctx.accounts.vault_wsol_ata.amount;
+        let tokens_before = ctx.accounts.vault_token_account.amount;
+
+        wrap_sol(
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            vault_seeds,
+            max_in_lamports,
+        )?;
+        aggregator_swap(
+            &ctx.accounts.aggregator_program.to_account_info(),
+            ctx.remaining_accounts,
+            swap_ix_data,
+            ctx.accounts.vault.key(),
+            vault_seeds,
+            &ctx.accounts.state.config,
+        )?;
+
+        ctx.accounts.vault_wsol_ata.reload()?;
+        ctx.accounts.vault_token_account.reload()?;
+        let tokens_bought = swap_output(tokens_before, ctx.accounts.vault_token_account.amount)?;
+        let profit_lamports = settle_swap_input(
+            &mut ctx.accounts.state,
+            max_in_lamports,
+            ctx.accounts
+                .vault_wsol_ata
+                .amount
+                .saturating_sub(wsol_before),
+            tokens_bought,
+        )?;
+
+        let state = &mut ctx.accounts.state;
+        state.swap_pending = true;
+        state.pending_profit_lamports = profit_lamports;
+        state.pending_tokens_bought = tokens_bought;
+        Ok(())
+    }
+
+    /// `remaining_accounts` carries the mint's transfer hook accounts, if any.
+    pub fn finalize_distribution<'info>(
+        ctx: Context<'_, '_, '_, 'info, FinalizeDistribution<'info>>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(
+            ctx.accounts.distribution.state.swap_pending,
+            VaultError::NoSwapPending
+        );
+        require!(
+            ctx.accounts.distribution.state.dca.is_none(),
+            VaultError::SwapPending
+        );
+
+        let accounts = ctx.accounts.distribution.accounts(
+            Some(ctx.accounts.vault_wsol_ata.to_account_info()),
+            token::spl_token::native_mint::DECIMALS,
+            ctx.accounts.token_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        let profit_lamports = ctx.accounts.distribution.state.pending_profit_lamports;
+        let tokens_bought = ctx.accounts.distribution.state.pending_tokens_bought;
+        let event = finish_crank(
+            &mut ctx.accounts.distribution.state,
+            &accounts,
+            RecordSink::Account(&mut ctx.accounts.buyback_record),
+            ctx.accounts.buyback_history.as_ref(),
+            ctx.accounts.distribution.lock_tranche.as_mut(),
+            profit_lamports,
+            tokens_bought,
+            clock.unix_timestamp,
+        )?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    /// First half of a `config.dca` crank: wraps the profit into a
+    /// state-owned WSOL ATA and opens a Jupiter DCA order with it, buying
+    /// the token over `cycles` orders into the vault token account. The vault
+    /// stays `swap_pending` until `settle_dca` runs after the order closes.
+    /// The keeper fronts the DCA account's rent, which Jupiter refunds to the
+    /// state and `settle_dca` sweeps into the vault.
+    pub fn open_dca(ctx: Context<OpenDca>) -> Result<()> {
+        ctx.accounts
+            .operator_pass
+            .check(&ctx.accounts.state.config, ctx.accounts.payer.key())?;
+        let clock = Clock::get()?;
+        let settings = ctx
+            .accounts
+            .state
+            .config
+            .dca
+            .ok_or(VaultError::InvalidConfig)?;
+        let state_key = ctx.accounts.state.key();
+        let vault_seeds: &[&[u8]] = &[
+            b"vault",
+            state_key.as_ref(),
+            &[ctx.accounts.state.vault_bump],
+        ];
+        let seed_mint = ctx.accounts.state.seed_mint;
+        let authority_key = ctx.accounts.state.authority;
+        let state_seeds: &[&[u8]] = &[
+            b"state",
+            seed_mint.as_ref(),
+            authority_key.as_ref(),
+            &[ctx.accounts.state.bump],
+        ];
+
+        let max_in_lamports = crank_profit(
+            &mut ctx.accounts.state,
+            &ctx.accounts.vault.to_account_info(),
+            clock.unix_timestamp,
+        )?;
+        // Jupiter splits the deposit into equal cycles.
+        let cycles = settings.cycles as u64;
+        let in_amount_per_cycle = max_in_lamports / cycles;
+        require!(in_amount_per_cycle > 0, VaultError::NoProfit);
+        let in_lamports = in_amount_per_cycle * cycles;
+        if in_lamports < max_in_lamports
+            && matches!(ctx.accounts.state.config.twap, TwapMode::Split { .. })
+        {
+            let state = &mut ctx.accounts.state;
+            state.twap_budget_lamports = state
+                .twap_budget_lamports
+                .saturating_add(max_in_lamports - in_lamports);
+        }
+        wrap_sol(
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.state_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            vault_seeds,
+            in_lamports,
+        )?;
+
+        let application_idx = ctx.accounts.state.crank_count;
+        let mut data = Vec::with_capacity(45);
+        data.extend_from_slice(&JUPITER_DCA_OPEN_V2);
+        data.extend_from_slice(&application_idx.to_le_bytes());
+        data.extend_from_slice(&in_lamports.to_le_bytes());
+        data.extend_from_slice(&in_amount_per_cycle.to_le_bytes());
+        data.extend_from_slice(&settings.cycle_secs.to_le_bytes());
+        // min_out_amount, max_out_amount, start_at: None
+        data.extend_from_slice(&[0, 0, 0]);
+        let ix = Instruction {
+            program_id: JUPITER_DCA_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(ctx.accounts.dca.key(), false),
+                AccountMeta::new_readonly(state_key, true),
+                AccountMeta::new(ctx.accounts.payer.key(), true),
+                AccountMeta::new_readonly(ctx.accounts.wsol_mint.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.mint.key(), false),
+                AccountMeta::new(ctx.accounts.state_wsol_ata.key(), false),
+                AccountMeta::new(ctx.accounts.dca_in_ata.key(), false),
+                AccountMeta::new(ctx.accounts.dca_out_ata.key(), false),
+                AccountMeta::new_readonly(System::id(), false),
+                AccountMeta::new_readonly(ctx.accounts.token_program.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.associated_token_program.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.dca_event_authority.key(), false),
+                AccountMeta::new_readonly(JUPITER_DCA_PROGRAM_ID, false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.dca.to_account_info(),
+                ctx.accounts.state.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.wsol_mint.to_account_info(),
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.state_wsol_ata.to_account_info(),
+                ctx.accounts.dca_in_ata.to_account_info(),
+                ctx.accounts.dca_out_ata.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.associated_token_program.to_account_info(),
+                ctx.accounts.dca_event_authority.to_account_info(),
+                ctx.accounts.dca_program.to_account_info(),
+            ],
+            &[state_seeds],
+        )?;
+
+        let state = &mut ctx.accounts.state;
+        state.swap_pending = true;
+        state.pending_profit_lamports = in_lamports;
+        state.pending_tokens_bought = 0;
+        state.dca = Some(ctx.accounts.dca.key());
+        state.dca_tokens_before = ctx.accounts.vault_token_account.amount;
+        emit_cpi!(DcaOpenedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            dca: ctx.accounts.dca.key(),
+            in_lamports,
+            cycles: settings.cycles,
+            cycle_secs: settings.cycle_secs,
+        });
+        Ok(())
+    }
+
+    /// Second half of a DCA crank, once Jupiter has closed the order: counts
+    /// the tokens it delivered to the vault token account, returns unspent
+    /// WSOL and the refunded rent to the vault, and leaves the buyback for
+    /// `finalize_distribution`. An order that bought nothing just clears
+    /// `swap_pending`.
+    pub fn settle_dca(ctx: Context<SettleDca>) -> Result<()> {
+        require!(
+            ctx.accounts.state.dca == Some(ctx.accounts.dca.key()),
+            VaultError::NoSwapPending
+        );
+        require!(ctx.accounts.dca.data_is_empty(), VaultError::DcaStillOpen);
+        let unspent_lamports = ctx.accounts.state_wsol_ata.amount;
+        let seed_mint = ctx.accounts.state.seed_mint;
+        let authority_key = ctx.accounts.state.authority;
+        let state_seeds: &[&[u8]] = &[
+            b"state",
+            seed_mint.as_ref(),
+            authority_key.as_ref(),
+            &[ctx.accounts.state.bump],
+        ];
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.state_wsol_ata.to_account_info(),
+                destination: ctx.accounts.vault.to_account_info(),
+                authority: ctx.accounts.state.to_account_info(),
+            },
+            &[state_seeds],
+        ))?;
+        let state_info = ctx.accounts.state.to_account_info();
+        let rent_floor = Rent::get()?.minimum_balance(state_info.data_len());
+        let refunded = state_info.lamports().saturating_sub(rent_floor);
+        if refunded > 0 {
+            state_info.sub_lamports(refunded)?;
+            ctx.accounts.vault.add_lamports(refunded)?;
+        }
+
+        let tokens_bought = ctx
+            .accounts
+            .vault_token_account
+            .amount
+            .saturating_sub(ctx.accounts.state.dca_tokens_before);
+        let state = &mut ctx.accounts.state;
+        let spent_lamports = state
+            .pending_profit_lamports
+            .saturating_sub(unspent_lamports);
+        state.dca = None;
+        state.dca_tokens_before = 0;
+        state.pending_profit_lamports = spent_lamports;
+        state.pending_tokens_bought = tokens_bought;
+        state.swap_pending = tokens_bought > 0;
+        emit_cpi!(DcaSettledEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            dca: ctx.accounts.dca.key(),
+            spent_lamports,
+            tokens_bought,
+        });
+        Ok(())
+    }
+
+    /// Runs the `prepare_swap` half for several vaults in one instruction, so
+    /// a keeper can service small vaults without a full crank each. Per
+    /// vault, `remaining_accounts` holds its state, vault PDA, WSOL ATA and
+    /// token account, followed by `account_count` route accounts. Each vault
+    /// is left `swap_pending` for a `finalize_distribution`, which is cheap
+    /// enough to pack several of into the same transaction. One vault that
+    /// can't crank fails the batch, so keepers should `check_crank` first.
+    pub fn crank_many<'info>(
+        ctx: Context<'_, '_, '_, 'info, CrankMany<'info>>,
+        routes: Vec<MultiMintRoute>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let mut offset = 0usize;
+        for route in routes {
+            let end = offset + CRANK_MANY_VAULT_ACCOUNTS + route.account_count as usize;
+            require!(
+                end <= ctx.remaining_accounts.len(),
+                VaultError::InvalidRoute
+            );
+            let group = &ctx.remaining_accounts[offset..end];
+            offset = end;
+
+            let mut state = Account::<VaultState>::try_from(&group[0])?;
+            ctx.accounts
+                .operator_pass
+                .check(&state.config, ctx.accounts.payer.key())?;
+            let state_key = state.key();
+            let vault = &group[1];
+            let vault_bump = state.vault_bump;
+            let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[vault_bump]];
+            require_keys_eq!(
+                Pubkey::create_program_address(vault_seeds, ctx.program_id)
+                    .map_err(|_| VaultError::InvalidRoute)?,
+                vault.key(),
+                VaultError::InvalidRoute
+            );
+            let wsol_before = {
+                let wsol = Account::<token::TokenAccount>::try_from(&group[2])?;
+                require!(
+                    wsol.mint == token::spl_token::native_mint::ID && wsol.owner == vault.key(),
+                    VaultError::InvalidRoute
+                );
+                wsol.amount
+            };
+            let tokens_before = {
+                let vault_token = InterfaceAccount::<TokenAccount>::try_from(&group[3])?;
+                require!(
+                    vault_token.mint == state.mint && vault_token.owner == state_key,
+                    VaultError::InvalidRoute
+                );
+                vault_token.amount
+            };
+
+            let max_in_lamports = crank_profit(&mut state, vault, clock.unix_timestamp)?;
+            wrap_sol(
+                vault,
+                &group[2],
+                &ctx.accounts.token_program.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                vault_seeds,
+                max_in_lamports,
+            )?;
+            aggregator_swap(
+                &ctx.accounts.aggregator_program.to_account_info(),
+                &group[CRANK_MANY_VAULT_ACCOUNTS..],
+                route.data,
+                vault.key(),
+                vault_seeds,
+                &state.config,
+            )?;
+
+            let tokens_bought = swap_output(tokens_before, token::accessor::amount(&group[3])?)?;
+            let profit_lamports = settle_swap_input(
+                &mut state,
+                max_in_lamports,
+                token::accessor::amount(&group[2])?.saturating_sub(wsol_before),
+                tokens_bought,
+            )?;
+            state.swap_pending = true;
+            state.pending_profit_lamports = profit_lamports;
+            state.pending_tokens_bought = tokens_bought;
+            state.exit(ctx.program_id)?;
+        }
+        require!(
+            offset == ctx.remaining_accounts.len(),
+            VaultError::InvalidRoute
+        );
+        Ok(())
+    }
+
+    /// Applies the distribution split to tokens left undistributed in the
+    /// vault token account (e.g. a crank whose distribution never landed).
+    /// Skips the crank interval and pays no keeper reward; locked tokens get
+    /// their own `LockTranche`.
+    /// `remaining_accounts` carries the mint's transfer hook accounts, if any.
+    pub fn distribute_pending<'info>(
+        ctx: Context<'_, '_, '_, 'info, DistributePending<'info>>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(!ctx.accounts.distribution.state.paused, VaultError::Paused);
+        require!(
+            !ctx.accounts.distribution.state.swap_pending,
+            VaultError::SwapPending
+        );
+
+        // Tokens already bought earn no keeper reward and get no oracle check.
+        let accounts = DistributionAccounts {
+            keeper_token_account: None,
+            price_oracle: None,
+            quote_oracle: None,
+            memo_program: None,
+            ..ctx.accounts.distribution.accounts(
+                None,
+                token::spl_token::native_mint::DECIMALS,
+                ctx.accounts
+                    .distribution
+                    .mint_token_program
+                    .to_account_info(),
+                ctx.remaining_accounts,
+            )
+        };
+        let distribution = distribute(
+            &mut ctx.accounts.distribution.state,
+            &accounts,
+            ctx.accounts.distribution.vault_token_account.amount,
+            clock.unix_timestamp,
+        )?;
+        open_lock_tranche(
+            &mut ctx.accounts.distribution.state,
+            ctx.accounts.distribution.lock_tranche.as_mut(),
+            distribution.lock_amount,
+            accounts.keeper,
+            clock.unix_timestamp,
+        )?;
+
+        emit_cpi!(BuybackEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.distribution.state.next_event_seq(),
+            vault_id: ctx.accounts.distribution.state.vault_id,
+            crank_index: None,
+            profit_lamports: 0,
+            burn_amount: distribution.burn_amount,
+            lock_amount: distribution.lock_amount,
+            liquidity_amount: distribution.liquidity_amount,
+            stake_rewards_amount: distribution.stake_rewards_amount,
+            treasury_amount: distribution.treasury_amount,
+            retained_amount: distribution.retained_amount,
+            holder_rewards_amount: distribution.holder_rewards_amount,
+            fee_stake_amount: distribution.fee_stake_amount,
+            keeper_reward_amount: 0,
+            keeper: accounts.keeper,
+            burn_address: accounts.burn_token_account.key(),
+            timelock_account: accounts.timelock_token_account.key(),
+            execution_price: 0,
+            oracle_price: None,
+            burned_bps_of_supply: ctx.accounts.distribution.state.burned_bps_of_supply,
+        });
+        Ok(())
+    }
+
+    /// Single-instruction crank that swaps directly against the configured
+    /// Raydium CPMM pool instead of routing through an aggregator.
+    /// `remaining_accounts` carries the mint's transfer hook accounts, if any.
+    pub fn crank_raydium<'info>(
+        ctx: Context<'_, '_, '_, 'info, CrankRaydium<'info>>,
+        minimum_amount_out: u64,
+    ) -> Result<()> {
+        ctx.accounts.operator_pass.check(
+            &ctx.accounts.distribution.state.config,
+            ctx.accounts.distribution.payer.key(),
+        )?;
+        let clock = Clock::get()?;
+        require!(
+            ctx.accounts.distribution.state.config.raydium_pool
+                == Some(ctx.accounts.raydium_pool_state.key()),
+            VaultError::PoolNotConfigured
+        );
+        let state_key = ctx.accounts.distribution.state.key();
+        let vault_bump = ctx.accounts.distribution.state.vault_bump;
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[vault_bump]];
+
+        let profit_lamports = crank_profit(
+            &mut ctx.accounts.distribution.state,
+            &ctx.accounts.distribution.vault.to_account_info(),
+            clock.unix_timestamp,
+        )?;
+        let tokens_before = ctx.accounts.distribution.vault_token_account.amount;
+
+        wrap_sol(
+            &ctx.accounts.distribution.vault.to_account_info(),
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            vault_seeds,
+            profit_lamports,
+        )?;
+        raydium_swap(
+            ctx.accounts,
+            profit_lamports,
+            minimum_amount_out,
+            vault_seeds,
+        )?;
+
+        ctx.accounts.distribution.vault_token_account.reload()?;
+        let tokens_bought = swap_output(
+            tokens_before,
+            ctx.accounts.distribution.vault_token_account.amount,
+        )?;
+        let accounts = ctx.accounts.distribution.accounts(
+            Some(ctx.accounts.vault_wsol_ata.to_account_info()),
+            token::spl_token::native_mint::DECIMALS,
+            ctx.accounts.token_program.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        let event = finish_crank(
+            &mut ctx.accounts.distribution.state,
+            &accounts,
+            RecordSink::Account(&mut ctx.accounts.buyback_record),
+            ctx.accounts.buyback_history.as_ref(),
+            ctx.accounts.distribution.lock_tranche.as_mut(),
+            profit_lamports,
+            tokens_bought,
+            clock.unix_timestamp,
+        )?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    /// Single-instruction crank that swaps directly against the configured
+    /// Orca Whirlpool. The keeper supplies the three tick arrays the swap
+    /// may cross.
+    pub fn crank_whirlpool(ctx: Context<CrankWhirlpool>, minimum_amount_out: u64) -> Result<()> {
+        ctx.accounts.operator_pass.check(
+            &ctx.accounts.distribution.state.config,
+            ctx.accounts.distribution.payer.key(),
+        )?;
+        let clock = Clock::get()?;
+        require!(
+            ctx.accounts.distribution.state.config.whirlpool == Some(ctx.accounts.whirlpool.key()),
+            VaultError::PoolNotConfigured
+        );
+        let state_key = ctx.accounts.distribution.state.key();
+        let vault_bump = ctx.accounts.distribution.state.vault_bump;
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[vault_bump]];
+
+        let profit_lamports = crank_profit(
+            &mut ctx.accounts.distribution.state,
+            &ctx.accounts.distribution.vault.to_account_info(),
+            clock.unix_timestamp,
+        )?;
+        let tokens_before = ctx.accounts.distribution.vault_token_account.amount;
+
+        wrap_sol(
+            &ctx.accounts.distribution.vault.to_account_info(),
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            vault_seeds,
+            profit_lamports,
+        )?;
+        whirlpool_swap(
+            ctx.accounts,
+            profit_lamports,
+            minimum_amount_out,
+            vault_seeds,
+        )?;
+
+        ctx.accounts.distribution.vault_token_account.reload()?;
+        let tokens_bought = swap_output(
+            tokens_before,
+            ctx.accounts.distribution.vault_token_account.amount,
+        )?;
+        let accounts = ctx.accounts.distribution.accounts(
+            Some(ctx.accounts.vault_wsol_ata.to_account_info()),
+            token::spl_token::native_mint::DECIMALS,
+            ctx.accounts.token_program.to_account_info(),
+            &[],
+        );
+        let event = finish_crank(
+            &mut ctx.accounts.distribution.state,
+            &accounts,
+            RecordSink::Account(&mut ctx.accounts.buyback_record),
+            ctx.accounts.buyback_history.as_ref(),
+            ctx.accounts.distribution.lock_tranche.as_mut(),
+            profit_lamports,
+            tokens_bought,
+            clock.unix_timestamp,
+        )?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    /// Creates the vault PDA's OpenBook v2 open orders account (and the
+    /// indexer that numbers it) on `config.openbook_bids.market`, which
+    /// `crank_openbook` bids from.
+    pub fn init_openbook_orders(ctx: Context<InitOpenBookOrders>) -> Result<()> {
+        let state_key = ctx.accounts.state.key();
+        let vault_seeds: &[&[u8]] = &[
+            b"vault",
+            state_key.as_ref(),
+            &[ctx.accounts.state.vault_bump],
+        ];
+        let payer = ctx.accounts.payer.key();
+        let vault = ctx.accounts.vault.key();
+        let indexer = ctx.accounts.open_orders_indexer.key();
+
+        let ix = Instruction {
+            program_id: OPENBOOK_V2_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(vault, true),
+                AccountMeta::new(indexer, false),
+                AccountMeta::new_readonly(System::id(), false),
+            ],
+            data: OPENBOOK_CREATE_OPEN_ORDERS_INDEXER.to_vec(),
+        };
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.open_orders_indexer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[vault_seeds],
+        )?;
+
+        let name = "burnflip";
+        let mut data = OPENBOOK_CREATE_OPEN_ORDERS_ACCOUNT.to_vec();
+        data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        data.extend_from_slice(name.as_bytes());
+        // No delegate; an absent optional account is the OpenBook program id.
+        let ix = Instruction {
+            program_id: OPENBOOK_V2_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(vault, true),
+                AccountMeta::new_readonly(OPENBOOK_V2_PROGRAM_ID, false),
+                AccountMeta::new(indexer, false),
+                AccountMeta::new(ctx.accounts.open_orders_account.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.market.key(), false),
+                AccountMeta::new_readonly(System::id(), false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.openbook_program.to_account_info(),
+                ctx.accounts.open_orders_indexer.to_account_info(),
+                ctx.accounts.open_orders_account.to_account_info(),
+                ctx.accounts.market.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[vault_seeds],
+        )?;
+        Ok(())
+    }
+
+    /// Limit-order crank for `config.openbook_bids`. Wraps this crank's
+    /// profit, cancels the vault's resting bid and settles the market: fills
+    /// are distributed and recorded like a swap's output, priced at the WSOL
+    /// the bid gave up. Everything left in the WSOL ATA is then bid again,
+    /// post-only, `discount_bps` under the SOL price implied by
+    /// `price_oracle`. A crank that settles no fills takes no
+    /// `buyback_record`.
+    pub fn crank_openbook(ctx: Context<CrankOpenBook>) -> Result<()> {
+        ctx.accounts.operator_pass.check(
+            &ctx.accounts.distribution.state.config,
+            ctx.accounts.distribution.payer.key(),
+        )?;
+        let clock = Clock::get()?;
+        let bids = ctx
+            .accounts
+            .distribution
+            .state
+            .config
+            .openbook_bids
+            .ok_or(VaultError::PoolNotConfigured)?;
+        require_keys_eq!(
+            ctx.accounts.market.key(),
+            bids.market,
+            VaultError::PoolNotConfigured
+        );
+        let state_key = ctx.accounts.distribution.state.key();
+        let vault_bump = ctx.accounts.distribution.state.vault_bump;
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[vault_bump]];
+
+        let profit_lamports = crank_profit(
+            &mut ctx.accounts.distribution.state,
+            &ctx.accounts.distribution.vault.to_account_info(),
+            clock.unix_timestamp,
+        )?;
+        wrap_sol(
+            &ctx.accounts.distribution.vault.to_account_info(),
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            vault_seeds,
+            profit_lamports,
+        )?;
+        ctx.accounts.vault_wsol_ata.reload()?;
+        let wsol_before = ctx.accounts.vault_wsol_ata.amount;
+        let tokens_before = ctx.accounts.distribution.vault_token_account.amount;
+
+        openbook_cancel_and_settle(ctx.accounts, vault_seeds)?;
+        ctx.accounts.vault_wsol_ata.reload()?;
+        ctx.accounts.distribution.vault_token_account.reload()?;
+        let returned_lamports = ctx
+            .accounts
+            .vault_wsol_ata
+            .amount
+            .saturating_sub(wsol_before);
+        let tokens_bought = ctx
+            .accounts
+            .distribution
+            .vault_token_account
+            .amount
+            .saturating_sub(tokens_before);
+        let spent_lamports = ctx
+            .accounts
+            .distribution
+            .state
+            .openbook_bid_lamports
+            .saturating_sub(returned_lamports);
+        ctx.accounts.distribution.state.openbook_bid_lamports = 0;
+
+        if tokens_bought > 0 {
+            let accounts = ctx.accounts.distribution.accounts(
+                Some(ctx.accounts.vault_wsol_ata.to_account_info()),
+                token::spl_token::native_mint::DECIMALS,
+                ctx.accounts.token_program.to_account_info(),
+                &[],
+            );
+            let record = ctx
+                .accounts
+                .buyback_record
+                .as_mut()
+                .ok_or(VaultError::MissingBuybackRecord)?;
+            let event = finish_crank(
+                &mut ctx.accounts.distribution.state,
+                &accounts,
+                RecordSink::Account(record),
+                ctx.accounts.buyback_history.as_ref(),
+                ctx.accounts.distribution.lock_tranche.as_mut(),
+                spent_lamports,
+                tokens_bought,
+                clock.unix_timestamp,
+            )?;
+            emit_cpi!(event);
+        }
+
+        let price_lots = openbook_bid_price_lots(ctx.accounts, &bids, clock.unix_timestamp)?;
+        let (quote_lot_size, _) = openbook_lot_sizes(&ctx.accounts.market)?;
+        let bid_lamports = ctx.accounts.vault_wsol_ata.amount;
+        let max_quote_lots = i64::try_from(bid_lamports / quote_lot_size as u64)
+            .map_err(|_| error!(VaultError::MathOverflow))?;
+        require!(max_quote_lots > 0, VaultError::NoProfit);
+        openbook_place_bid(
+            ctx.accounts,
+            price_lots,
+            max_quote_lots,
+            ctx.accounts.distribution.state.crank_count,
+            vault_seeds,
+        )?;
+        ctx.accounts.vault_wsol_ata.reload()?;
+        let bid_lamports = bid_lamports.saturating_sub(ctx.accounts.vault_wsol_ata.amount);
+
+        let state = &mut ctx.accounts.distribution.state;
+        state.openbook_bid_lamports = bid_lamports;
+        emit_cpi!(BidPlacedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            market: bids.market,
+            price_lots,
+            bid_lamports,
+            filled_lamports: spent_lamports,
+            tokens_bought,
+        });
+        Ok(())
+    }
+
+    /// Single-instruction crank against the configured pump.fun AMM pool, for
+    /// graduated tokens Jupiter has not indexed yet. `base_amount_out` comes
+    /// from the keeper's off-chain quote; the whole profit is the input cap
+    /// and any unspent WSOL returns to the vault when the ATA is closed.
+    /// The first `route_account_count` remaining accounts are pump's trailing
+    /// `buy` accounts; any after them are the mint's transfer hook accounts.
+    pub fn crank_pump_amm<'info>(
+        ctx: Context<'_, '_, '_, 'info, CrankPumpAmm<'info>>,
+        base_amount_out: u64,
+        route_account_count: u8,
+    ) -> Result<()> {
+        ctx.accounts.operator_pass.check(
+            &ctx.accounts.distribution.state.config,
+            ctx.accounts.distribution.payer.key(),
+        )?;
+        let clock = Clock::get()?;
+        let (route_accounts, hook_accounts) =
+            split_route_accounts(ctx.remaining_accounts, route_account_count)?;
+        require!(
+            ctx.accounts.distribution.state.config.pump_pool == Some(ctx.accounts.pump_pool.key()),
+            VaultError::PoolNotConfigured
+        );
+        let state_key = ctx.accounts.distribution.state.key();
+        let vault_bump = ctx.accounts.distribution.state.vault_bump;
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[vault_bump]];
+
+        let profit_lamports = crank_profit(
+            &mut ctx.accounts.distribution.state,
+            &ctx.accounts.distribution.vault.to_account_info(),
+            clock.unix_timestamp,
+        )?;
+        let tokens_before = ctx.accounts.distribution.vault_token_account.amount;
+
+        wrap_sol(
+            &ctx.accounts.distribution.vault.to_account_info(),
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            vault_seeds,
+            profit_lamports,
+        )?;
+        pump_amm_swap(
+            ctx.accounts,
+            route_accounts,
+            base_amount_out,
+            profit_lamports,
+            vault_seeds,
+        )?;
+
+        ctx.accounts.distribution.vault_token_account.reload()?;
+        let tokens_bought = swap_output(
+            tokens_before,
+            ctx.accounts.distribution.vault_token_account.amount,
+        )?;
+        let accounts = ctx.accounts.distribution.accounts(
+            Some(ctx.accounts.vault_wsol_ata.to_account_info()),
+            token::spl_token::native_mint::DECIMALS,
+            ctx.accounts.token_program.to_account_info(),
+            hook_accounts,
+        );
+        let event = finish_crank(
+            &mut ctx.accounts.distribution.state,
+            &accounts,
+            RecordSink::Account(&mut ctx.accounts.buyback_record),
+            ctx.accounts.buyback_history.as_ref(),
+            ctx.accounts.distribution.lock_tranche.as_mut(),
+            profit_lamports,
+            tokens_bought,
+            clock.unix_timestamp,
+        )?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    /// Pairs the reserved SOL and tokens from the `AddLiquidity` strategy in
+    /// the configured Raydium CPMM pool and sends the LP tokens to the burn
+    /// address. Deposit sizing comes from the keeper's view of the pool, but
+    /// the maximums are capped by what the vault has reserved.
+    pub fn add_liquidity(
+        ctx: Context<AddLiquidity>,
+        lp_token_amount: u64,
+        maximum_sol_amount: u64,
+        maximum_token_amount: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.state.config.raydium_pool == Some(ctx.accounts.raydium_pool_state.key()),
+            VaultError::PoolNotConfigured
+        );
+        let state = &ctx.accounts.state;
+        require!(
+            maximum_sol_amount > 0 && maximum_sol_amount <= state.liquidity_reserve_lamports,
+            VaultError::InsufficientLiquidityReserve
+        );
+        require!(
+            maximum_token_amount > 0 && maximum_token_amount <= state.liquidity_reserve_tokens,
+            VaultError::InsufficientLiquidityReserve
+        );
+
+        let state_key = state.key();
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[state.vault_bump]];
+        let seed_mint = state.seed_mint;
+        let authority_key = state.authority;
+        let state_seeds: &[&[u8]] = &[
+            b"state",
+            seed_mint.as_ref(),
+            authority_key.as_ref(),
+            &[state.bump],
+        ];
+
+        // The CPMM owner must control both input accounts, so the SOL leg is
+        // wrapped into a state-owned WSOL ATA rather than the vault's.
+        wrap_sol(
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.state_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            vault_seeds,
+            maximum_sol_amount,
+        )?;
+
+        let tokens_before = ctx.accounts.vault_token_account.amount;
+        raydium_deposit(
+            ctx.accounts,
+            lp_token_amount,
+            maximum_sol_amount,
+            maximum_token_amount,
+            state_seeds,
+        )?;
+        ctx.accounts.vault_token_account.reload()?;
+        ctx.accounts.state_wsol_ata.reload()?;
+        let token_amount = tokens_before - ctx.accounts.vault_token_account.amount;
+        let sol_amount = maximum_sol_amount - ctx.accounts.state_wsol_ata.amount;
+
+        // Unused WSOL and the ATA rent go back to the vault.
+        let cpi_close = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.state_wsol_ata.to_account_info(),
+                destination: ctx.accounts.vault.to_account_info(),
+                authority: ctx.accounts.state.to_account_info(),
+            },
+            &[state_seeds],
+        );
+        token_interface::close_account(cpi_close)?;
+
+        let state = &mut ctx.accounts.state;
+        state.liquidity_reserve_lamports -= sol_amount;
+        state.liquidity_reserve_tokens -= token_amount;
+
+        emit_cpi!(LiquidityAddedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            pool: ctx.accounts.raydium_pool_state.key(),
+            sol_amount,
+            token_amount,
+            lp_burned: lp_token_amount,
+            burn_lp_account: ctx.accounts.burn_lp_token_account.key(),
+        });
+        Ok(())
+    }
+
+    /// Creates the Raydium CPMM pool for a token that has no liquidity yet,
+    /// seeded with `sol_amount` of vault profit and `token_amount` of
+    /// retained tokens, and burns the LP. The initial amounts set the
+    /// launch price. The vault PDA is the pool creator, so the pool fee and
+    /// pool rent also come out of profit. The new pool becomes
+    /// `config.raydium_pool` for `crank_raydium` and `add_liquidity`.
+    pub fn bootstrap_raydium_pool(
+        ctx: Context<BootstrapRaydiumPool>,
+        sol_amount: u64,
+        token_amount: u64,
+        open_time: u64,
+    ) -> Result<()> {
+        let state = &ctx.accounts.state;
+        require!(
+            state.config.raydium_pool.is_none(),
+            VaultError::PoolAlreadyConfigured
+        );
+        require!(
+            token_amount > 0 && token_amount <= state.retained_tokens,
+            VaultError::InsufficientRetainedTokens
+        );
+        let vault = ctx.accounts.vault.to_account_info();
+        let floor = state.reserved_lamports()?;
+        require!(
+            sol_amount > 0 && vault.lamports().saturating_sub(floor) > sol_amount,
+            VaultError::NoProfit
+        );
+
+        let state_key = state.key();
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[state.vault_bump]];
+        let seed_mint = state.seed_mint;
+        let authority_key = state.authority;
+        let state_seeds: &[&[u8]] = &[
+            b"state",
+            seed_mint.as_ref(),
+            authority_key.as_ref(),
+            &[state.bump],
+        ];
+
+        // The creator signs, pays and owns both inputs, so the token leg
+        // moves into a vault-owned ATA next to the vault's WSOL ATA.
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.mint_token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.vault_token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.vault_mint_ata.to_account_info(),
+                authority: ctx.accounts.state.to_account_info(),
+            },
+            &[state_seeds],
+        );
+        token_interface::transfer_checked(cpi_ctx, token_amount, ctx.accounts.mint.decimals)?;
+        wrap_sol(
+            &vault,
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            vault_seeds,
+            sol_amount,
+        )?;
+
+        raydium_initialize(
+            ctx.accounts,
+            sol_amount,
+            token_amount,
+            open_time,
+            vault_seeds,
+        )?;
+
+        let lp_amount = {
+            let data = ctx.accounts.vault_lp_token_account.try_borrow_data()?;
+            TokenAccount::try_deserialize(&mut &data[..])?.amount
+        };
+        let cpi_burn = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                from: ctx.accounts.vault_lp_token_account.to_account_info(),
+                authority: vault.clone(),
+            },
+            &[vault_seeds],
+        );
+        token_interface::burn(cpi_burn, lp_amount)?;
+        for (account, token_program) in [
+            (
+                ctx.accounts.vault_lp_token_account.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+            ),
+            (
+                ctx.accounts.vault_mint_ata.to_account_info(),
+                ctx.accounts.mint_token_program.to_account_info(),
+            ),
+            (
+                ctx.accounts.vault_wsol_ata.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+            ),
+        ] {
+            let cpi_close = CpiContext::new_with_signer(
+                token_program,
+                CloseAccount {
+                    account,
+                    destination: vault.clone(),
+                    authority: vault.clone(),
+                },
+                &[vault_seeds],
+            );
+            token_interface::close_account(cpi_close)?;
+        }
+
+        let state = &mut ctx.accounts.state;
+        require!(
+            vault.lamports() >= state.reserved_lamports()?,
+            VaultError::NoProfit
+        );
+        state.retained_tokens -= token_amount;
+        state.config.raydium_pool = Some(ctx.accounts.raydium_pool_state.key());
+
+        emit_cpi!(PoolBootstrappedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            pool: ctx.accounts.raydium_pool_state.key(),
+            lp_mint: ctx.accounts.lp_mint.key(),
+            sol_amount,
+            token_amount,
+            lp_burned: lp_amount,
+        });
+        Ok(())
+    }
+
+    /// Claims pump.fun creator fees accrued for the vault PDA (set as the
+    /// coin's creator) straight into the vault, where they count as profit.
+    pub fn harvest_pump_fees(ctx: Context<HarvestPumpFees>) -> Result<()> {
+        let state_key = ctx.accounts.state.key();
+        let vault_seeds: &[&[u8]] = &[
+            b"vault",
+            state_key.as_ref(),
+            &[ctx.accounts.state.vault_bump],
+        ];
+        let vault = ctx.accounts.vault.to_account_info();
+        let before = vault.lamports();
+
+        let ix = Instruction {
+            program_id: PUMP_FUN_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(vault.key(), true),
+                AccountMeta::new(ctx.accounts.pump_creator_vault.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.system_program.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.pump_event_authority.key(), false),
+                AccountMeta::new_readonly(PUMP_FUN_PROGRAM_ID, false),
+            ],
+            data: PUMP_FUN_COLLECT_CREATOR_FEE.to_vec(),
+        };
+        invoke_signed(
+            &ix,
+            &[
+                vault.clone(),
+                ctx.accounts.pump_creator_vault.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.pump_event_authority.to_account_info(),
+                ctx.accounts.pump_program.to_account_info(),
+            ],
+            &[vault_seeds],
+        )?;
+
+        let lamports = vault.lamports().saturating_sub(before);
+        emit_cpi!(FeesHarvestedEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            source: FeeSource::PumpFun,
+            lamports,
+            token_amount: 0,
+        });
+        Ok(())
+    }
+
+    /// Collects trading fees from the vault-owned Raydium CLMM position in
+    /// `config.raydium_position` by decreasing zero liquidity. The SOL side
+    /// is unwrapped into the vault as profit; the token side lands in the
+    /// vault token account and is distributed by the next crank.
+    pub fn harvest_raydium_fees(ctx: Context<HarvestRaydiumFees>) -> Result<()> {
+        require!(
+            ctx.accounts.state.config.raydium_position
+                == Some(ctx.accounts.raydium_personal_position.key()),
+            VaultError::PoolNotConfigured
+        );
+        let state_key = ctx.accounts.state.key();
+        let vault_seeds: &[&[u8]] = &[
+            b"vault",
+            state_key.as_ref(),
+            &[ctx.accounts.state.vault_bump],
+        ];
+        let wsol_before = ctx.accounts.vault_wsol_ata.amount;
+        let tokens_before = ctx.accounts.vault_token_account.amount;
+
+        let sol_is_token_0 = ctx.accounts.wsol_mint.key() < ctx.accounts.mint.key();
+        let (recipient_0, recipient_1, mint_0, mint_1) = if sol_is_token_0 {
+            (
+                ctx.accounts.vault_wsol_ata.to_account_info(),
+                ctx.accounts.vault_token_account.to_account_info(),
+                ctx.accounts.wsol_mint.to_account_info(),
+                ctx.accounts.mint.to_account_info(),
+            )
+        } else {
+            (
+                ctx.accounts.vault_token_account.to_account_info(),
+                ctx.accounts.vault_wsol_ata.to_account_info(),
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.wsol_mint.to_account_info(),
+            )
+        };
+
+        let mut data = Vec::with_capacity(40);
+        data.extend_from_slice(&RAYDIUM_CLMM_DECREASE_LIQUIDITY_V2);
+        data.extend_from_slice(&0u128.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+
+        let a = &ctx.accounts;
+        let ix = Instruction {
+            program_id: RAYDIUM_CLMM_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(a.vault.key(), true),
+                AccountMeta::new_readonly(a.position_nft_account.key(), false),
+                AccountMeta::new(a.raydium_personal_position.key(), false),
+                AccountMeta::new(a.raydium_pool_state.key(), false),
+                AccountMeta::new(a.raydium_protocol_position.key(), false),
+                AccountMeta::new(a.raydium_token_vault_0.key(), false),
+                AccountMeta::new(a.raydium_token_vault_1.key(), false),
+                AccountMeta::new(a.tick_array_lower.key(), false),
+                AccountMeta::new(a.tick_array_upper.key(), false),
+                AccountMeta::new(recipient_0.key(), false),
+                AccountMeta::new(recipient_1.key(), false),
+                AccountMeta::new_readonly(a.token_program.key(), false),
+                AccountMeta::new_readonly(a.token_2022_program.key(), false),
+                AccountMeta::new_readonly(a.memo_program.key(), false),
+                AccountMeta::new_readonly(mint_0.key(), false),
+                AccountMeta::new_readonly(mint_1.key(), false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &ix,
+            &[
+                a.vault.to_account_info(),
+                a.position_nft_account.to_account_info(),
+                a.raydium_personal_position.to_account_info(),
+                a.raydium_pool_state.to_account_info(),
+                a.raydium_protocol_position.to_account_info(),
+                a.raydium_token_vault_0.to_account_info(),
+                a.raydium_token_vault_1.to_account_info(),
+                a.tick_array_lower.to_account_info(),
+                a.tick_array_upper.to_account_info(),
+                recipient_0,
+                recipient_1,
+                a.token_program.to_account_info(),
+                a.token_2022_program.to_account_info(),
+                a.memo_program.to_account_info(),
+                mint_0,
+                mint_1,
+            ],
+            &[vault_seeds],
+        )?;
+
+        ctx.accounts.vault_wsol_ata.reload()?;
+        ctx.accounts.vault_token_account.reload()?;
+        let lamports = ctx
+            .accounts
+            .vault_wsol_ata
+            .amount
+            .saturating_sub(wsol_before);
+        let token_amount = ctx
+            .accounts
+            .vault_token_account
+            .amount
+            .saturating_sub(tokens_before);
+        close_vault_wsol(
+            &ctx.accounts.state,
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+
+        emit_cpi!(FeesHarvestedEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            source: FeeSource::RaydiumClmm,
+            lamports,
+            token_amount,
+        });
+        Ok(())
+    }
+
+    /// Claims swap fees from the vault-owned Meteora DLMM position in
+    /// `config.meteora_position`. As with Raydium, the SOL side becomes vault
+    /// profit and the token side waits in the vault token account.
+    /// `sol_is_token_x` gives the pair's orientation; DLMM rejects a mismatch.
+    pub fn harvest_meteora_fees(
+        ctx: Context<HarvestMeteoraFees>,
+        sol_is_token_x: bool,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.state.config.meteora_position == Some(ctx.accounts.meteora_position.key()),
+            VaultError::PoolNotConfigured
+        );
+        let state_key = ctx.accounts.state.key();
+        let vault_seeds: &[&[u8]] = &[
+            b"vault",
+            state_key.as_ref(),
+            &[ctx.accounts.state.vault_bump],
+        ];
+        let wsol_before = ctx.accounts.vault_wsol_ata.amount;
+        let tokens_before = ctx.accounts.vault_token_account.amount;
+
+        let (user_token_x, user_token_y, mint_x, mint_y) = if sol_is_token_x {
+            (
+                ctx.accounts.vault_wsol_ata.to_account_info(),
+                ctx.accounts.vault_token_account.to_account_info(),
+                ctx.accounts.wsol_mint.to_account_info(),
+                ctx.accounts.mint.to_account_info(),
+            )
+        } else {
+            (
+                ctx.accounts.vault_token_account.to_account_info(),
+                ctx.accounts.vault_wsol_ata.to_account_info(),
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.wsol_mint.to_account_info(),
+            )
+        };
+
+        let a = &ctx.accounts;
+        let ix = Instruction {
+            program_id: METEORA_DLMM_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(a.meteora_lb_pair.key(), false),
+                AccountMeta::new(a.meteora_position.key(), false),
+                AccountMeta::new(a.bin_array_lower.key(), false),
+                AccountMeta::new(a.bin_array_upper.key(), false),
+                AccountMeta::new_readonly(a.vault.key(), true),
+                AccountMeta::new(a.meteora_reserve_x.key(), false),
+                AccountMeta::new(a.meteora_reserve_y.key(), false),
+                AccountMeta::new(user_token_x.key(), false),
+                AccountMeta::new(user_token_y.key(), false),
+                AccountMeta::new_readonly(mint_x.key(), false),
+                AccountMeta::new_readonly(mint_y.key(), false),
+                AccountMeta::new_readonly(a.token_program.key(), false),
+                AccountMeta::new_readonly(a.meteora_event_authority.key(), false),
+                AccountMeta::new_readonly(METEORA_DLMM_PROGRAM_ID, false),
+            ],
+            data: METEORA_DLMM_CLAIM_FEE.to_vec(),
+        };
+        invoke_signed(
+            &ix,
+            &[
+                a.meteora_lb_pair.to_account_info(),
+                a.meteora_position.to_account_info(),
+                a.bin_array_lower.to_account_info(),
+                a.bin_array_upper.to_account_info(),
+                a.vault.to_account_info(),
+                a.meteora_reserve_x.to_account_info(),
+                a.meteora_reserve_y.to_account_info(),
+                user_token_x,
+                user_token_y,
+                mint_x,
+                mint_y,
+                a.token_program.to_account_info(),
+                a.meteora_event_authority.to_account_info(),
+                a.meteora_program.to_account_info(),
+            ],
+            &[vault_seeds],
+        )?;
+
+        ctx.accounts.vault_wsol_ata.reload()?;
+        ctx.accounts.vault_token_account.reload()?;
+        let lamports = ctx
+            .accounts
+            .vault_wsol_ata
+            .amount
+            .saturating_sub(wsol_before);
+        let token_amount = ctx
+            .accounts
+            .vault_token_account
+            .amount
+            .saturating_sub(tokens_before);
+        close_vault_wsol(
+            &ctx.accounts.state,
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+
+        emit_cpi!(FeesHarvestedEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            source: FeeSource::MeteoraDlmm,
+            lamports,
+            token_amount,
+        });
+        Ok(())
+    }
+
+    /// Opens a state-owned position in `config.whirlpool` over the given tick
+    /// range and records it as `config.whirlpool_position`. The position is
+    /// empty until `increase_whirlpool_liquidity` funds it.
+    pub fn open_whirlpool_position(
+        ctx: Context<OpenWhirlpoolPosition>,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+    ) -> Result<()> {
+        let config = &ctx.accounts.state.config;
+        require!(
+            config.whirlpool == Some(ctx.accounts.whirlpool.key()),
+            VaultError::PoolNotConfigured
+        );
+        require!(
+            config.whirlpool_position.is_none(),
+            VaultError::PositionAlreadyOpen
+        );
+
+        let mut data = Vec::with_capacity(17);
+        data.extend_from_slice(&WHIRLPOOL_OPEN_POSITION);
+        data.push(ctx.bumps.position);
+        data.extend_from_slice(&tick_lower_index.to_le_bytes());
+        data.extend_from_slice(&tick_upper_index.to_le_bytes());
+
+        let a = &ctx.accounts;
+        let ix = Instruction {
+            program_id: WHIRLPOOL_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(a.payer.key(), true),
+                AccountMeta::new_readonly(a.state.key(), false),
+                AccountMeta::new(a.position.key(), false),
+                AccountMeta::new(a.position_mint.key(), true),
+                AccountMeta::new(a.position_token_account.key(), false),
+                AccountMeta::new_readonly(a.whirlpool.key(), false),
+                AccountMeta::new_readonly(a.token_program.key(), false),
+                AccountMeta::new_readonly(a.system_program.key(), false),
+                AccountMeta::new_readonly(a.rent.key(), false),
+                AccountMeta::new_readonly(a.associated_token_program.key(), false),
+            ],
+            data,
+        };
+        invoke(
+            &ix,
+            &[
+                a.payer.to_account_info(),
+                a.state.to_account_info(),
+                a.position.to_account_info(),
+                a.position_mint.to_account_info(),
+                a.position_token_account.to_account_info(),
+                a.whirlpool.to_account_info(),
+                a.token_program.to_account_info(),
+                a.system_program.to_account_info(),
+                a.rent.to_account_info(),
+                a.associated_token_program.to_account_info(),
+            ],
+        )?;
+
+        let position = ctx.accounts.position.key();
+        let state = &mut ctx.accounts.state;
+        state.config.whirlpool_position = Some(position);
+        emit_cpi!(WhirlpoolPositionOpenedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            whirlpool: ctx.accounts.whirlpool.key(),
+            position,
+            tick_lower_index,
+            tick_upper_index,
+        });
+        Ok(())
+    }
+
+    /// Adds `liquidity_amount` to `config.whirlpool_position` from the SOL and
+    /// tokens reserved by the `AddLiquidity` strategy, keeping the liquidity
+    /// instead of burning an LP share as `add_liquidity` does. As there, the
+    /// maximums are capped by what the vault has reserved.
+    pub fn increase_whirlpool_liquidity(
+        ctx: Context<IncreaseWhirlpoolLiquidity>,
+        liquidity_amount: u128,
+        maximum_sol_amount: u64,
+        maximum_token_amount: u64,
+    ) -> Result<()> {
+        let state = &ctx.accounts.state;
+        require!(
+            state.config.whirlpool_position == Some(ctx.accounts.position.key()),
+            VaultError::PoolNotConfigured
+        );
+        require!(
+            maximum_sol_amount > 0 && maximum_sol_amount <= state.liquidity_reserve_lamports,
+            VaultError::InsufficientLiquidityReserve
+        );
+        require!(
+            maximum_token_amount > 0 && maximum_token_amount <= state.liquidity_reserve_tokens,
+            VaultError::InsufficientLiquidityReserve
+        );
+
+        let state_key = state.key();
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[state.vault_bump]];
+        let seed_mint = state.seed_mint;
+        let authority_key = state.authority;
+        let state_seeds: &[&[u8]] = &[
+            b"state",
+            seed_mint.as_ref(),
+            authority_key.as_ref(),
+            &[state.bump],
+        ];
+
+        // The state PDA owns the position, so both legs must come from
+        // state-owned accounts, as with `add_liquidity`.
+        wrap_sol(
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.state_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            vault_seeds,
+            maximum_sol_amount,
+        )?;
+
+        let sol_is_token_a = ctx.accounts.wsol_mint.key() < ctx.accounts.mint.key();
+        let (owner_a, owner_b, maximum_a, maximum_b) = if sol_is_token_a {
+            (
+                ctx.accounts.state_wsol_ata.to_account_info(),
+                ctx.accounts.vault_token_account.to_account_info(),
+                maximum_sol_amount,
+                maximum_token_amount,
+            )
+        } else {
+            (
+                ctx.accounts.vault_token_account.to_account_info(),
+                ctx.accounts.state_wsol_ata.to_account_info(),
+                maximum_token_amount,
+                maximum_sol_amount,
+            )
+        };
+
+        let mut data = Vec::with_capacity(40);
+        data.extend_from_slice(&WHIRLPOOL_INCREASE_LIQUIDITY);
+        data.extend_from_slice(&liquidity_amount.to_le_bytes());
+        data.extend_from_slice(&maximum_a.to_le_bytes());
+        data.extend_from_slice(&maximum_b.to_le_bytes());
+
+        let tokens_before = ctx.accounts.vault_token_account.amount;
+        let a = &ctx.accounts;
+        let ix = Instruction {
+            program_id: WHIRLPOOL_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(a.whirlpool.key(), false),
+                AccountMeta::new_readonly(a.token_program.key(), false),
+                AccountMeta::new_readonly(a.state.key(), true),
+                AccountMeta::new(a.position.key(), false),
+                AccountMeta::new_readonly(a.position_token_account.key(), false),
+                AccountMeta::new(owner_a.key(), false),
+                AccountMeta::new(owner_b.key(), false),
+                AccountMeta::new(a.whirlpool_token_vault_a.key(), false),
+                AccountMeta::new(a.whirlpool_token_vault_b.key(), false),
+                AccountMeta::new(a.tick_array_lower.key(), false),
+                AccountMeta::new(a.tick_array_upper.key(), false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &ix,
+            &[
+                a.whirlpool.to_account_info(),
+                a.token_program.to_account_info(),
+                a.state.to_account_info(),
+                a.position.to_account_info(),
+                a.position_token_account.to_account_info(),
+                owner_a,
+                owner_b,
+                a.whirlpool_token_vault_a.to_account_info(),
+                a.whirlpool_token_vault_b.to_account_info(),
+                a.tick_array_lower.to_account_info(),
+                a.tick_array_upper.to_account_info(),
+            ],
+            &[state_seeds],
+        )?;
+        ctx.accounts.vault_token_account.reload()?;
+        ctx.accounts.state_wsol_ata.reload()?;
+        let token_amount = tokens_before - ctx.accounts.vault_token_account.amount;
+        let sol_amount = maximum_sol_amount - ctx.accounts.state_wsol_ata.amount;
+
+        // Unused WSOL and the ATA rent go back to the vault.
+        let cpi_close = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.state_wsol_ata.to_account_info(),
+                destination: ctx.accounts.vault.to_account_info(),
+                authority: ctx.accounts.state.to_account_info(),
+            },
+            &[state_seeds],
+        );
+        token_interface::close_account(cpi_close)?;
+
+        let state = &mut ctx.accounts.state;
+        state.liquidity_reserve_lamports -= sol_amount;
+        state.liquidity_reserve_tokens -= token_amount;
+
+        emit_cpi!(PositionIncreasedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            whirlpool: ctx.accounts.whirlpool.key(),
+            position: ctx.accounts.position.key(),
+            liquidity: liquidity_amount,
+            sol_amount,
+            token_amount,
+        });
+        Ok(())
+    }
+
+    /// Collects trading fees from `config.whirlpool_position`. As with the
+    /// other harvests, the SOL side becomes vault profit and the token side
+    /// waits in the vault token account for the next crank.
+    pub fn harvest_whirlpool_fees(ctx: Context<HarvestWhirlpoolFees>) -> Result<()> {
+        let state = &ctx.accounts.state;
+        require!(
+            state.config.whirlpool_position == Some(ctx.accounts.position.key()),
+            VaultError::PoolNotConfigured
+        );
+        let seed_mint = state.seed_mint;
+        let authority_key = state.authority;
+        let state_seeds: &[&[u8]] = &[
+            b"state",
+            seed_mint.as_ref(),
+            authority_key.as_ref(),
+            &[state.bump],
+        ];
+        let wsol_before = ctx.accounts.vault_wsol_ata.amount;
+        let tokens_before = ctx.accounts.vault_token_account.amount;
+
+        let a = &ctx.accounts;
+        // Fees only reach the position once it is checkpointed.
+        let ix = Instruction {
+            program_id: WHIRLPOOL_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(a.whirlpool.key(), false),
+                AccountMeta::new(a.position.key(), false),
+                AccountMeta::new_readonly(a.tick_array_lower.key(), false),
+                AccountMeta::new_readonly(a.tick_array_upper.key(), false),
+            ],
+            data: WHIRLPOOL_UPDATE_FEES_AND_REWARDS.to_vec(),
+        };
+        invoke(
+            &ix,
+            &[
+                a.whirlpool.to_account_info(),
+                a.position.to_account_info(),
+                a.tick_array_lower.to_account_info(),
+                a.tick_array_upper.to_account_info(),
+            ],
+        )?;
+
+        let sol_is_token_a = a.wsol_mint.key() < a.mint.key();
+        let (owner_a, vault_a, owner_b, vault_b) = if sol_is_token_a {
+            (
+                a.vault_wsol_ata.to_account_info(),
+                a.whirlpool_token_vault_a.to_account_info(),
+                a.vault_token_account.to_account_info(),
+                a.whirlpool_token_vault_b.to_account_info(),
+            )
+        } else {
+            (
+                a.vault_token_account.to_account_info(),
+                a.whirlpool_token_vault_a.to_account_info(),
+                a.vault_wsol_ata.to_account_info(),
+                a.whirlpool_token_vault_b.to_account_info(),
+            )
+        };
+        let ix = Instruction {
+            program_id: WHIRLPOOL_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(a.whirlpool.key(), false),
+                AccountMeta::new_readonly(a.state.key(), true),
+                AccountMeta::new(a.position.key(), false),
+                AccountMeta::new_readonly(a.position_token_account.key(), false),
+                AccountMeta::new(owner_a.key(), false),
+                AccountMeta::new(vault_a.key(), false),
+                AccountMeta::new(owner_b.key(), false),
+                AccountMeta::new(vault_b.key(), false),
+                AccountMeta::new_readonly(a.token_program.key(), false),
+            ],
+            data: WHIRLPOOL_COLLECT_FEES.to_vec(),
+        };
+        invoke_signed(
+            &ix,
+            &[
+                a.whirlpool.to_account_info(),
+                a.state.to_account_info(),
+                a.position.to_account_info(),
+                a.position_token_account.to_account_info(),
+                owner_a,
+                vault_a,
+                owner_b,
+                vault_b,
+                a.token_program.to_account_info(),
+            ],
+            &[state_seeds],
+        )?;
+
+        ctx.accounts.vault_wsol_ata.reload()?;
+        ctx.accounts.vault_token_account.reload()?;
+        let lamports = ctx
+            .accounts
+            .vault_wsol_ata
+            .amount
+            .saturating_sub(wsol_before);
+        let token_amount = ctx
+            .accounts
+            .vault_token_account
+            .amount
+            .saturating_sub(tokens_before);
+        close_vault_wsol(
+            &ctx.accounts.state,
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+
+        emit_cpi!(FeesHarvestedEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            source: FeeSource::OrcaWhirlpool,
+            lamports,
+            token_amount,
+        });
+        Ok(())
+    }
+
+    /// Creates the state PDA's stake escrow in `config.m3m3_vault`, which
+    /// `stake_for_fees` stakes into.
+    pub fn init_fee_stake_escrow(ctx: Context<InitFeeStakeEscrow>) -> Result<()> {
+        require!(
+            ctx.accounts.state.config.m3m3_vault == Some(ctx.accounts.m3m3_vault.key()),
+            VaultError::PoolNotConfigured
+        );
+        let a = &ctx.accounts;
+        let ix = Instruction {
+            program_id: M3M3_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(a.m3m3_vault.key(), false),
+                AccountMeta::new(a.stake_escrow.key(), false),
+                AccountMeta::new(a.full_balance_list.key(), false),
+                AccountMeta::new(a.top_staker_list.key(), false),
+                AccountMeta::new_readonly(a.state.key(), false),
+                AccountMeta::new(a.payer.key(), true),
+                AccountMeta::new_readonly(a.system_program.key(), false),
+                AccountMeta::new_readonly(a.m3m3_event_authority.key(), false),
+                AccountMeta::new_readonly(M3M3_PROGRAM_ID, false),
+            ],
+            data: M3M3_INITIALIZE_STAKE_ESCROW.to_vec(),
+        };
+        invoke(
+            &ix,
+            &[
+                a.m3m3_vault.to_account_info(),
+                a.stake_escrow.to_account_info(),
+                a.full_balance_list.to_account_info(),
+                a.top_staker_list.to_account_info(),
+                a.state.to_account_info(),
+                a.payer.to_account_info(),
+                a.system_program.to_account_info(),
+                a.m3m3_event_authority.to_account_info(),
+                a.m3m3_program.to_account_info(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Stakes the tokens kept by the `StakeForFees` strategy into
+    /// `config.m3m3_vault` under the state PDA's escrow. The stake is never
+    /// withdrawn, so the tokens stay out of circulation while earning a
+    /// share of the pool's fees for `harvest_fee_stake`.
+    /// `remaining_accounts` carries the pool accounts in `m3m3_pool_accounts`
+    /// order.
+    pub fn stake_for_fees<'info>(ctx: Context<'_, '_, '_, 'info, FeeStake<'info>>) -> Result<()> {
+        let state = &ctx.accounts.state;
+        require!(
+            state.config.m3m3_vault == Some(ctx.accounts.m3m3_vault.key()),
+            VaultError::PoolNotConfigured
+        );
+        let amount = state.fee_stake_tokens;
+        require!(amount > 0, VaultError::NoTokens);
+        let state_seeds: &[&[u8]] = &[
+            b"state",
+            state.seed_mint.as_ref(),
+            state.authority.as_ref(),
+            &[state.bump],
+        ];
+        let (pool_metas, pool_infos) = m3m3_pool_accounts(ctx.remaining_accounts)?;
+
+        let mut data = Vec::with_capacity(16);
+        data.extend_from_slice(&M3M3_STAKE);
+        data.extend_from_slice(&amount.to_le_bytes());
+
+        let a = &ctx.accounts;
+        let smallest_stake_escrow = a
+            .smallest_stake_escrow
+            .as_ref()
+            .map_or(M3M3_PROGRAM_ID, |escrow| escrow.key());
+        let mut metas = vec![
+            AccountMeta::new(a.m3m3_vault.key(), false),
+            AccountMeta::new(a.stake_token_vault.key(), false),
+            AccountMeta::new(a.quote_token_vault.key(), false),
+            AccountMeta::new(a.top_staker_list.key(), false),
+            AccountMeta::new(a.full_balance_list.key(), false),
+            AccountMeta::new(a.stake_escrow.key(), false),
+            AccountMeta::new(smallest_stake_escrow, false),
+            AccountMeta::new(a.vault_token_account.key(), false),
+            AccountMeta::new_readonly(a.state.key(), true),
+        ];
+        metas.extend(pool_metas);
+        metas.extend([
+            AccountMeta::new_readonly(a.token_program.key(), false),
+            AccountMeta::new_readonly(a.m3m3_event_authority.key(), false),
+            AccountMeta::new_readonly(M3M3_PROGRAM_ID, false),
+        ]);
+        let mut infos = vec![
+            a.m3m3_vault.to_account_info(),
+            a.stake_token_vault.to_account_info(),
+            a.quote_token_vault.to_account_info(),
+            a.top_staker_list.to_account_info(),
+            a.full_balance_list.to_account_info(),
+            a.stake_escrow.to_account_info(),
+            a.smallest_stake_escrow
+                .as_ref()
+                .map_or_else(|| a.m3m3_program.to_account_info(), |e| e.to_account_info()),
+            a.vault_token_account.to_account_info(),
+            a.state.to_account_info(),
+        ];
+        infos.extend(pool_infos);
+        infos.extend([
+            a.token_program.to_account_info(),
+            a.m3m3_event_authority.to_account_info(),
+            a.m3m3_program.to_account_info(),
+        ]);
+        let ix = Instruction {
+            program_id: M3M3_PROGRAM_ID,
+            accounts: metas,
+            data,
+        };
+        invoke_signed(&ix, &infos, &[state_seeds])?;
+
+        let state = &mut ctx.accounts.state;
+        state.fee_stake_tokens = 0;
+        state.staked_fee_tokens = state.staked_fee_tokens.saturating_add(amount);
+        emit_cpi!(FeeStakeEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            m3m3_vault: ctx.accounts.m3m3_vault.key(),
+            amount,
+            staked_fee_tokens: state.staked_fee_tokens,
+        });
+        Ok(())
+    }
+
+    /// Claims the fees earned by the state PDA's M3M3 stake. Meteora pays
+    /// them in the pool's quote token, SOL, which is unwrapped into the vault
+    /// as profit for the next buyback. `remaining_accounts` is as for
+    /// `stake_for_fees`.
+    pub fn harvest_fee_stake<'info>(
+        ctx: Context<'_, '_, '_, 'info, FeeStake<'info>>,
+    ) -> Result<()> {
+        let state = &ctx.accounts.state;
+        require!(
+            state.config.m3m3_vault == Some(ctx.accounts.m3m3_vault.key()),
+            VaultError::PoolNotConfigured
+        );
+        let state_seeds: &[&[u8]] = &[
+            b"state",
+            state.seed_mint.as_ref(),
+            state.authority.as_ref(),
+            &[state.bump],
+        ];
+        let (pool_metas, pool_infos) = m3m3_pool_accounts(ctx.remaining_accounts)?;
+        let wsol_before = ctx.accounts.vault_wsol_ata.amount;
+
+        let mut data = Vec::with_capacity(16);
+        data.extend_from_slice(&M3M3_CLAIM_FEE);
+        data.extend_from_slice(&u64::MAX.to_le_bytes());
+
+        let a = &ctx.accounts;
+        let smallest_stake_escrow = a
+            .smallest_stake_escrow
+            .as_ref()
+            .map_or(M3M3_PROGRAM_ID, |escrow| escrow.key());
+        let mut metas = vec![
+            AccountMeta::new(a.m3m3_vault.key(), false),
+            AccountMeta::new(a.top_staker_list.key(), false),
+            AccountMeta::new(a.full_balance_list.key(), false),
+            AccountMeta::new(a.stake_escrow.key(), false),
+            AccountMeta::new(smallest_stake_escrow, false),
+            AccountMeta::new(a.vault_wsol_ata.key(), false),
+            AccountMeta::new_readonly(a.state.key(), true),
+            AccountMeta::new(a.stake_token_vault.key(), false),
+            AccountMeta::new(a.quote_token_vault.key(), false),
+        ];
+        metas.extend(pool_metas);
+        metas.extend([
+            AccountMeta::new_readonly(a.token_program.key(), false),
+            AccountMeta::new_readonly(a.m3m3_event_authority.key(), false),
+            AccountMeta::new_readonly(M3M3_PROGRAM_ID, false),
+        ]);
+        let mut infos = vec![
+            a.m3m3_vault.to_account_info(),
+            a.top_staker_list.to_account_info(),
+            a.full_balance_list.to_account_info(),
+            a.stake_escrow.to_account_info(),
+            a.smallest_stake_escrow
+                .as_ref()
+                .map_or_else(|| a.m3m3_program.to_account_info(), |e| e.to_account_info()),
+            a.vault_wsol_ata.to_account_info(),
+            a.state.to_account_info(),
+            a.stake_token_vault.to_account_info(),
+            a.quote_token_vault.to_account_info(),
+        ];
+        infos.extend(pool_infos);
+        infos.extend([
+            a.token_program.to_account_info(),
+            a.m3m3_event_authority.to_account_info(),
+            a.m3m3_program.to_account_info(),
+        ]);
+        let ix = Instruction {
+            program_id: M3M3_PROGRAM_ID,
+            accounts: metas,
+            data,
+        };
+        invoke_signed(&ix, &infos, &[state_seeds])?;
+
+        ctx.accounts.vault_wsol_ata.reload()?;
+        let lamports = ctx
+            .accounts
+            .vault_wsol_ata
+            .amount
+            .saturating_sub(wsol_before);
+        close_vault_wsol(
+            &ctx.accounts.state,
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+
+        emit_cpi!(FeesHarvestedEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            source: FeeSource::MeteoraStakeForFee,
+            lamports,
+            token_amount: 0,
+        });
+        Ok(())
+    }
+
+    /// Deposits idle vault SOL into the configured SPL stake pool. The vault
+    /// must keep `config.working_balance_lamports` liquid. Staked principal
+    /// still counts toward the vault balance for profit purposes.
+    pub fn stake_idle(ctx: Context<StakeIdle>, lamports: u64) -> Result<()> {
+        let state = &ctx.accounts.state;
+        require!(
+            state.config.stake_pool == Some(ctx.accounts.stake_pool.key()),
+            VaultError::PoolNotConfigured
+        );
+        let vault = ctx.accounts.vault.to_account_info();
+        let remaining = vault
+            .lamports()
+            .checked_sub(lamports)
+            .ok_or(VaultError::InsufficientIdleBalance)?;
+        require!(
+            lamports > 0 && remaining >= state.config.working_balance_lamports,
+            VaultError::InsufficientIdleBalance
+        );
+
+        let state_key = state.key();
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[state.vault_bump]];
+        let pool_tokens_before = ctx.accounts.vault_lst_account.amount;
+
+        let mut data = Vec::with_capacity(9);
+        data.push(STAKE_POOL_DEPOSIT_SOL);
+        data.extend_from_slice(&lamports.to_le_bytes());
+        let a = &ctx.accounts;
+        let ix = Instruction {
+            program_id: SPL_STAKE_POOL_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(a.stake_pool.key(), false),
+                AccountMeta::new_readonly(a.stake_pool_withdraw_authority.key(), false),
+                AccountMeta::new(a.reserve_stake.key(), false),
+                AccountMeta::new(a.vault.key(), true),
+                AccountMeta::new(a.vault_lst_account.key(), false),
+                AccountMeta::new(a.manager_fee_account.key(), false),
+                AccountMeta::new(a.vault_lst_account.key(), false),
+                AccountMeta::new(a.pool_mint.key(), false),
+                AccountMeta::new_readonly(a.system_program.key(), false),
+                AccountMeta::new_readonly(a.token_program.key(), false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &ix,
+            &[
+                a.stake_pool.to_account_info(),
+                a.stake_pool_withdraw_authority.to_account_info(),
+                a.reserve_stake.to_account_info(),
+                a.vault.to_account_info(),
+                a.vault_lst_account.to_account_info(),
+                a.manager_fee_account.to_account_info(),
+                a.pool_mint.to_account_info(),
+                a.system_program.to_account_info(),
+                a.token_program.to_account_info(),
+                a.stake_pool_program.to_account_info(),
+            ],
+            &[vault_seeds],
+        )?;
+
+        ctx.accounts.vault_lst_account.reload()?;
+        let pool_tokens = ctx
+            .accounts
+            .vault_lst_account
+            .amount
+            .saturating_sub(pool_tokens_before);
+        let state = &mut ctx.accounts.state;
+        state.staked_lamports = state
+            .staked_lamports
+            .checked_add(lamports)
+            .ok_or(VaultError::MathOverflow)?;
+        state.staked_pool_tokens = state
+            .staked_pool_tokens
+            .checked_add(pool_tokens)
+            .ok_or(VaultError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Redeems stake pool tokens back into vault SOL. Permissionless so
+    /// keepers can free principal ahead of a crank; any yield over the
+    /// redeemed share of principal shows up as profit.
+    pub fn unstake_idle(ctx: Context<UnstakeIdle>, pool_tokens: u64) -> Result<()> {
+        let state = &ctx.accounts.state;
+        require!(
+            state.config.stake_pool == Some(ctx.accounts.stake_pool.key()),
+            VaultError::PoolNotConfigured
+        );
+        require!(
+            pool_tokens > 0 && pool_tokens <= state.staked_pool_tokens,
+            VaultError::InsufficientIdleBalance
+        );
+        let principal = (state.staked_lamports as u128 * pool_tokens as u128
+            / state.staked_pool_tokens as u128) as u64;
+
+        let state_key = state.key();
+        let vault_seeds: &[&[u8]] = &[b"vault", state_key.as_ref(), &[state.vault_bump]];
+        let mut data = Vec::with_capacity(9);
+        data.push(STAKE_POOL_WITHDRAW_SOL);
+        data.extend_from_slice(&pool_tokens.to_le_bytes());
+        let a = &ctx.accounts;
+        let ix = Instruction {
+            program_id: SPL_STAKE_POOL_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(a.stake_pool.key(), false),
+                AccountMeta::new_readonly(a.stake_pool_withdraw_authority.key(), false),
+                AccountMeta::new_readonly(a.vault.key(), true),
+                AccountMeta::new(a.vault_lst_account.key(), false),
+                AccountMeta::new(a.reserve_stake.key(), false),
+                AccountMeta::new(a.vault.key(), false),
+                AccountMeta::new(a.manager_fee_account.key(), false),
+                AccountMeta::new(a.pool_mint.key(), false),
+                AccountMeta::new_readonly(a.clock.key(), false),
+                AccountMeta::new_readonly(a.stake_history.key(), false),
+                AccountMeta::new_readonly(a.stake_program.key(), false),
+                AccountMeta::new_readonly(a.token_program.key(), false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &ix,
+            &[
+                a.stake_pool.to_account_info(),
+                a.stake_pool_withdraw_authority.to_account_info(),
+                a.vault.to_account_info(),
+                a.vault_lst_account.to_account_info(),
+                a.reserve_stake.to_account_info(),
+                a.manager_fee_account.to_account_info(),
+                a.pool_mint.to_account_info(),
+                a.clock.to_account_info(),
+                a.stake_history.to_account_info(),
+                a.stake_program.to_account_info(),
+                a.token_program.to_account_info(),
+                a.stake_pool_program.to_account_info(),
+            ],
+            &[vault_seeds],
+        )?;
+
+        let state = &mut ctx.accounts.state;
+        state.staked_lamports -= principal;
+        state.staked_pool_tokens -= pool_tokens;
+        Ok(())
+    }
+
+    /// Creates the vault's account with the configured yield backend (the
+    /// vault PDA is its authority) and records it in config.
+    pub fn init_yield_account<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitYieldAccount<'info>>,
+    ) -> Result<()> {
+        let state_key = ctx.accounts.state.key();
+        let vault_seeds: &[&[u8]] = &[
+            b"vault",
+            state_key.as_ref(),
+            &[ctx.accounts.state.vault_bump],
+        ];
+        let adapter = yield_adapter(
+            &ctx.accounts.state,
+            ctx.accounts.vault.to_account_info(),
+            None,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        )?;
+        adapter.init_account(
+            &ctx.accounts.yield_account.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            vault_seeds,
+        )?;
+        ctx.accounts.state.config.yield_account = Some(ctx.accounts.yield_account.key());
+        Ok(())
+    }
+
+    /// Lends idle vault SOL through the configured yield backend, keeping
+    /// `config.working_balance_lamports` liquid. Lent principal still counts
+    /// toward the vault balance for profit purposes.
+    pub fn deposit_idle<'info>(
+        ctx: Context<'_, '_, '_, 'info, DepositIdle<'info>>,
+        lamports: u64,
+    ) -> Result<()> {
+        let vault = ctx.accounts.vault.to_account_info();
+        let remaining = vault
+            .lamports()
+            .checked_sub(lamports)
+            .ok_or(VaultError::InsufficientIdleBalance)?;
+        require!(
+            lamports > 0 && remaining >= ctx.accounts.state.config.working_balance_lamports,
+            VaultError::InsufficientIdleBalance
+        );
+        let state_key = ctx.accounts.state.key();
+        let vault_seeds: &[&[u8]] = &[
+            b"vault",
+            state_key.as_ref(),
+            &[ctx.accounts.state.vault_bump],
+        ];
+
+        wrap_sol(
+            &vault,
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            vault_seeds,
+            lamports,
+        )?;
+        let adapter = yield_adapter(
+            &ctx.accounts.state,
+            vault.clone(),
+            Some(ctx.accounts.vault_wsol_ata.to_account_info()),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.remaining_accounts,
+        )?;
+        adapter.deposit(lamports, vault_seeds)?;
+        close_vault_wsol(
+            &ctx.accounts.state,
+            &vault,
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+
+        let state = &mut ctx.accounts.state;
+        state.lent_lamports = state
+            .lent_lamports
+            .checked_add(lamports)
+            .ok_or(VaultError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Withdraws lent SOL back into the vault. Permissionless so keepers can
+    /// free principal ahead of a crank; once principal is fully withdrawn,
+    /// further withdrawals (the interest) show up as profit.
+    pub fn withdraw_idle<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawIdle<'info>>,
+        lamports: u64,
+    ) -> Result<()> {
+        require!(lamports > 0, VaultError::InsufficientIdleBalance);
+        let vault = ctx.accounts.vault.to_account_info();
+        let state_key = ctx.accounts.state.key();
+        let vault_seeds: &[&[u8]] = &[
+            b"vault",
+            state_key.as_ref(),
+            &[ctx.accounts.state.vault_bump],
+        ];
+
+        let adapter = yield_adapter(
+            &ctx.accounts.state,
+            vault.clone(),
+            Some(ctx.accounts.vault_wsol_ata.to_account_info()),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.remaining_accounts,
+        )?;
+        adapter.withdraw(lamports, vault_seeds)?;
+        ctx.accounts.vault_wsol_ata.reload()?;
+        let withdrawn = ctx.accounts.vault_wsol_ata.amount;
+        close_vault_wsol(
+            &ctx.accounts.state,
+            &vault,
+            &ctx.accounts.vault_wsol_ata.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+
+        let state = &mut ctx.accounts.state;
+        state.lent_lamports = state.lent_lamports.saturating_sub(withdrawn);
+        Ok(())
+    }
+
+    /// Sends the SOL withheld by `protocol_fee_bps` to the protocol treasury.
+    /// Permissionless, like `burn_sol`.
+    pub fn collect_protocol_fee(ctx: Context<CollectProtocolFee>) -> Result<()> {
+        let lamports = ctx.accounts.state.protocol_fee_reserve_lamports;
+        require!(lamports > 0, VaultError::NoProfit);
+        let state_key = ctx.accounts.state.key();
+        let vault_seeds: &[&[u8]] = &[
+            b"vault",
+            state_key.as_ref(),
+            &[ctx.accounts.state.vault_bump],
+        ];
+
+        let keeper_pool_lamports = pay_protocol_fee(
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.keeper_pool.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &[vault_seeds],
+            lamports,
+            ctx.accounts.protocol_config.settings.keeper_pool_bps,
+        )?;
+        ctx.accounts.state.protocol_fee_reserve_lamports = 0;
+
+        emit_cpi!(ProtocolFeeCollectedEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            lamports,
+            treasury: ctx.accounts.treasury.key(),
+            keeper_pool_lamports,
+        });
+        Ok(())
+    }
+
+    /// Pays the keeper of a small vault's latest crank a flat tip from the
+    /// shared `KeeperPool`, for vaults whose own profit can't fund a
+    /// meaningful `keeper_reward_bps`. Once per crank, before the next one.
+    pub fn claim_keeper_tip(ctx: Context<ClaimKeeperTip>) -> Result<()> {
+        let settings = &ctx.accounts.protocol_config.settings;
+        let record = &ctx.accounts.buyback_record;
+        require!(
+            !record.tip_claimed
+                && record.index + 1 == ctx.accounts.state.crank_count
+                && ctx.accounts.state.config.base_mint.is_none()
+                && record.profit_lamports > 0
+                && record.profit_lamports <= settings.keeper_tip_max_profit_lamports,
+            VaultError::KeeperTipUnavailable
+        );
+        let pool = ctx.accounts.keeper_pool.to_account_info();
+        let rent = Rent::get()?.minimum_balance(8 + KeeperPool::SIZE);
+        let lamports = settings
+            .keeper_tip_lamports
+            .min(pool.lamports().saturating_sub(rent));
+        require!(lamports > 0, VaultError::KeeperTipUnavailable);
+
+        ctx.accounts.keeper_pool.sub_lamports(lamports)?;
+        ctx.accounts.keeper.add_lamports(lamports)?;
+        ctx.accounts.keeper_pool.total_paid_lamports = ctx
+            .accounts
+            .keeper_pool
+            .total_paid_lamports
+            .saturating_add(lamports);
+        ctx.accounts.buyback_record.tip_claimed = true;
+
+        emit_cpi!(KeeperTipPaidEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            state: ctx.accounts.state.key(),
+            keeper: ctx.accounts.keeper.key(),
+            lamports,
+        });
+        Ok(())
+    }
+
+    /// Sends the SOL withheld by `config.sol_burn_bps` to the burn address.
+    /// Permissionless, like the crank.
+    pub fn burn_sol(ctx: Context<BurnSol>) -> Result<()> {
+        let lamports = ctx.accounts.state.sol_burn_reserve_lamports;
+        require!(lamports > 0, VaultError::NoProfit);
+        let state_key = ctx.accounts.state.key();
+        let vault_seeds: &[&[u8]] = &[
+            b"vault",
+            state_key.as_ref(),
+            &[ctx.accounts.state.vault_bump],
+        ];
+
+        invoke_signed(
+            &system_instruction::transfer(
+                ctx.accounts.vault.key,
+                ctx.accounts.burn_authority.key,
+                lamports,
+            ),
+            &[
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.burn_authority.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[vault_seeds],
+        )?;
+        ctx.accounts.state.sol_burn_reserve_lamports = 0;
+
+        emit_cpi!(SolBurnedEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            lamports,
+            burn_address: ctx.accounts.burn_authority.key(),
+        });
+        Ok(())
+    }
+
+    /// Moves retained tokens out of the vault for the authority's strategic
+    /// use (LP seeding, listings, partnerships).
+    pub fn release_retained(ctx: Context<ReleaseRetained>, amount: u64) -> Result<()> {
+        let state = &ctx.accounts.state;
+        require!(
+            amount > 0 && amount <= state.retained_tokens,
+            VaultError::InsufficientRetainedTokens
+        );
+        let state_seeds: &[&[u8]] = &[
+            b"state",
+            state.seed_mint.as_ref(),
+            state.authority.as_ref(),
+            &[state.bump],
+        ];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.vault_token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.destination_token_account.to_account_info(),
+                authority: ctx.accounts.state.to_account_info(),
+            },
+            &[state_seeds],
+        );
+        token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.mint.decimals)?;
+        ctx.accounts.state.retained_tokens -= amount;
+        Ok(())
+    }
+
+    /// Commits the tokens kept by the `HolderRewards` strategy to `root`, a
+    /// merkle root of `(claimant, amount)` allocations built off-chain, and
+    /// moves them into the new distribution's token account. At most one
+    /// distribution per epoch.
+    pub fn publish_distribution(ctx: Context<PublishDistribution>, root: [u8; 32]) -> Result<()> {
+        let clock = Clock::get()?;
+        let state = &ctx.accounts.state;
+        require!(
+            state.distribution_count == 0 || clock.epoch > state.last_distribution_epoch,
+            VaultError::DistributionTooSoon
+        );
+        let amount = state.holder_reward_tokens;
+        require!(amount > 0, VaultError::NoTokens);
+        let state_seeds: &[&[u8]] = &[
+            b"state",
+            state.seed_mint.as_ref(),
+            state.authority.as_ref(),
+            &[state.bump],
+        ];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.distribution_token_account.to_account_info(),
+                    authority: ctx.accounts.state.to_account_info(),
+                },
+                &[state_seeds],
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        let state = &mut ctx.accounts.state;
+        let distribution = &mut ctx.accounts.distribution;
+        distribution.state = state.key();
+        distribution.index = state.distribution_count;
+        distribution.mint = state.mint;
+        distribution.root = root;
+        distribution.total_amount = amount;
+        distribution.claimed_amount = 0;
+        distribution.epoch = clock.epoch;
+        distribution.lock_tranche = None;
+        distribution.bump = ctx.bumps.distribution;
+        state.holder_reward_tokens = 0;
+        state.distribution_count += 1;
+        state.last_distribution_epoch = clock.epoch;
+
+        emit_cpi!(DistributionPublishedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            distribution: distribution.key(),
+            index: distribution.index,
+            root,
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Pays `amount` to the claimant if `proof` links their leaf to the
+    /// distribution's root. The `ClaimStatus` PDA stops a second claim.
+    pub fn claim_distribution(
+        ctx: Context<ClaimDistribution>,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let claimant = ctx.accounts.claimant.key();
+        let distribution = &ctx.accounts.distribution;
+        require!(
+            verify_merkle_proof(&proof, distribution.root, merkle_leaf(&claimant, amount)),
+            VaultError::InvalidMerkleProof
+        );
+        let claimed_amount = distribution
+            .claimed_amount
+            .checked_add(amount)
+            .filter(|claimed| *claimed <= distribution.total_amount)
+            .ok_or(VaultError::InvalidMerkleProof)?;
+
+        let state_key = distribution.state;
+        let index = distribution.index.to_le_bytes();
+        let seeds: &[&[u8]] = &[
+            b"distribution",
+            state_key.as_ref(),
+            &index,
+            &[distribution.bump],
+        ];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.distribution_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.claimant_token_account.to_account_info(),
+                    authority: ctx.accounts.distribution.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        let distribution = &mut ctx.accounts.distribution;
+        distribution.claimed_amount = claimed_amount;
+        let claim_status = &mut ctx.accounts.claim_status;
+        claim_status.distribution = distribution.key();
+        claim_status.claimant = claimant;
+        claim_status.amount = amount;
+        claim_status.claimed_ts = Clock::get()?.unix_timestamp;
+
+        emit_cpi!(DistributionClaimedEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            distribution: distribution.key(),
+            claimant,
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Moves everything vested in an `UnlockMode::Airdrop` tranche into a
+    /// new merkle distribution committed to `root`, built off-chain from
+    /// holder balances. Holders then claim through `claim_distribution`;
+    /// nothing reaches the authority. Closes the tranche once drained.
+    /// `remaining_accounts` carries the mint's transfer hook accounts, if any.
+    pub fn airdrop_tranche<'info>(
+        ctx: Context<'_, '_, '_, 'info, AirdropTranche<'info>>,
+        root: [u8; 32],
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let tranche = &ctx.accounts.lock_tranche;
+        require!(
+            tranche.unlock_mode == UnlockMode::Airdrop,
+            VaultError::UnlockModeMismatch
+        );
+        let amount = tranche
+            .vested(clock.unix_timestamp)
+            .saturating_sub(tranche.claimed);
+        require!(amount > 0, VaultError::TimelockActive);
+
+        let state_key = ctx.accounts.state.key();
+        let seeds = &[
+            b"timelock".as_ref(),
+            state_key.as_ref(),
+            &[ctx.accounts.state.timelock_bump],
+        ];
+        let accounts = ReleaseAccounts {
+            state: state_key,
+            mint: ctx.accounts.mint.to_account_info(),
+            mint_decimals: ctx.accounts.mint.decimals,
+            timelock_token_account: ctx.accounts.timelock_token_account.to_account_info(),
+            timelock_authority: ctx.accounts.timelock_authority.to_account_info(),
+            destination: Some(ctx.accounts.distribution_token_account.to_account_info()),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            transfer_hook_accounts: ctx.remaining_accounts.to_vec(),
+        };
+        let tranche = &mut ctx.accounts.lock_tranche;
+        let event = release_tranche(
+            &mut ctx.accounts.state,
+            tranche,
+            &accounts,
+            amount,
+            &seeds[..],
+        )?;
+        let tranche_remaining = event.tranche_remaining;
+        emit_cpi!(event);
+
+        let state = &mut ctx.accounts.state;
+        let distribution = &mut ctx.accounts.distribution;
+        distribution.state = state_key;
+        distribution.index = state.distribution_count;
+        distribution.mint = tranche.mint;
+        distribution.root = root;
+        distribution.total_amount = amount;
+        distribution.claimed_amount = 0;
+        distribution.epoch = clock.epoch;
+        distribution.lock_tranche = Some(tranche.key());
+        distribution.bump = ctx.bumps.distribution;
+        state.distribution_count += 1;
+        emit_cpi!(DistributionPublishedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state_key,
+            distribution: distribution.key(),
+            index: distribution.index,
+            root,
+            amount,
+        });
+
+        if tranche_remaining == 0 {
+            tranche.close(ctx.accounts.rent_receiver.to_account_info())?;
+        }
+        Ok(())
+    }
+
+    /// Releases `amount` of the vested-but-unclaimed part of a `LockTranche`
+    /// from the timelock ATA, so the authority can release in measured steps.
+    /// Where the tokens go follows the tranche's `UnlockMode`. Once the
+    /// tranche is fully claimed it is closed and its rent returned to whoever
+    /// paid for it; a drained timelock ATA is closed to the authority and
+    /// recreated by the next crank.
+    /// `remaining_accounts` carries the mint's transfer hook accounts, if any.
+    pub fn unlock<'info>(
+        ctx: Context<'_, '_, '_, 'info, Unlock<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let tranche = &ctx.accounts.lock_tranche;
+        let unlockable = tranche
+            .vested(clock.unix_timestamp)
+            .saturating_sub(tranche.claimed);
+        require!(unlockable > 0, VaultError::TimelockActive);
+        require!(
+            amount > 0 && amount <= unlockable,
+            VaultError::UnlockExceedsVested
+        );
+        require!(
+            tranche.unlock_mode != UnlockMode::Airdrop,
+            VaultError::UnlockModeMismatch
+        );
+        if tranche.unlock_mode != UnlockMode::Burn {
+            let state = &mut ctx.accounts.state;
+            state.roll_unlock_window(clock.unix_timestamp);
+            require!(
+                amount <= state.unlock_allowance(clock.unix_timestamp),
+                VaultError::UnlockRateLimited
+            );
+            state.unlock_window_amount += amount;
+        }
+
+        let state_key = ctx.accounts.state.key();
+        let seeds = &[
+            b"timelock".as_ref(),
+            state_key.as_ref(),
+            &[ctx.accounts.state.timelock_bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let accounts = ReleaseAccounts {
+            state: state_key,
+            mint: ctx.accounts.mint.to_account_info(),
+            mint_decimals: ctx.accounts.mint.decimals,
+            timelock_token_account: ctx.accounts.timelock_token_account.to_account_info(),
+            timelock_authority: ctx.accounts.timelock_authority.to_account_info(),
+            destination: ctx
+                .accounts
+                .destination_token_account
+                .as_ref()
+                .map(|a| a.to_account_info()),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            transfer_hook_accounts: ctx.remaining_accounts.to_vec(),
+        };
+        let tranche = &mut ctx.accounts.lock_tranche;
+        let event = release_tranche(
+            &mut ctx.accounts.state,
+            tranche,
+            &accounts,
+            amount,
+            &seeds[..],
+        )?;
+        let tranche_remaining = event.tranche_remaining;
+        emit_cpi!(event);
+        if tranche_remaining == 0 {
+            tranche.close(ctx.accounts.rent_receiver.to_account_info())?;
+        }
+
+        ctx.accounts.timelock_token_account.reload()?;
+        if ctx.accounts.timelock_token_account.amount == 0 {
+            token_interface::close_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                CloseAccount {
+                    account: ctx.accounts.timelock_token_account.to_account_info(),
+                    destination: ctx.accounts.authority.to_account_info(),
+                    authority: ctx.accounts.timelock_authority.to_account_info(),
+                },
+                signer,
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Moves a Streamflow-mode tranche's unclaimed tokens into a
+    /// non-cancelable Streamflow stream to the configured recipient that
+    /// follows the tranche's remaining schedule, then closes the tranche.
+    /// `remaining_accounts` are the Streamflow accounts, in order: metadata
+    /// (new keypair, signer), escrow tokens, treasury, treasury tokens,
+    /// withdrawor, partner, partner tokens, fee oracle, rent sysvar and the
+    /// Streamflow program. The timelock PDA pays Streamflow's rent and fees,
+    /// so it must be funded with SOL in the same transaction.
+    pub fn stream_lock<'info>(ctx: Context<'_, '_, '_, 'info, StreamLock<'info>>) -> Result<()> {
+        let clock = Clock::get()?;
+        let tranche = &ctx.accounts.lock_tranche;
+        let UnlockMode::Streamflow { recipient } = tranche.unlock_mode else {
+            return err!(VaultError::UnlockModeMismatch);
+        };
+        require_keys_eq!(
+            ctx.accounts.recipient.key(),
+            recipient,
+            VaultError::UnlockModeMismatch
+        );
+        let remaining = tranche.amount - tranche.claimed;
+        require!(remaining > 0, VaultError::NoTokens);
+
+        let state_key = ctx.accounts.state.key();
+        let seeds = &[
+            b"timelock".as_ref(),
+            state_key.as_ref(),
+            &[ctx.accounts.state.timelock_bump],
+        ];
+        streamflow_create(
+            &ctx.accounts,
+            ctx.remaining_accounts,
+            &StreamSchedule::for_tranche(tranche, clock.unix_timestamp)?,
+            &seeds[..],
+        )?;
+
+        let mint = ctx.accounts.lock_tranche.mint;
+        let state = &mut ctx.accounts.state;
+        state.add_unlocked(mint, remaining, false);
+        let tranche = &mut ctx.accounts.lock_tranche;
+        tranche.claimed = tranche.amount;
+        emit_cpi!(LockStreamedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state_key,
+            lock_tranche: tranche.key(),
+            stream: ctx.remaining_accounts[0].key(),
+            recipient,
+            amount: remaining,
+            end_ts: tranche.unlock_ts,
+        });
+        tranche.close(ctx.accounts.rent_receiver.to_account_info())
+    }
+
+    /// Replaces the emergency council, signed by `config.council_threshold`
+    /// current members in `remaining_accounts`. An empty `members` dissolves
+    /// it, after which `propose_config` may install a new one.
+    pub fn rotate_council(
+        ctx: Context<EmergencyCouncil>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        ctx.accounts.check_approved(ctx.remaining_accounts)?;
+        validate_council(&members, threshold)?;
+        let state = &mut ctx.accounts.state;
+        state.config.council = members;
+        state.config.council_threshold = threshold;
+        emit_cpi!(CouncilSetEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state.key(),
+            members: state.config.council.clone(),
+            threshold,
+        });
+        Ok(())
+    }
+
+    /// Starts an emergency release of a lock tranche (e.g. a token
+    /// migration) to `destination`, which the council commits to: `None` for
+    /// burn-at-unlock tranches, the escrow for staking-rewards ones. Needs
+    /// `config.council_threshold` distinct council members as signers in
+    /// `remaining_accounts`; `early_unlock` can run once
+    /// `EARLY_UNLOCK_DELAY_SECS` have passed.
+    pub fn request_early_unlock<'info>(
+        ctx: Context<'_, '_, '_, 'info, RequestEarlyUnlock<'info>>,
+        destination: Option<Pubkey>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let config = &ctx.accounts.state.config;
+        require!(!config.council.is_empty(), VaultError::CouncilNotApproved);
+        // Streamflow, permanent and airdrop tranches never release directly.
+        let destination_ok = match ctx.accounts.lock_tranche.unlock_mode {
+            UnlockMode::Burn => destination.is_none(),
+            UnlockMode::StakingRewards { escrow } => destination == Some(escrow),
+            UnlockMode::Release => destination.is_some(),
+            UnlockMode::Streamflow { .. } | UnlockMode::Permanent | UnlockMode::Airdrop => false,
+        };
+        require!(destination_ok, VaultError::UnlockModeMismatch);
+        let approvals = council_approvals(&config.council, ctx.remaining_accounts);
+        require!(
+            approvals.count_ones() >= config.council_threshold as u32,
+            VaultError::CouncilNotApproved
+        );
+
+        let ready_ts = clock
+            .unix_timestamp
+            .checked_add(EARLY_UNLOCK_DELAY_SECS)
+            .ok_or(VaultError::MathOverflow)?;
+        ctx.accounts.early_unlock.set_inner(EarlyUnlock {
+            state: ctx.accounts.state.key(),
+            lock_tranche: ctx.accounts.lock_tranche.key(),
+            destination,
+            approvals,
+            ready_ts,
+        });
+
+        msg!(
+            "EARLY UNLOCK REQUESTED for tranche {}",
+            ctx.accounts.lock_tranche.key()
+        );
+        emit_cpi!(EarlyUnlockRequestedEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            state: ctx.accounts.state.key(),
+            lock_tranche: ctx.accounts.lock_tranche.key(),
+            destination,
+            approvals,
+            ready_ts,
+        });
+        Ok(())
+    }
+
+    /// Releases what is still locked in a tranche to the council's committed
+    /// destination once a council-approved `EarlyUnlock` has waited out its
+    /// delay, ignoring the vesting schedule but not the unlock mode. The rate
+    /// limit still applies: a non-burn release is capped at the current
+    /// `config.unlock_rate_limit` allowance, and the `EarlyUnlock` stays open
+    /// for later calls until the tranche is drained.
+    /// `remaining_accounts` carries the mint's transfer hook accounts, if any.
+    pub fn early_unlock<'info>(
+        ctx: Context<'_, '_, '_, 'info, EarlyUnlockRelease<'info>>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp >= ctx.accounts.early_unlock.ready_ts,
+            VaultError::TimelockActive
+        );
+        let tranche = &ctx.accounts.lock_tranche;
+        let mut amount = tranche.amount - tranche.claimed;
+        if tranche.unlock_mode != UnlockMode::Burn {
+            let state = &mut ctx.accounts.state;
+            state.roll_unlock_window(clock.unix_timestamp);
+            amount = amount.min(state.unlock_allowance(clock.unix_timestamp));
+            require!(amount > 0, VaultError::UnlockRateLimited);
+            state.unlock_window_amount += amount;
+        }
+
+        let state_key = ctx.accounts.state.key();
+        let seeds = &[
+            b"timelock".as_ref(),
+            state_key.as_ref(),
+            &[ctx.accounts.state.timelock_bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let accounts = ReleaseAccounts {
+            state: state_key,
+            mint: ctx.accounts.mint.to_account_info(),
+            mint_decimals: ctx.accounts.mint.decimals,
+            timelock_token_account: ctx.accounts.timelock_token_account.to_account_info(),
+            timelock_authority: ctx.accounts.timelock_authority.to_account_info(),
+            destination: ctx
+                .accounts
+                .destination_token_account
+                .as_ref()
+                .map(|a| a.to_account_info()),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            transfer_hook_accounts: ctx.remaining_accounts.to_vec(),
+        };
+        let tranche = &mut ctx.accounts.lock_tranche;
+        let event = release_tranche(
+            &mut ctx.accounts.state,
+            tranche,
+            &accounts,
+            amount,
+            &seeds[..],
+        )?;
+
+        msg!("EARLY UNLOCK of {} from tranche {}", amount, tranche.key());
+        emit_cpi!(EarlyUnlockEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            state: state_key,
+            lock_tranche: tranche.key(),
+            amount,
+            destination: event.destination,
+            tranche_remaining: event.tranche_remaining,
+            approvals: ctx.accounts.early_unlock.approvals,
+        });
+        if event.tranche_remaining == 0 {
+            tranche.close(ctx.accounts.rent_receiver.to_account_info())?;
+            ctx.accounts
+                .early_unlock
+                .close(ctx.accounts.authority.to_account_info())?;
+        }
+
+        ctx.accounts.timelock_token_account.reload()?;
+        if ctx.accounts.timelock_token_account.amount == 0 {
+            token_interface::close_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                CloseAccount {
+                    account: ctx.accounts.timelock_token_account.to_account_info(),
+                    destination: ctx.accounts.authority.to_account_info(),
+                    authority: ctx.accounts.timelock_authority.to_account_info(),
+                },
+                signer,
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Moves the authority's tokens back under the timelock PDA in a new
+    /// cliff `LockTranche` that matures `duration_secs` from now, for teams
+    /// extending their commitment.
+    pub fn relock(ctx: Context<Relock>, amount: u64, duration_secs: i64) -> Result<()> {
+        require!(amount > 0 && duration_secs > 0, VaultError::InvalidConfig);
+        let clock = Clock::get()?;
+
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.source_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.timelock_token_account.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        let state_key = ctx.accounts.state.key();
+        let index = ctx.accounts.state.next_lock_index()?;
+        ctx.accounts.state.total_locked = ctx.accounts.state.total_locked.saturating_add(amount);
+        let mut tranche = LockTranche::new(
+            state_key,
+            index,
+            ctx.accounts.mint.key(),
+            amount,
+            ctx.accounts.payer.key(),
+            clock.unix_timestamp,
+            &ctx.accounts.state.config,
+        )?;
+        tranche.release = LockRelease::Cliff;
+        tranche.bucket_count = 0;
+        tranche.unlock_ts = clock
+            .unix_timestamp
+            .checked_add(duration_secs)
+            .ok_or(VaultError::MathOverflow)?;
+        let unlock_ts = tranche.unlock_ts;
+        ctx.accounts.lock_tranche.set_inner(tranche);
+
+        emit_cpi!(RelockEvent {
+            version: EVENT_VERSION,
+            seq: ctx.accounts.state.next_event_seq(),
+            lock_tranche: ctx.accounts.lock_tranche.key(),
+            amount,
+            unlock_ts,
+        });
+        Ok(())
+    }
+
+    /// Mints a one-of-one receipt NFT for a lock tranche to the authority,
+    /// with Metaplex metadata pointing at `uri` (which describes the amount
+    /// and maturity). The mint authority is dropped afterwards.
+    pub fn mint_lock_receipt(ctx: Context<MintLockReceipt>, uri: String) -> Result<()> {
+        require!(uri.len() <= MAX_METADATA_URI_LEN, VaultError::InvalidConfig);
+        let (metadata, _) = Pubkey::find_program_address(
+            &[
+                b"metadata",
+                TOKEN_METADATA_PROGRAM_ID.as_ref(),
+                ctx.accounts.receipt_mint.key().as_ref(),
+            ],
+            &TOKEN_METADATA_PROGRAM_ID,
+        );
+        require_keys_eq!(
+            ctx.accounts.metadata.key(),
+            metadata,
+            VaultError::InvalidRoute
+        );
+        let state = &ctx.accounts.state;
+        let state_seeds: &[&[u8]] = &[
+            b"state",
+            state.seed_mint.as_ref(),
+            state.authority.as_ref(),
+            &[state.bump],
+        ];
+
+        token_interface::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.receipt_mint.to_account_info(),
+                    to: ctx.accounts.authority_receipt_account.to_account_info(),
+                    authority: ctx.accounts.state.to_account_info(),
+                },
+                &[state_seeds],
+            ),
+            1,
+        )?;
+
+        let name = format!("Lock #{}", ctx.accounts.lock_tranche.index);
+        let mut data = vec![CREATE_METADATA_ACCOUNT_V3];
+        for field in [name.as_str(), "LOCK", uri.as_str()] {
+            data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            data.extend_from_slice(field.as_bytes());
+        }
+        data.extend_from_slice(&0u16.to_le_bytes()); // seller_fee_basis_points
+        data.extend_from_slice(&[0, 0, 0]); // creators, collection, uses: None
+        data.push(0); // is_mutable
+        data.push(0); // collection_details: None
+        let ix = Instruction {
+            program_id: TOKEN_METADATA_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(metadata, false),
+                AccountMeta::new_readonly(ctx.accounts.receipt_mint.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.state.key(), true),
+                AccountMeta::new(ctx.accounts.payer.key(), true),
+                AccountMeta::new_readonly(ctx.accounts.state.key(), true),
+                AccountMeta::new_readonly(System::id(), false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.metadata.to_account_info(),
+                ctx.accounts.receipt_mint.to_account_info(),
+                ctx.accounts.state.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.token_metadata_program.to_account_info(),
+            ],
+            &[state_seeds],
+        )?;
+
+        token_interface::set_authority(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                SetAuthority {
+                    current_authority: ctx.accounts.state.to_account_info(),
+                    account_or_mint: ctx.accounts.receipt_mint.to_account_info(),
+                },
+                &[state_seeds],
+            ),
+            AuthorityType::MintTokens,
+            None,
+        )?;
+        Ok(())
+    }
+
+    /// Mints an MPL-Core asset for one `BuybackRecord` to
+    /// `config.proof_recipient`, with the buyback amounts as attributes, as a
+    /// shareable proof of the burn. Anyone may pay for it; each record gets at
+    /// most one proof, at `[b"proof", buyback_record]`.
+    pub fn mint_buyback_proof(ctx: Context<MintBuybackProof>, uri: String) -> Result<()> {
+        require!(uri.len() <= MAX_METADATA_URI_LEN, VaultError::InvalidConfig);
+        require!(
+            ctx.accounts.state.config.proof_recipient == Some(ctx.accounts.recipient.key()),
+            VaultError::InvalidConfig
+        );
+        let record = &ctx.accounts.buyback_record;
+        let record_key = record.key();
+        let asset_seeds: &[&[u8]] = &[b"proof", record_key.as_ref(), &[ctx.bumps.asset]];
+
+        let name = format!("Buyback #{}", record.index);
+        let mut data = vec![MPL_CORE_CREATE_V1, 0]; // data_state: AccountState
+        for field in [name.as_str(), uri.as_str()] {
+            data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            data.extend_from_slice(field.as_bytes());
+        }
+        let attributes = [
+            ("profit_lamports", record.profit_lamports),
+            ("tokens_bought", record.tokens_bought),
+            ("burn_amount", record.burn_amount),
+            ("lock_amount", record.lock_amount),
+            ("execution_price", record.execution_price),
+            ("timestamp", record.timestamp as u64),
+        ];
+        data.push(1); // plugins: Some
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.push(MPL_CORE_ATTRIBUTES_PLUGIN);
+        data.extend_from_slice(&(attributes.len() as u32).to_le_bytes());
+        for (key, value) in attributes {
+            let value = value.to_string();
+            for field in [key, value.as_str()] {
+                data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+                data.extend_from_slice(field.as_bytes());
+            }
+        }
+        data.push(0); // plugin authority: default
+
+        // Absent optional accounts are passed as the MPL-Core program id.
+        let ix = Instruction {
+            program_id: MPL_CORE_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(ctx.accounts.asset.key(), true),
+                AccountMeta::new_readonly(MPL_CORE_PROGRAM_ID, false),
+                AccountMeta::new_readonly(MPL_CORE_PROGRAM_ID, false),
+                AccountMeta::new(ctx.accounts.payer.key(), true),
+                AccountMeta::new_readonly(ctx.accounts.recipient.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.state.key(), false),
+                AccountMeta::new_readonly(System::id(), false),
+                AccountMeta::new_readonly(MPL_CORE_PROGRAM_ID, false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.asset.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.recipient.to_account_info(),
+                ctx.accounts.state.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.mpl_core_program.to_account_info(),
+            ],
+            &[asset_seeds],
+        )?;
+        Ok(())
+    }
+
+    /// Posts one `BuybackRecord` as a Wormhole message from the vault's
+    /// emitter PDA, `[b"emitter", state]`, so deployments of the token on
+    /// other chains can verify and mirror its burn statistics. The payload
+    /// is a Borsh `BuybackAttestation`. Anyone may pay for it, including the
+    /// bridge fee; each record is posted at most once, from
+    /// `[b"wormhole_message", buyback_record]`.
+    pub fn attest_buyback(ctx: Context<AttestBuyback>) -> Result<()> {
+        let bridge = ctx.accounts.bridge.try_borrow_data()?;
+        // Guardian set index and last lamports precede the config's
+        // expiration time and fee.
+        let fee = bridge
+            .get(16..24)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+            .ok_or(VaultError::InvalidRoute)?;
+        drop(bridge);
+        if fee > 0 {
+            invoke(
+                &system_instruction::transfer(
+                    ctx.accounts.payer.key,
+                    ctx.accounts.fee_collector.key,
+                    fee,
+                ),
+                &[
+                    ctx.accounts.payer.to_account_info(),
+                    ctx.accounts.fee_collector.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+        // The sequence account only exists after the emitter's first post.
+        let sequence = ctx
+            .accounts
+            .sequence
+            .try_borrow_data()?
+            .get(..8)
+            .map_or(0, |b| u64::from_le_bytes(b.try_into().unwrap()));
+
+        let state = &ctx.accounts.state;
+        let record = &ctx.accounts.buyback_record;
+        let payload = BuybackAttestation {
+            version: EVENT_VERSION,
+            state: state.key(),
+            mint: state.mint,
+            index: record.index,
+            profit_lamports: record.profit_lamports,
+            tokens_bought: record.tokens_bought,
+            burn_amount: record.burn_amount,
+            lock_amount: record.lock_amount,
+            execution_price: record.execution_price,
+            timestamp: record.timestamp,
+            total_burned: state.total_burned,
+            burned_bps_of_supply: state.burned_bps_of_supply,
+        }
+        .try_to_vec()?;
+        let mut data = vec![WORMHOLE_POST_MESSAGE];
+        data.extend_from_slice(&(record.index as u32).to_le_bytes()); // nonce
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(&payload);
+        data.push(WORMHOLE_CONSISTENCY_FINALIZED);
+
+        let state_key = state.key();
+        let record_key = record.key();
+        let message_seeds: &[&[u8]] = &[
+            b"wormhole_message",
+            record_key.as_ref(),
+            &[ctx.bumps.message],
+        ];
+        let emitter_seeds: &[&[u8]] = &[b"emitter", state_key.as_ref(), &[ctx.bumps.emitter]];
+        let ix = Instruction {
+            program_id: WORMHOLE_CORE_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(ctx.accounts.bridge.key(), false),
+                AccountMeta::new(ctx.accounts.message.key(), true),
+                AccountMeta::new_readonly(ctx.accounts.emitter.key(), true),
+                AccountMeta::new(ctx.accounts.sequence.key(), false),
+                AccountMeta::new(ctx.accounts.payer.key(), true),
+                AccountMeta::new(ctx.accounts.fee_collector.key(), false),
+                AccountMeta::new_readonly(sysvar::clock::ID, false),
+                AccountMeta::new_readonly(sysvar::rent::ID, false),
+                AccountMeta::new_readonly(System::id(), false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.bridge.to_account_info(),
+                ctx.accounts.message.to_account_info(),
+                ctx.accounts.emitter.to_account_info(),
+                ctx.accounts.sequence.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.fee_collector.to_account_info(),
+                ctx.accounts.clock.to_account_info(),
+                ctx.accounts.rent.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.wormhole_program.to_account_info(),
+            ],
+            &[message_seeds, emitter_seeds],
+        )?;
+
+        let state = &mut ctx.accounts.state;
+        emit_cpi!(BuybackAttestedEvent {
+            version: EVENT_VERSION,
+            seq: state.next_event_seq(),
+            state: state_key,
+            buyback_record: record_key,
+            emitter: ctx.accounts.emitter.key(),
+            sequence,
+        });
+        Ok(())
+    }
+
+    /// Creates the vault's `DonorLeaderboard`. Anyone may pay for it;
+    /// deposits update it once it is passed in.
+    pub fn init_leaderboard(ctx: Context<InitLeaderboard>) -> Result<()> {
+        ctx.accounts.leaderboard.state = ctx.accounts.state.key();
+        Ok(())
+    }
+
+    /// Creates the vault's `BuybackHistory` ring buffer. Anyone may pay for
+    /// it; cranks write to it once it is passed in.
+    pub fn init_history(ctx: Context<InitHistory>) -> Result<()> {
+        ctx.accounts.buyback_history.load_init()?.state = ctx.accounts.state.key();
+        Ok(())
+    }
+
+    /// Read-only preview of the next crank, returned via return data so
+    /// keepers and frontends can check it without building a route. Strategy
+    /// amounts are in lamports of swap input; the token split follows the
+    /// same weights once the route's output is known.
+    pub fn simulate_crank(ctx: Context<SimulateCrank>) -> Result<CrankPreview> {
+        let now = Clock::get()?.unix_timestamp;
+        let mut state = VaultState::clone(&ctx.accounts.state);
+
+        let interval_ok = !state.swap_pending && state.check_crank_interval(now).is_ok();
+        let profit = ctx
+            .accounts
+            .vault
+            .lamports()
+            .saturating_sub(state.reserved_lamports()?);
+        let spend = state.take_tranche(profit);
+        let liquidity_lamports = bps_of(spend, state.config.liquidity_sol_bps as u64)?;
+        let sol_burn_lamports = bps_of(spend, state.config.sol_burn_bps as u64)?;
+        let swap_lamports = spend - liquidity_lamports - sol_burn_lamports;
+        let protocol_fee_lamports = bps_of(swap_lamports, state.protocol_fee_bps as u64)?;
+        let swap_lamports = swap_lamports - protocol_fee_lamports;
+        let keeper_reward_lamports = bps_of(swap_lamports, state.keeper_reward_bps(now))?;
+        let distributable = swap_lamports - keeper_reward_lamports;
+
+        Ok(CrankPreview {
+            profit_lamports: profit,
+            swap_lamports,
+            liquidity_lamports,
+            sol_burn_lamports,
+            protocol_fee_lamports,
+            keeper_reward_lamports,
+            burn_lamports: bps_of(
+                distributable,
+                state.config.weight_of(DistributionStrategy::Burn) as u64,
+            )?,
+            lock_lamports: bps_of(
+                distributable,
+                state.config.weight_of(DistributionStrategy::Lock) as u64,
+            )?,
+            interval_ok,
+            profit_ok: swap_lamports > 0,
+        })
+    }
+
+    /// Read-only reason the next single-mint crank would fail, or `Ready`,
+    /// so keepers can log why they skipped without sending a failing
+    /// transaction.
+    pub fn check_crank(ctx: Context<SimulateCrank>) -> Result<CrankStatus> {
+        let now = Clock::get()?.unix_timestamp;
+        let mut state = VaultState::clone(&ctx.accounts.state);
+        if ctx.accounts.protocol_config.paused {
+            return Ok(CrankStatus::ProtocolPaused);
+        }
+        let status = state.interval_status(now)?;
+        if status == CrankStatus::Paused {
+            return Ok(status);
+        }
+        if state.swap_pending {
+            return Ok(CrankStatus::SwapPending);
+        }
+        if !state.config.buyback_mints.is_empty() {
+            return Ok(CrankStatus::MultiMintVault);
+        }
+        if state.config.base_mint.is_some() {
+            return Ok(CrankStatus::BaseMintVault);
+        }
+        if status != CrankStatus::Ready {
+            return Ok(status);
+        }
+
+        let profit = ctx
+            .accounts
+            .vault
+            .lamports()
+            .saturating_sub(state.reserved_lamports()?);
+        let spend = state.take_tranche(profit);
+        let withheld = bps_of(spend, state.config.liquidity_sol_bps as u64)?
+            + bps_of(spend, state.config.sol_burn_bps as u64)?;
+        if spend <= withheld {
+            return Ok(CrankStatus::NoProfit);
+        }
+        Ok(CrankStatus::Ready)
+    }
+
+    /// Read-only schedule of the outstanding tranches passed in
+    /// `remaining_accounts`, returned via return data so wallets don't have
+    /// to rebuild it from history. At most `MAX_SCHEDULE_TRANCHES` fit.
+    pub fn lock_schedule<'info>(
+        ctx: Context<'_, '_, '_, 'info, ViewState<'info>>,
+    ) -> Result<Vec<LockScheduleEntry>> {
+        require!(
+            ctx.remaining_accounts.len() <= MAX_SCHEDULE_TRANCHES,
+            VaultError::TooManyTranches
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let mut schedule = Vec::with_capacity(ctx.remaining_accounts.len());
+        for info in ctx.remaining_accounts {
+            let tranche = Account::<LockTranche>::try_from(info)?;
+            require_keys_eq!(
+                tranche.state,
+                ctx.accounts.state.key(),
+                VaultError::InvalidRoute
+            );
+            schedule.push(LockScheduleEntry {
+                lock_tranche: info.key(),
+                index: tranche.index,
+                mint: tranche.mint,
+                amount: tranche.amount,
+                claimed: tranche.claimed,
+                vested: tranche.vested(now),
+                unlock_ts: tranche.unlock_ts,
+                matured: now >= tranche.unlock_ts,
+            });
+        }
+        Ok(schedule)
+    }
+
+    /// Read-only lifetime totals via return data, so SDKs and other programs
+    /// needn't track the `VaultState` layout.
+    pub fn get_stats(ctx: Context<ViewState>) -> Result<VaultStats> {
+        let now = Clock::get()?.unix_timestamp;
+        let state = &ctx.accounts.state;
+        let (profit_lamports_7d, tokens_bought_7d) =
+            state.rolling_7d.trailing(ROLLING_7D_SECS, now);
+        let (profit_lamports_30d, tokens_bought_30d) =
+            state.rolling_30d.trailing(ROLLING_30D_SECS, now);
+        Ok(VaultStats {
+            vault_id: state.vault_id,
+            total_profit_lamports: state.total_profit_lamports,
+            total_burned: state.total_burned,
+            burned_bps_of_supply: state.burned_bps_of_supply,
+            total_locked: state.total_locked,
+            total_unlocked: state.total_unlocked,
+            total_cranks: state.crank_count,
+            lock_tranches: state.lock_count,
+            last_crank_ts: state.last_crank_ts,
+            paused: state.paused,
+            profit_lamports_7d,
+            tokens_bought_7d,
+            profit_lamports_30d,
+            tokens_bought_30d,
+        })
+    }
+
+    /// Permissionless consistency check for monitoring bots. Account-level
+    /// relationships are enforced by the `AssertInvariants` constraints; the
+    /// remaining state-level checks below fail with `InvariantViolated`.
+    pub fn assert_invariants(ctx: Context<AssertInvariants>) -> Result<()> {
+        let clock = Clock::get()?;
+        let state = &ctx.accounts.state;
+        let state_key = state.key();
+
+        let (_, state_bump) = Pubkey::find_program_address(
+            &[b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+            ctx.program_id,
+        );
+        invariant(state_bump == state.bump, "state bump")?;
+        let (_, vault_bump) =
+            Pubkey::find_program_address(&[b"vault", state_key.as_ref()], ctx.program_id);
+        invariant(vault_bump == state.vault_bump, "vault bump")?;
+        let (_, timelock_bump) =
+            Pubkey::find_program_address(&[b"timelock", state_key.as_ref()], ctx.program_id);
+        invariant(timelock_bump == state.timelock_bump, "timelock bump")?;
+
+        let vault = ctx.accounts.vault.to_account_info();
+        invariant(vault.owner == &System::id(), "vault owner")?;
+        invariant(vault.data_is_empty(), "vault data")?;
+        let rent = Rent::get()?;
+        invariant(vault.lamports() >= rent.minimum_balance(0), "vault rent")?;
+
+        invariant(state.config.validate().is_ok(), "config")?;
+        invariant(
+            ctx.accounts.burn_token_account.owner == state.burn_address,
+            "burn account owner",
+        )?;
+
+        if state.last_crank_ts == 0 {
+            invariant(state.timelock_unlock_ts == 0, "timelock before first crank")?;
+        } else {
+            invariant(
+                state.timelock_unlock_ts >= state.last_crank_ts,
+                "timelock ordering",
+            )?;
+            invariant(
+                state.last_crank_ts <= clock.unix_timestamp + state.config.clock_tolerance_secs,
+                "last crank in future",
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Distribution {
+    burn_amount: u64,
+    lock_amount: u64,
+    liquidity_amount: u64,
+    stake_rewards_amount: u64,
+    treasury_amount: u64,
+    retained_amount: u64,
+    holder_rewards_amount: u64,
+    fee_stake_amount: u64,
+    keeper_reward_amount: u64,
+}
+
+fn bps_of(amount: u64, bps: u64) -> Result<u64> {
+    let value = (amount as u128)
+        .checked_mul(bps as u128)
+        .ok_or(VaultError::MathOverflow)?
+        / 10_000;
+    u64::try_from(value).map_err(|_| error!(VaultError::MathOverflow))
+}
+
+/// Checks the crank gates and returns the lamports this crank will swap:
+/// the profit (or the current TWAP tranche of it), minus the SOL leg set
+/// aside for the `AddLiquidity` strategy.
+fn crank_profit(state: &mut VaultState, vault: &AccountInfo, now: i64) -> Result<u64> {
+    require!(
+        state.config.buyback_mints.is_empty(),
+        VaultError::MultiMintVault
+    );
+    crank_spend(state, vault, now)
+}
+
+fn crank_spend(state: &mut VaultState, vault: &AccountInfo, now: i64) -> Result<u64> {
+    require!(state.config.base_mint.is_none(), VaultError::BaseMintVault);
+    require!(!state.swap_pending, VaultError::SwapPending);
+    state.check_crank_interval(now)?;
+
+    let vault_balance = vault.lamports();
+    let floor = state.reserved_lamports()?;
+    require!(vault_balance > floor, VaultError::NoProfit);
+    let spend = state.take_tranche(vault_balance - floor);
+
+    let liquidity_lamports = bps_of(spend, state.config.liquidity_sol_bps as u64)?;
+    state.liquidity_reserve_lamports = state
+        .liquidity_reserve_lamports
+        .checked_add(liquidity_lamports)
+        .ok_or(VaultError::MathOverflow)?;
+    let sol_burn_lamports = bps_of(spend, state.config.sol_burn_bps as u64)?;
+    state.sol_burn_reserve_lamports = state
+        .sol_burn_reserve_lamports
+        .checked_add(sol_burn_lamports)
+        .ok_or(VaultError::MathOverflow)?;
+    let swap_lamports = spend - liquidity_lamports - sol_burn_lamports;
+    let protocol_fee_lamports = bps_of(swap_lamports, state.protocol_fee_bps as u64)?;
+    state.protocol_fee_reserve_lamports = state
+        .protocol_fee_reserve_lamports
+        .checked_add(protocol_fee_lamports)
+        .ok_or(VaultError::MathOverflow)?;
+    let swap_lamports = swap_lamports - protocol_fee_lamports;
+    require!(swap_lamports > 0, VaultError::NoProfit);
+    Ok(swap_lamports)
+}
+
+/// Tokens a swap delivered to the vault token account. A route that fills
+/// nothing fails the whole crank, so the interval, TWAP tranche and
+/// liquidity leg are left untouched and a keeper can retry immediately with
+/// a fresh route.
+fn swap_output(tokens_before: u64, tokens_after: u64) -> Result<u64> {
+    let bought = tokens_after.saturating_sub(tokens_before);
+    require!(bought > 0, VaultError::NoTokens);
+    Ok(bought)
+}
+
+/// Aggregator routes may be exact-out, so the wrapped amount is only an upper
+/// bound. Returns the lamports actually spent, credits anything unspent back
+/// to the TWAP budget, and enforces `config.exact_out_amount` when set.
+fn settle_swap_input(
+    state: &mut VaultState,
+    max_in_lamports: u64,
+    unspent_lamports: u64,
+    bought_amount: u64,
+) -> Result<u64> {
+    if let Some(target) = state.config.exact_out_amount {
+        require!(bought_amount >= target, VaultError::ExactOutNotMet);
+    }
+    let unspent = unspent_lamports.min(max_in_lamports);
+    if unspent > 0 && matches!(state.config.twap, TwapMode::Split { .. }) {
+        state.twap_budget_lamports = state.twap_budget_lamports.saturating_add(unspent);
+    }
+    Ok(max_in_lamports - unspent)
+}
+
+/// Moves `lamports` from the vault PDA into its WSOL ATA and syncs the
+/// native balance.
+fn wrap_sol<'info>(
+    vault: &AccountInfo<'info>,
+    wsol_ata: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    vault_seeds: &[&[u8]],
+    lamports: u64,
+) -> Result<()> {
+    let wrap_ix = system_instruction::transfer(vault.key, wsol_ata.key, lamports);
+    invoke_signed(
+        &wrap_ix,
+        &[vault.clone(), wsol_ata.clone(), system_program.clone()],
+        &[vault_seeds],
+    )?;
+
+    let sync_ix = token::spl_token::instruction::sync_native(token_program.key, wsol_ata.key)?;
+    invoke(&sync_ix, &[token_program.clone(), wsol_ata.clone()])?;
+    Ok(())
+}
+
+/// Splits a crank's `remaining_accounts` into the first `route_account_count`
+/// for the swap and the rest, which hold the mint's transfer hook accounts.
+fn split_route_accounts<'a, 'info>(
+    remaining_accounts: &'a [AccountInfo<'info>],
+    route_account_count: u8,
+) -> Result<(&'a [AccountInfo<'info>], &'a [AccountInfo<'info>])> {
+    let count = route_account_count as usize;
+    require!(count <= remaining_accounts.len(), VaultError::InvalidRoute);
+    Ok(remaining_accounts.split_at(count))
+}
+
+/// With a non-empty `config.amm_allowlist`, every executable account in the
+/// route must be the aggregator, a token/system program, or an allowlisted
+/// AMM.
+fn check_route_programs(
+    route_accounts: &[AccountInfo],
+    aggregator: Aggregator,
+    amm_allowlist: &[Pubkey],
+) -> Result<()> {
+    if amm_allowlist.is_empty() {
+        return Ok(());
+    }
+    for account in route_accounts.iter().filter(|a| a.executable) {
+        let key = account.key();
+        let infrastructure = key == aggregator.program_id()
+            || key == token::ID
+            || key == anchor_spl::token_2022::ID
+            || key == anchor_spl::associated_token::ID
+            || key == System::id()
+            || key == SPL_MEMO_PROGRAM_ID;
+        require!(
+            infrastructure || amm_allowlist.contains(&key),
+            VaultError::AmmNotAllowed
+        );
+    }
+    Ok(())
+}
+
+/// Aggregator CPI swap (WSOL -> BurnFlip token) through the vault's
+/// `config.aggregator`, after checking the route against
+/// `config.amm_allowlist`.
+fn aggregator_swap<'info>(
+    aggregator_program: &AccountInfo<'info>,
+    route_accounts: &[AccountInfo<'info>],
+    data: Vec<u8>,
+    vault_key: Pubkey,
+    vault_seeds: &[&[u8]],
+    config: &VaultConfig,
+) -> Result<()> {
+    require_keys_eq!(
+        aggregator_program.key(),
+        config.aggregator.program_id(),
+        VaultError::InvalidRoute
+    );
+    check_route_programs(route_accounts, config.aggregator, &config.amm_allowlist)?;
+    AggregatorAdapter::new(config.aggregator, vault_key).swap(route_accounts, data, vault_seeds)
+}
+
+/// Swap CPI of the configured aggregator. Routes come from the aggregator's
+/// own quote API as instruction data plus accounts, are passed through
+/// unchanged and run with the vault PDA, which owns the WSOL ATA, as the
+/// user authority. DFlow enforces the quoted minimum output itself.
+struct AggregatorAdapter {
+    program_id: Pubkey,
+    vault_key: Pubkey,
+}
+
+impl AggregatorAdapter {
+    fn new(aggregator: Aggregator, vault_key: Pubkey) -> Self {
+        Self {
+            program_id: aggregator.program_id(),
+            vault_key,
+        }
+    }
+
+    fn swap<'info>(
+        &self,
+        route_accounts: &[AccountInfo<'info>],
+        data: Vec<u8>,
+        vault_seeds: &[&[u8]],
+    ) -> Result<()> {
+        let ix = vault_signed_route(self.program_id, route_accounts, data, self.vault_key);
+        invoke_signed(&ix, route_accounts, &[vault_seeds])?;
+        Ok(())
+    }
+}
+
+/// Instruction over `route_accounts` as given, with the vault marked as
+/// signer wherever it appears.
+fn vault_signed_route(
+    program_id: Pubkey,
+    route_accounts: &[AccountInfo],
+    data: Vec<u8>,
+    vault_key: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: route_accounts
+            .iter()
+            .map(|a| AccountMeta {
+                pubkey: *a.key,
+                is_signer: a.is_signer || *a.key == vault_key,
+                is_writable: a.is_writable,
+            })
+            .collect(),
+        data,
+    }
+}
+
+/// Hash of a route's account keys, in order, as committed by
+/// `set_approved_route`.
+fn route_accounts_hash(route_accounts: &[AccountInfo]) -> [u8; 32] {
+    let keys: Vec<&[u8]> = route_accounts.iter().map(|a| a.key.as_ref()).collect();
+    hashv(&keys).to_bytes()
+}
+
+/// `in_amount` and `quoted_out_amount` of Jupiter route data.
+fn jupiter_route_amounts(data: &[u8]) -> Option<(u64, u64)> {
+    let tail = data.len().checked_sub(JUPITER_ROUTE_TAIL_LEN)?;
+    let read = |at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+    Some((read(tail), read(tail + 8)))
+}
+
+/// Verified collection of a Metaplex `MetadataV1` account for `mint`.
+fn metadata_verified_collection(metadata: &AccountInfo, mint: &Pubkey) -> Result<Option<Pubkey>> {
+    let (expected, _) = Pubkey::find_program_address(
+        &[
+            b"metadata",
+            TOKEN_METADATA_PROGRAM_ID.as_ref(),
+            mint.as_ref(),
+        ],
+        &TOKEN_METADATA_PROGRAM_ID,
+    );
+    require!(
+        metadata.key() == expected && *metadata.owner == TOKEN_METADATA_PROGRAM_ID,
+        VaultError::NotOperator
+    );
+    let data = metadata.try_borrow_data()?;
+    let bytes = |at: usize, len: usize| {
+        data.get(at..at + len)
+            .ok_or_else(|| error!(VaultError::NotOperator))
+    };
+    let u32_at = |at: usize| -> Result<usize> {
+        Ok(u32::from_le_bytes(bytes(at, 4)?.try_into().unwrap()) as usize)
+    };
+    // Key, update authority, mint, then name, symbol and uri.
+    require!(bytes(0, 1)? == [METADATA_V1_KEY], VaultError::NotOperator);
+    let mut at = 1 + 32 + 32;
+    for _ in 0..3 {
+        at += 4 + u32_at(at)?;
+    }
+    at += 2; // seller_fee_basis_points
+    if bytes(at, 1)? == [1] {
+        at += 4 + u32_at(at + 1)? * METADATA_CREATOR_LEN;
+    }
+    at += 1 + 2; // creators tag, primary_sale_happened, is_mutable
+                 // edition_nonce and token_standard are `Option<u8>`.
+    for _ in 0..2 {
+        at += if bytes(at, 1)? == [1] { 2 } else { 1 };
+    }
+    if bytes(at, 1)? != [1] || bytes(at + 1, 1)? != [1] {
+        return Ok(None);
+    }
+    Ok(Some(Pubkey::new_from_array(
+        bytes(at + 2, 32)?.try_into().unwrap(),
+    )))
+}
+
+/// Direct Raydium CPMM `swap_base_input` for WSOL -> token. The vault PDA
+/// owns the input ATA and signs as the swap payer.
+fn raydium_swap(
+    accounts: &CrankRaydium<'_>,
+    amount_in: u64,
+    minimum_amount_out: u64,
+    vault_seeds: &[&[u8]],
+) -> Result<()> {
+    let mut data = Vec::with_capacity(24);
+    data.extend_from_slice(&RAYDIUM_SWAP_BASE_INPUT);
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&minimum_amount_out.to_le_bytes());
+
+    let account_infos = [
+        accounts.distribution.vault.to_account_info(),
+        accounts.raydium_authority.to_account_info(),
+        accounts.raydium_amm_config.to_account_info(),
+        accounts.raydium_pool_state.to_account_info(),
+        accounts.vault_wsol_ata.to_account_info(),
+        accounts.distribution.vault_token_account.to_account_info(),
+        accounts.raydium_input_vault.to_account_info(),
+        accounts.raydium_output_vault.to_account_info(),
+        accounts.token_program.to_account_info(),
+        accounts.distribution.mint_token_program.to_account_info(),
+        accounts.wsol_mint.to_account_info(),
+        accounts.distribution.mint.to_account_info(),
+        accounts.raydium_observation_state.to_account_info(),
+    ];
+    let ix = Instruction {
+        program_id: RAYDIUM_CPMM_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(accounts.distribution.vault.key(), true),
+            AccountMeta::new_readonly(accounts.raydium_authority.key(), false),
+            AccountMeta::new_readonly(accounts.raydium_amm_config.key(), false),
+            AccountMeta::new(accounts.raydium_pool_state.key(), false),
+            AccountMeta::new(accounts.vault_wsol_ata.key(), false),
+            AccountMeta::new(accounts.distribution.vault_token_account.key(), false),
+            AccountMeta::new(accounts.raydium_input_vault.key(), false),
+            AccountMeta::new(accounts.raydium_output_vault.key(), false),
+            AccountMeta::new_readonly(accounts.token_program.key(), false),
+            AccountMeta::new_readonly(accounts.distribution.mint_token_program.key(), false),
+            AccountMeta::new_readonly(accounts.wsol_mint.key(), false),
+            AccountMeta::new_readonly(accounts.distribution.mint.key(), false),
+            AccountMeta::new(accounts.raydium_observation_state.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(&ix, &account_infos, &[vault_seeds])?;
+    Ok(())
+}
+
+/// Direct Orca Whirlpool `swap` for WSOL -> token, exact input. Whirlpool
+/// token A/B ordering follows mint key ordering, which fixes the direction.
+fn whirlpool_swap(
+    accounts: &CrankWhirlpool<'_>,
+    amount_in: u64,
+    minimum_amount_out: u64,
+    vault_seeds: &[&[u8]],
+) -> Result<()> {
+    let a_to_b = accounts.wsol_mint.key() < accounts.distribution.mint.key();
+    let (owner_account_a, owner_account_b) = if a_to_b {
+        (
+            accounts.vault_wsol_ata.to_account_info(),
+            accounts.distribution.vault_token_account.to_account_info(),
+        )
+    } else {
+        (
+            accounts.distribution.vault_token_account.to_account_info(),
+            accounts.vault_wsol_ata.to_account_info(),
+        )
+    };
+    let sqrt_price_limit = if a_to_b {
+        WHIRLPOOL_MIN_SQRT_PRICE
+    } else {
+        WHIRLPOOL_MAX_SQRT_PRICE
+    };
+
+    let mut data = Vec::with_capacity(42);
+    data.extend_from_slice(&WHIRLPOOL_SWAP);
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&minimum_amount_out.to_le_bytes());
+    data.extend_from_slice(&sqrt_price_limit.to_le_bytes());
+    data.push(1); // amount_specified_is_input
+    data.push(a_to_b as u8);
+
+    let ix = Instruction {
+        program_id: WHIRLPOOL_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(accounts.token_program.key(), false),
+            AccountMeta::new_readonly(accounts.distribution.vault.key(), true),
+            AccountMeta::new(accounts.whirlpool.key(), false),
+            AccountMeta::new(owner_account_a.key(), false),
+            AccountMeta::new(accounts.whirlpool_token_vault_a.key(), false),
+            AccountMeta::new(owner_account_b.key(), false),
+            AccountMeta::new(accounts.whirlpool_token_vault_b.key(), false),
+            AccountMeta::new(accounts.tick_array_0.key(), false),
+            AccountMeta::new(accounts.tick_array_1.key(), false),
+            AccountMeta::new(accounts.tick_array_2.key(), false),
+            AccountMeta::new(accounts.whirlpool_oracle.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &ix,
+        &[
+            accounts.token_program.to_account_info(),
+            accounts.distribution.vault.to_account_info(),
+            accounts.whirlpool.to_account_info(),
+            owner_account_a,
+            accounts.whirlpool_token_vault_a.to_account_info(),
+            owner_account_b,
+            accounts.whirlpool_token_vault_b.to_account_info(),
+            accounts.tick_array_0.to_account_info(),
+            accounts.tick_array_1.to_account_info(),
+            accounts.tick_array_2.to_account_info(),
+            accounts.whirlpool_oracle.to_account_info(),
+        ],
+        &[vault_seeds],
+    )?;
+    Ok(())
+}
+
+/// `Market` quote and base lot sizes of an OpenBook v2 market.
+fn openbook_lot_sizes(market: &AccountInfo) -> Result<(i64, i64)> {
+    let data = market.try_borrow_data()?;
+    let field = |at: usize| {
+        data.get(at..at + 8)
+            .map(|b| i64::from_le_bytes(b.try_into().unwrap()))
+            .filter(|&size| size > 0)
+            .ok_or_else(|| error!(VaultError::PoolNotConfigured))
+    };
+    Ok((
+        field(OPENBOOK_LOT_SIZES_OFFSET)?,
+        field(OPENBOOK_LOT_SIZES_OFFSET + 8)?,
+    ))
+}
+
+/// Bid price in quote lots per base lot: the token's lamport price from the
+/// token and SOL USD feeds, less `bids.discount_bps`.
+fn openbook_bid_price_lots(accounts: &CrankOpenBook, bids: &OpenBookBids, now: i64) -> Result<i64> {
+    let price_oracle = accounts
+        .distribution
+        .price_oracle
+        .as_ref()
+        .ok_or(VaultError::InvalidOracle)?;
+    require!(
+        accounts.distribution.state.config.price_oracle == Some(price_oracle.key())
+            && accounts.sol_oracle.key() == bids.sol_oracle,
+        VaultError::InvalidOracle
+    );
+    let token_price = read_oracle_price(price_oracle)?;
+    let sol_price = read_oracle_price(&accounts.sol_oracle)?;
+    for price in [&token_price, &sol_price] {
+        require!(price.price > 0, VaultError::InvalidOracle);
+        require!(
+            now.saturating_sub(price.publish_time) <= bids.max_staleness_secs as i64,
+            VaultError::OracleStale
+        );
+    }
+    let (quote_lot_size, base_lot_size) = openbook_lot_sizes(&accounts.market)?;
+
+    // lamports per base unit = token_usd / sol_usd * 10^(9 - decimals)
+    let mut numerator = (token_price.price as u128)
+        .checked_mul(base_lot_size as u128)
+        .and_then(|v| v.checked_mul(10_000 - bids.discount_bps as u128))
+        .ok_or(VaultError::MathOverflow)?;
+    let mut denominator = (sol_price.price as u128)
+        .checked_mul(quote_lot_size as u128)
+        .and_then(|v| v.checked_mul(10_000))
+        .ok_or(VaultError::MathOverflow)?;
+    let expo = token_price.expo - sol_price.expo + token::spl_token::native_mint::DECIMALS as i32
+        - accounts.distribution.mint.decimals as i32;
+    let scale = 10u128
+        .checked_pow(expo.unsigned_abs())
+        .ok_or(VaultError::MathOverflow)?;
+    if expo >= 0 {
+        numerator = numerator
+            .checked_mul(scale)
+            .ok_or(VaultError::MathOverflow)?;
+    } else {
+        denominator = denominator
+            .checked_mul(scale)
+            .ok_or(VaultError::MathOverflow)?;
+    }
+    let price_lots =
+        i64::try_from(numerator / denominator).map_err(|_| error!(VaultError::MathOverflow))?;
+    require!(price_lots > 0, VaultError::InvalidOracle);
+    Ok(price_lots)
+}
+
+/// Cancels the vault's bids and settles its open orders account, filled
+/// tokens into the vault token account and free WSOL into the WSOL ATA.
+fn openbook_cancel_and_settle(accounts: &CrankOpenBook, vault_seeds: &[&[u8]]) -> Result<()> {
+    let mut data = OPENBOOK_CANCEL_ALL_ORDERS.to_vec();
+    data.extend_from_slice(&[1, 0]); // side_option: Some(Bid)
+    data.push(OPENBOOK_MAX_ORDERS);
+    // Absent optional accounts are passed as the OpenBook program id.
+    let ix = Instruction {
+        program_id: OPENBOOK_V2_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(accounts.distribution.vault.key(), true),
+            AccountMeta::new(accounts.open_orders_account.key(), false),
+            AccountMeta::new_readonly(OPENBOOK_V2_PROGRAM_ID, false),
+            AccountMeta::new_readonly(accounts.market.key(), false),
+            AccountMeta::new(accounts.bids.key(), false),
+            AccountMeta::new(accounts.asks.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &ix,
+        &[
+            accounts.distribution.vault.to_account_info(),
+            accounts.open_orders_account.to_account_info(),
+            accounts.openbook_program.to_account_info(),
+            accounts.market.to_account_info(),
+            accounts.bids.to_account_info(),
+            accounts.asks.to_account_info(),
+        ],
+        &[vault_seeds],
+    )?;
+
+    // The keeper covers the penalty OpenBook charges for settling with
+    // unconsumed fill events.
+    let ix = Instruction {
+        program_id: OPENBOOK_V2_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(accounts.distribution.vault.key(), true),
+            AccountMeta::new(accounts.distribution.payer.key(), true),
+            AccountMeta::new(accounts.open_orders_account.key(), false),
+            AccountMeta::new(accounts.market.key(), false),
+            AccountMeta::new_readonly(accounts.market_authority.key(), false),
+            AccountMeta::new(accounts.market_base_vault.key(), false),
+            AccountMeta::new(accounts.market_quote_vault.key(), false),
+            AccountMeta::new(accounts.distribution.vault_token_account.key(), false),
+            AccountMeta::new(accounts.vault_wsol_ata.key(), false),
+            AccountMeta::new_readonly(OPENBOOK_V2_PROGRAM_ID, false),
+            AccountMeta::new_readonly(accounts.token_program.key(), false),
+            AccountMeta::new_readonly(System::id(), false),
+        ],
+        data: OPENBOOK_SETTLE_FUNDS.to_vec(),
+    };
+    invoke_signed(
+        &ix,
+        &[
+            accounts.distribution.vault.to_account_info(),
+            accounts.distribution.payer.to_account_info(),
+            accounts.open_orders_account.to_account_info(),
+            accounts.market.to_account_info(),
+            accounts.market_authority.to_account_info(),
+            accounts.market_base_vault.to_account_info(),
+            accounts.market_quote_vault.to_account_info(),
+            accounts.distribution.vault_token_account.to_account_info(),
+            accounts.vault_wsol_ata.to_account_info(),
+            accounts.openbook_program.to_account_info(),
+            accounts.token_program.to_account_info(),
+            accounts.system_program.to_account_info(),
+        ],
+        &[vault_seeds],
+    )?;
+    Ok(())
+}
+
+/// Posts a post-only bid for up to `max_quote_lots` of WSOL at `price_lots`.
+fn openbook_place_bid(
+    accounts: &CrankOpenBook,
+    price_lots: i64,
+    max_quote_lots: i64,
+    client_order_id: u64,
+    vault_seeds: &[&[u8]],
+) -> Result<()> {
+    let mut data = Vec::with_capacity(51);
+    data.extend_from_slice(&OPENBOOK_PLACE_ORDER);
+    data.push(0); // side: Bid
+    data.extend_from_slice(&price_lots.to_le_bytes());
+    data.extend_from_slice(&i64::MAX.to_le_bytes()); // max_base_lots
+    data.extend_from_slice(&max_quote_lots.to_le_bytes());
+    data.extend_from_slice(&client_order_id.to_le_bytes());
+    data.push(OPENBOOK_POST_ONLY);
+    data.extend_from_slice(&0u64.to_le_bytes()); // expiry_timestamp: never
+    data.push(0); // self_trade_behavior: DecrementTake
+    data.push(OPENBOOK_MAX_ORDERS);
+
+    let oracle_key = |oracle: &Option<UncheckedAccount>| {
+        oracle.as_ref().map_or(OPENBOOK_V2_PROGRAM_ID, |a| a.key())
+    };
+    let ix = Instruction {
+        program_id: OPENBOOK_V2_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(accounts.distribution.vault.key(), true),
+            AccountMeta::new(accounts.open_orders_account.key(), false),
+            AccountMeta::new_readonly(OPENBOOK_V2_PROGRAM_ID, false),
+            AccountMeta::new(accounts.vault_wsol_ata.key(), false),
+            AccountMeta::new(accounts.market.key(), false),
+            AccountMeta::new(accounts.bids.key(), false),
+            AccountMeta::new(accounts.asks.key(), false),
+            AccountMeta::new(accounts.event_heap.key(), false),
+            AccountMeta::new(accounts.market_quote_vault.key(), false),
+            AccountMeta::new_readonly(oracle_key(&accounts.market_oracle_a), false),
+            AccountMeta::new_readonly(oracle_key(&accounts.market_oracle_b), false),
+            AccountMeta::new_readonly(accounts.token_program.key(), false),
+        ],
+        data,
+    };
+    let mut infos = vec![
+        accounts.distribution.vault.to_account_info(),
+        accounts.open_orders_account.to_account_info(),
+        accounts.openbook_program.to_account_info(),
+        accounts.vault_wsol_ata.to_account_info(),
+        accounts.market.to_account_info(),
+        accounts.bids.to_account_info(),
+        accounts.asks.to_account_info(),
+        accounts.event_heap.to_account_info(),
+        accounts.market_quote_vault.to_account_info(),
+        accounts.token_program.to_account_info(),
+    ];
+    infos.extend(
+        [&accounts.market_oracle_a, &accounts.market_oracle_b]
+            .into_iter()
+            .flatten()
+            .map(|a| a.to_account_info()),
+    );
+    invoke_signed(&ix, &infos, &[vault_seeds])?;
+    Ok(())
+}
+
+/// Direct pump.fun AMM `buy` (quote WSOL -> base token). Only the leading
+/// accounts the vault cares about are named; pump's fee, creator and event
+/// accounts follow in order via `trailing_accounts` so the adapter keeps up
+/// as pump appends accounts to the instruction.
+fn pump_amm_swap<'info>(
+    accounts: &CrankPumpAmm<'info>,
+    trailing_accounts: &[AccountInfo<'info>],
+    base_amount_out: u64,
+    max_quote_amount_in: u64,
+    vault_seeds: &[&[u8]],
+) -> Result<()> {
+    let mut data = Vec::with_capacity(24);
+    data.extend_from_slice(&PUMP_AMM_BUY);
+    data.extend_from_slice(&base_amount_out.to_le_bytes());
+    data.extend_from_slice(&max_quote_amount_in.to_le_bytes());
+
+    let mut metas = vec![
+        AccountMeta::new(accounts.pump_pool.key(), false),
+        AccountMeta::new(accounts.distribution.vault.key(), true),
+        AccountMeta::new_readonly(accounts.pump_global_config.key(), false),
+        AccountMeta::new_readonly(accounts.distribution.mint.key(), false),
+        AccountMeta::new_readonly(accounts.wsol_mint.key(), false),
+        AccountMeta::new(accounts.distribution.vault_token_account.key(), false),
+        AccountMeta::new(accounts.vault_wsol_ata.key(), false),
+        AccountMeta::new(accounts.pump_pool_base_token_account.key(), false),
+        AccountMeta::new(accounts.pump_pool_quote_token_account.key(), false),
+    ];
+    let mut account_infos = vec![
+        accounts.pump_pool.to_account_info(),
+        accounts.distribution.vault.to_account_info(),
+        accounts.pump_global_config.to_account_info(),
+        accounts.distribution.mint.to_account_info(),
+        accounts.wsol_mint.to_account_info(),
+        accounts.distribution.vault_token_account.to_account_info(),
+        accounts.vault_wsol_ata.to_account_info(),
+        accounts.pump_pool_base_token_account.to_account_info(),
+        accounts.pump_pool_quote_token_account.to_account_info(),
+    ];
+    for account in trailing_accounts {
+        metas.push(AccountMeta {
+            pubkey: *account.key,
+            is_signer: account.is_signer,
+            is_writable: account.is_writable,
+        });
+        account_infos.push(account.clone());
+    }
+
+    let ix = Instruction {
+        program_id: PUMP_AMM_PROGRAM_ID,
+        accounts: metas,
+        data,
+    };
+    invoke_signed(&ix, &account_infos, &[vault_seeds])?;
+    Ok(())
+}
+
+/// Raydium CPMM `deposit` with the state PDA as owner. CPMM orders token 0/1
+/// by mint key, so the WSOL and token legs are mapped accordingly.
+/// Streamflow amounts and times for the unclaimed part of a tranche: what
+/// has already vested is released as the cliff, the rest streams per-second
+/// until `unlock_ts`.
+struct StreamSchedule {
+    start_time: u64,
+    net_amount: u64,
+    amount_per_period: u64,
+    cliff_amount: u64,
+}
+
+impl StreamSchedule {
+    fn for_tranche(tranche: &LockTranche, now: i64) -> Result<Self> {
+        let net_amount = tranche.amount - tranche.claimed;
+        if tranche.release == LockRelease::Cliff {
+            return Ok(Self {
+                start_time: tranche.unlock_ts.max(now) as u64,
+                net_amount,
+                amount_per_period: net_amount,
+                cliff_amount: net_amount,
+            });
+        }
+        let stream_start = match tranche.release {
+            LockRelease::CliffStream { cliff_secs, .. } => tranche.start_ts + cliff_secs as i64,
+            _ => tranche.start_ts,
+        };
+        let start = now.max(stream_start);
+        let cliff_amount = tranche.vested(start).saturating_sub(tranche.claimed);
+        let streamed = (net_amount - cliff_amount) as u128;
+        let secs = tranche.unlock_ts.saturating_sub(start).max(1) as u128;
+        Ok(Self {
+            start_time: start as u64,
+            net_amount,
+            amount_per_period: streamed.div_ceil(secs).max(1) as u64,
+            cliff_amount,
+        })
+    }
+}
+
+fn streamflow_create<'info>(
+    accounts: &StreamLock<'info>,
+    streamflow_accounts: &[AccountInfo<'info>],
+    schedule: &StreamSchedule,
+    timelock_seeds: &[&[u8]],
+) -> Result<()> {
+    let [metadata, escrow_tokens, treasury, treasury_tokens, withdrawor, partner, partner_tokens, fee_oracle, rent, program] =
+        streamflow_accounts
+    else {
+        return err!(VaultError::InvalidRoute);
+    };
+    require_keys_eq!(
+        program.key(),
+        STREAMFLOW_PROGRAM_ID,
+        VaultError::InvalidRoute
+    );
+
+    let mut name = [0u8; 64];
+    let label = format!("Lock #{}", accounts.lock_tranche.index);
+    name[..label.len()].copy_from_slice(label.as_bytes());
+
+    let mut data = Vec::with_capacity(8 + 48 + 6 + 64 + 8 + 2);
+    data.extend_from_slice(&STREAMFLOW_CREATE);
+    data.extend_from_slice(&schedule.start_time.to_le_bytes());
+    data.extend_from_slice(&schedule.net_amount.to_le_bytes());
+    data.extend_from_slice(&1u64.to_le_bytes()); // period
+    data.extend_from_slice(&schedule.amount_per_period.to_le_bytes());
+    data.extend_from_slice(&schedule.start_time.to_le_bytes()); // cliff
+    data.extend_from_slice(&schedule.cliff_amount.to_le_bytes());
+    // Not cancelable, transferable, or top-up-able by either side, and no
+    // automatic withdrawal.
+    data.extend_from_slice(&[0; 6]);
+    data.extend_from_slice(&name);
+    data.extend_from_slice(&0u64.to_le_bytes()); // withdraw_frequency
+    data.extend_from_slice(&[0, 0]); // pausable, can_update_rate
+
+    let ix = Instruction {
+        program_id: STREAMFLOW_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(accounts.timelock_authority.key(), true),
+            AccountMeta::new(accounts.timelock_token_account.key(), false),
+            AccountMeta::new(accounts.recipient.key(), false),
+            AccountMeta::new(metadata.key(), true),
+            AccountMeta::new(escrow_tokens.key(), false),
+            AccountMeta::new(accounts.recipient_token_account.key(), false),
+            AccountMeta::new(treasury.key(), false),
+            AccountMeta::new(treasury_tokens.key(), false),
+            AccountMeta::new(withdrawor.key(), false),
+            AccountMeta::new(partner.key(), false),
+            AccountMeta::new(partner_tokens.key(), false),
+            AccountMeta::new_readonly(accounts.mint.key(), false),
+            AccountMeta::new_readonly(fee_oracle.key(), false),
+            AccountMeta::new_readonly(rent.key(), false),
+            AccountMeta::new_readonly(STREAMFLOW_PROGRAM_ID, false),
+            AccountMeta::new_readonly(accounts.token_program.key(), false),
+            AccountMeta::new_readonly(accounts.associated_token_program.key(), false),
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &ix,
+        &[
+            accounts.timelock_authority.to_account_info(),
+            accounts.timelock_token_account.to_account_info(),
+            accounts.recipient.to_account_info(),
+            metadata.clone(),
+            escrow_tokens.clone(),
+            accounts.recipient_token_account.to_account_info(),
+            treasury.clone(),
+            treasury_tokens.clone(),
+            withdrawor.clone(),
+            partner.clone(),
+            partner_tokens.clone(),
+            accounts.mint.to_account_info(),
+            fee_oracle.clone(),
+            rent.clone(),
+            program.clone(),
+            accounts.token_program.to_account_info(),
+            accounts.associated_token_program.to_account_info(),
+            accounts.system_program.to_account_info(),
+        ],
+        &[timelock_seeds],
+    )?;
+    Ok(())
+}
+
+fn raydium_deposit(
+    accounts: &AddLiquidity<'_>,
+    lp_token_amount: u64,
+    maximum_sol_amount: u64,
+    maximum_token_amount: u64,
+    state_seeds: &[&[u8]],
+) -> Result<()> {
+    let sol_is_token_0 = accounts.wsol_mint.key() < accounts.mint.key();
+    let (token_0_account, token_1_account, mint_0, mint_1, maximum_0, maximum_1) = if sol_is_token_0
+    {
+        (
+            accounts.state_wsol_ata.to_account_info(),
+            accounts.vault_token_account.to_account_info(),
+            accounts.wsol_mint.to_account_info(),
+            accounts.mint.to_account_info(),
+            maximum_sol_amount,
+            maximum_token_amount,
+        )
+    } else {
+        (
+            accounts.vault_token_account.to_account_info(),
+            accounts.state_wsol_ata.to_account_info(),
+            accounts.mint.to_account_info(),
+            accounts.wsol_mint.to_account_info(),
+            maximum_token_amount,
+            maximum_sol_amount,
+        )
+    };
+
+    let mut data = Vec::with_capacity(32);
+    data.extend_from_slice(&RAYDIUM_DEPOSIT);
+    data.extend_from_slice(&lp_token_amount.to_le_bytes());
+    data.extend_from_slice(&maximum_0.to_le_bytes());
+    data.extend_from_slice(&maximum_1.to_le_bytes());
+
+    let ix = Instruction {
+        program_id: RAYDIUM_CPMM_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(accounts.state.key(), true),
+            AccountMeta::new_readonly(accounts.raydium_authority.key(), false),
+            AccountMeta::new(accounts.raydium_pool_state.key(), false),
+            AccountMeta::new(accounts.burn_lp_token_account.key(), false),
+            AccountMeta::new(token_0_account.key(), false),
+            AccountMeta::new(token_1_account.key(), false),
+            AccountMeta::new(accounts.raydium_token_0_vault.key(), false),
+            AccountMeta::new(accounts.raydium_token_1_vault.key(), false),
+            AccountMeta::new_readonly(accounts.token_program.key(), false),
+            AccountMeta::new_readonly(accounts.token_2022_program.key(), false),
+            AccountMeta::new_readonly(mint_0.key(), false),
+            AccountMeta::new_readonly(mint_1.key(), false),
+            AccountMeta::new(accounts.lp_mint.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &ix,
+        &[
+            accounts.state.to_account_info(),
+            accounts.raydium_authority.to_account_info(),
+            accounts.raydium_pool_state.to_account_info(),
+            accounts.burn_lp_token_account.to_account_info(),
+            token_0_account,
+            token_1_account,
+            accounts.raydium_token_0_vault.to_account_info(),
+            accounts.raydium_token_1_vault.to_account_info(),
+            accounts.token_program.to_account_info(),
+            accounts.token_2022_program.to_account_info(),
+            mint_0,
+            mint_1,
+            accounts.lp_mint.to_account_info(),
+        ],
+        &[state_seeds],
+    )?;
+    Ok(())
+}
+
+/// Raydium CPMM `initialize` with the vault PDA as creator. Token 0/1 follow
+/// the same mint-key ordering as `raydium_deposit`.
+fn raydium_initialize(
+    accounts: &BootstrapRaydiumPool<'_>,
+    sol_amount: u64,
+    token_amount: u64,
+    open_time: u64,
+    vault_seeds: &[&[u8]],
+) -> Result<()> {
+    let sol_is_token_0 = accounts.wsol_mint.key() < accounts.mint.key();
+    let (token_0_account, token_1_account, mint_0, mint_1, amount_0, amount_1) = if sol_is_token_0 {
+        (
+            accounts.vault_wsol_ata.to_account_info(),
+            accounts.vault_mint_ata.to_account_info(),
+            accounts.wsol_mint.to_account_info(),
+            accounts.mint.to_account_info(),
+            sol_amount,
+            token_amount,
+        )
+    } else {
+        (
+            accounts.vault_mint_ata.to_account_info(),
+            accounts.vault_wsol_ata.to_account_info(),
+            accounts.mint.to_account_info(),
+            accounts.wsol_mint.to_account_info(),
+            token_amount,
+            sol_amount,
+        )
+    };
+    let (token_0_program, token_1_program) = if sol_is_token_0 {
+        (
+            accounts.token_program.key(),
+            accounts.mint_token_program.key(),
+        )
+    } else {
+        (
+            accounts.mint_token_program.key(),
+            accounts.token_program.key(),
+        )
+    };
+
+    let mut data = Vec::with_capacity(32);
+    data.extend_from_slice(&RAYDIUM_INITIALIZE);
+    data.extend_from_slice(&amount_0.to_le_bytes());
+    data.extend_from_slice(&amount_1.to_le_bytes());
+    data.extend_from_slice(&open_time.to_le_bytes());
+
+    let ix = Instruction {
+        program_id: RAYDIUM_CPMM_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(accounts.vault.key(), true),
+            AccountMeta::new_readonly(accounts.raydium_amm_config.key(), false),
+            AccountMeta::new_readonly(accounts.raydium_authority.key(), false),
+            AccountMeta::new(accounts.raydium_pool_state.key(), false),
+            AccountMeta::new_readonly(mint_0.key(), false),
+            AccountMeta::new_readonly(mint_1.key(), false),
+            AccountMeta::new(accounts.lp_mint.key(), false),
+            AccountMeta::new(token_0_account.key(), false),
+            AccountMeta::new(token_1_account.key(), false),
+            AccountMeta::new(accounts.vault_lp_token_account.key(), false),
+            AccountMeta::new(accounts.raydium_token_0_vault.key(), false),
+            AccountMeta::new(accounts.raydium_token_1_vault.key(), false),
+            AccountMeta::new(accounts.raydium_create_pool_fee.key(), false),
+            AccountMeta::new(accounts.raydium_observation_state.key(), false),
+            AccountMeta::new_readonly(accounts.token_program.key(), false),
+            AccountMeta::new_readonly(token_0_program, false),
+            AccountMeta::new_readonly(token_1_program, false),
+            AccountMeta::new_readonly(accounts.associated_token_program.key(), false),
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+            AccountMeta::new_readonly(accounts.rent.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &ix,
+        &[
+            accounts.vault.to_account_info(),
+            accounts.raydium_amm_config.to_account_info(),
+            accounts.raydium_authority.to_account_info(),
+            accounts.raydium_pool_state.to_account_info(),
+            mint_0,
+            mint_1,
+            accounts.lp_mint.to_account_info(),
+            token_0_account,
+            token_1_account,
+            accounts.vault_lp_token_account.to_account_info(),
+            accounts.raydium_token_0_vault.to_account_info(),
+            accounts.raydium_token_1_vault.to_account_info(),
+            accounts.raydium_create_pool_fee.to_account_info(),
+            accounts.raydium_observation_state.to_account_info(),
+            accounts.token_program.to_account_info(),
+            accounts.mint_token_program.to_account_info(),
+            accounts.associated_token_program.to_account_info(),
+            accounts.system_program.to_account_info(),
+            accounts.rent.to_account_info(),
+        ],
+        &[vault_seeds],
+    )?;
+    Ok(())
+}
+
+/// The Meteora dynamic AMM pool behind an M3M3 vault, as the fee vault
+/// expects it: pool, LP mint, lock escrow, escrow vault, token A/B vaults,
+/// A/B vaults, A/B vault LP accounts, A/B vault LP mints, then the AMM and
+/// vault programs. M3M3 checks them against its vault.
+fn m3m3_pool_accounts<'info>(
+    remaining: &[AccountInfo<'info>],
+) -> Result<(Vec<AccountMeta>, Vec<AccountInfo<'info>>)> {
+    let [pool, lp_mint, lock_escrow, escrow_vault, a_token_vault, b_token_vault, a_vault, b_vault, a_vault_lp, b_vault_lp, a_vault_lp_mint, b_vault_lp_mint, amm_program, vault_program] =
+        remaining
+    else {
+        return err!(VaultError::InvalidRoute);
+    };
+    let metas = vec![
+        AccountMeta::new(pool.key(), false),
+        AccountMeta::new_readonly(lp_mint.key(), false),
+        AccountMeta::new(lock_escrow.key(), false),
+        AccountMeta::new(escrow_vault.key(), false),
+        AccountMeta::new(a_token_vault.key(), false),
+        AccountMeta::new(b_token_vault.key(), false),
+        AccountMeta::new(a_vault.key(), false),
+        AccountMeta::new(b_vault.key(), false),
+        AccountMeta::new(a_vault_lp.key(), false),
+        AccountMeta::new(b_vault_lp.key(), false),
+        AccountMeta::new(a_vault_lp_mint.key(), false),
+        AccountMeta::new(b_vault_lp_mint.key(), false),
+        AccountMeta::new_readonly(amm_program.key(), false),
+        AccountMeta::new_readonly(vault_program.key(), false),
+    ];
+    Ok((metas, remaining.to_vec()))
+}
+
+/// Backend hooks for lending out idle vault SOL. Backends read their
+/// protocol accounts from `remaining_accounts` in their own documented order.
+trait YieldAdapter<'info> {
+    fn init_account(
+        &self,
+        yield_account: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        vault_seeds: &[&[u8]],
+    ) -> Result<()>;
+    /// Moves `lamports` of WSOL from the vault WSOL ATA into the backend.
+    fn deposit(&self, lamports: u64, vault_seeds: &[&[u8]]) -> Result<()>;
+    /// Moves `lamports` of WSOL from the backend into the vault WSOL ATA.
+    fn withdraw(&self, lamports: u64, vault_seeds: &[&[u8]]) -> Result<()>;
+}
+
+fn yield_adapter<'a, 'info: 'a>(
+    state: &VaultState,
+    vault: AccountInfo<'info>,
+    vault_wsol_ata: Option<AccountInfo<'info>>,
+    program: AccountInfo<'info>,
+    backend_accounts: &'a [AccountInfo<'info>],
+) -> Result<Box<dyn YieldAdapter<'info> + 'a>> {
+    match state.config.yield_backend {
+        Some(YieldBackend::Marginfi) => Ok(Box::new(MarginfiAdapter::new(
+            state,
+            vault,
+            vault_wsol_ata,
+            program,
+            backend_accounts,
+        )?)),
+        None => err!(VaultError::PoolNotConfigured),
+    }
+}
+
+/// marginfi v2 backend. `backend_accounts` is `[marginfi_program,
+/// marginfi_group]` for `init_account`, and `[marginfi_program,
+/// marginfi_group, marginfi_account, bank, bank_liquidity_vault,
+/// bank_liquidity_vault_authority, ..health accounts]` otherwise, where the
+/// health accounts are the bank/oracle pairs marginfi's risk engine needs.
+struct MarginfiAdapter<'a, 'info> {
+    vault: AccountInfo<'info>,
+    vault_wsol_ata: Option<AccountInfo<'info>>,
+    /// Token program for deposit/withdraw, system program for init.
+    program: AccountInfo<'info>,
+    accounts: &'a [AccountInfo<'info>],
+}
+
+impl<'a, 'info> MarginfiAdapter<'a, 'info> {
+    fn new(
+        state: &VaultState,
+        vault: AccountInfo<'info>,
+        vault_wsol_ata: Option<AccountInfo<'info>>,
+        program: AccountInfo<'info>,
+        accounts: &'a [AccountInfo<'info>],
+    ) -> Result<Self> {
+        require!(accounts.len() >= 2, VaultError::InvalidRoute);
+        require_keys_eq!(
+            accounts[0].key(),
+            MARGINFI_PROGRAM_ID,
+            VaultError::InvalidRoute
+        );
+        if vault_wsol_ata.is_some() {
+            require!(accounts.len() >= 6, VaultError::InvalidRoute);
+            require!(
+                state.config.yield_account == Some(accounts[2].key()),
+                VaultError::PoolNotConfigured
+            );
+        }
+        Ok(Self {
+            vault,
+            vault_wsol_ata,
+            program,
+            accounts,
+        })
+    }
+
+    fn wsol(&self) -> Result<&AccountInfo<'info>> {
+        self.vault_wsol_ata
+            .as_ref()
+            .ok_or_else(|| error!(VaultError::InvalidRoute))
+    }
+}
+
+impl<'a, 'info> YieldAdapter<'info> for MarginfiAdapter<'a, 'info> {
+    fn init_account(
+        &self,
+        yield_account: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        vault_seeds: &[&[u8]],
+    ) -> Result<()> {
+        let ix = Instruction {
+            program_id: MARGINFI_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(self.accounts[1].key(), false),
+                AccountMeta::new(yield_account.key(), true),
+                AccountMeta::new_readonly(self.vault.key(), true),
+                AccountMeta::new(payer.key(), true),
+                AccountMeta::new_readonly(self.program.key(), false),
+            ],
+            data: MARGINFI_ACCOUNT_INITIALIZE.to_vec(),
+        };
+        invoke_signed(
+            &ix,
+            &[
+                self.accounts[1].clone(),
+                yield_account.clone(),
+                self.vault.clone(),
+                payer.clone(),
+                self.program.clone(),
+                self.accounts[0].clone(),
+            ],
+            &[vault_seeds],
+        )?;
+        Ok(())
+    }
+
+    fn deposit(&self, lamports: u64, vault_seeds: &[&[u8]]) -> Result<()> {
+        let wsol = self.wsol()?;
+        let mut data = Vec::with_capacity(17);
+        data.extend_from_slice(&MARGINFI_DEPOSIT);
+        data.extend_from_slice(&lamports.to_le_bytes());
+        data.push(0); // deposit_up_to_limit: None
+        let ix = Instruction {
+            program_id: MARGINFI_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(self.accounts[1].key(), false),
+                AccountMeta::new(self.accounts[2].key(), false),
+                AccountMeta::new_readonly(self.vault.key(), true),
+                AccountMeta::new(self.accounts[3].key(), false),
+                AccountMeta::new(wsol.key(), false),
+                AccountMeta::new(self.accounts[4].key(), false),
+                AccountMeta::new_readonly(self.program.key(), false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &ix,
+            &[
+                self.accounts[1].clone(),
+                self.accounts[2].clone(),
+                self.vault.clone(),
+                self.accounts[3].clone(),
+                wsol.clone(),
+                self.accounts[4].clone(),
+                self.program.clone(),
+                self.accounts[0].clone(),
+            ],
+            &[vault_seeds],
+        )?;
+        Ok(())
+    }
+
+    fn withdraw(&self, lamports: u64, vault_seeds: &[&[u8]]) -> Result<()> {
+        let wsol = self.wsol()?;
+        let mut data = Vec::with_capacity(17);
+        data.extend_from_slice(&MARGINFI_WITHDRAW);
+        data.extend_from_slice(&lamports.to_le_bytes());
+        data.push(0); // withdraw_all: None
+        let mut metas = vec![
+            AccountMeta::new_readonly(self.accounts[1].key(), false),
+            AccountMeta::new(self.accounts[2].key(), false),
+            AccountMeta::new_readonly(self.vault.key(), true),
+            AccountMeta::new(self.accounts[3].key(), false),
+            AccountMeta::new(wsol.key(), false),
+            AccountMeta::new_readonly(self.accounts[5].key(), false),
+            AccountMeta::new(self.accounts[4].key(), false),
+            AccountMeta::new_readonly(self.program.key(), false),
+        ];
+        let mut infos = vec![
+            self.accounts[1].clone(),
+            self.accounts[2].clone(),
+            self.vault.clone(),
+            self.accounts[3].clone(),
+            wsol.clone(),
+            self.accounts[5].clone(),
+            self.accounts[4].clone(),
+            self.program.clone(),
+            self.accounts[0].clone(),
+        ];
+        for health in &self.accounts[6..] {
+            metas.push(AccountMeta::new_readonly(health.key(), false));
+            infos.push(health.clone());
+        }
+        let ix = Instruction {
+            program_id: MARGINFI_PROGRAM_ID,
+            accounts: metas,
+            data,
+        };
+        invoke_signed(&ix, &infos, &[vault_seeds])?;
+        Ok(())
+    }
+}
+
+/// Post-swap accounts shared by every crank path. Strategy destinations
+/// beyond burn and lock are only required when their weight is non-zero.
+struct DistributionAccounts<'info> {
+    vault: AccountInfo<'info>,
+    /// Closed back to the vault after distribution; absent for SPL profit.
+    vault_wsol_ata: Option<AccountInfo<'info>>,
+    vault_token_account: AccountInfo<'info>,
+    burn_token_account: AccountInfo<'info>,
+    timelock_token_account: AccountInfo<'info>,
+    stake_rewards_token_account: Option<AccountInfo<'info>>,
+    treasury_token_account: Option<AccountInfo<'info>>,
+    keeper_token_account: Option<AccountInfo<'info>>,
+    /// Crank payer, recorded in the `BuybackRecord`.
+    keeper: Pubkey,
+    /// Must be `config.price_oracle`; its price goes into the `BuybackEvent`.
+    price_oracle: Option<AccountInfo<'info>>,
+    /// USD price of the profit currency, for `config.oracle_guard`.
+    quote_oracle: Option<AccountInfo<'info>>,
+    mint: AccountInfo<'info>,
+    mint_decimals: u8,
+    /// Decimals of what `profit_lamports` counts: SOL, or the SPL profit mint.
+    profit_decimals: u8,
+    /// SPL Memo program, for the explorer-readable summary.
+    memo_program: Option<AccountInfo<'info>>,
+    /// SPL Token, for closing the WSOL ATA.
+    token_program: AccountInfo<'info>,
+    /// Owner of `mint`: SPL Token or Token-2022.
+    mint_token_program: AccountInfo<'info>,
+    /// Accounts for `mint`'s transfer hook, if it has one.
+    transfer_hook_accounts: Vec<AccountInfo<'info>>,
+}
+
+fn transfer_from_vault<'info>(
+    state: &Account<'info, VaultState>,
+    accounts: &DistributionAccounts<'info>,
+    to: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let state_seeds = &[
+        b"state".as_ref(),
+        state.seed_mint.as_ref(),
+        state.authority.as_ref(),
+        &[state.bump],
+    ];
+    let cpi_ctx = CpiContext::new_with_signer(
+        accounts.mint_token_program.clone(),
+        TransferChecked {
+            from: accounts.vault_token_account.clone(),
+            mint: accounts.mint.clone(),
+            to: to.clone(),
+            authority: state.to_account_info(),
+        },
+        &[&state_seeds[..]],
+    )
+    .with_remaining_accounts(accounts.transfer_hook_accounts.clone());
+    transfer_checked_with_hook(cpi_ctx, amount, accounts.mint_decimals)
+}
+
+/// `token_interface::transfer_checked` that also forwards the extra accounts
+/// of a Token-2022 transfer hook. The hook program, its validation account
+/// and the accounts that lists are looked up by key in the context's
+/// `remaining_accounts`, so they may come in any order among others; mints
+/// without a hook ignore them.
+fn transfer_checked_with_hook<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>,
+    amount: u64,
+    decimals: u8,
+) -> Result<()> {
+    onchain::invoke_transfer_checked(
+        ctx.program.key,
+        ctx.accounts.from,
+        ctx.accounts.mint,
+        ctx.accounts.to,
+        ctx.accounts.authority,
+        &ctx.remaining_accounts,
+        amount,
+        decimals,
+        ctx.signer_seeds,
+    )?;
+    Ok(())
+}
+
+/// Destination for a strategy leg, checked against the address in config.
+fn strategy_destination<'a, 'info>(
+    account: &'a Option<AccountInfo<'info>>,
+    expected: Option<Pubkey>,
+) -> Result<&'a AccountInfo<'info>> {
+    let account = account.as_ref().ok_or(VaultError::MissingStrategyAccount)?;
+    require!(
+        expected == Some(account.key()),
+        VaultError::MissingStrategyAccount
+    );
+    Ok(account)
+}
+
+/// Splits the distributable vault token balance across the configured
+/// strategies, then closes the WSOL ATA back to the vault to reclaim rent +
+/// remaining SOL. Tokens already reserved for liquidity or retained are not
+/// redistributed.
+fn distribute<'info>(
+    state: &mut Account<'info, VaultState>,
+    accounts: &DistributionAccounts<'info>,
+    vault_amount: u64,
+    now: i64,
+) -> Result<Distribution> {
+    let mut distributable = vault_amount
+        .saturating_sub(state.liquidity_reserve_tokens)
+        .saturating_sub(state.retained_tokens)
+        .saturating_sub(state.holder_reward_tokens)
+        .saturating_sub(state.fee_stake_tokens);
+    require!(distributable > 0, VaultError::NoTokens);
+
+    let mut distribution = Distribution::default();
+    if let Some(keeper_token_account) = &accounts.keeper_token_account {
+        let reward = bps_of(distributable, state.keeper_reward_bps(now))?;
+        transfer_from_vault(state, accounts, keeper_token_account, reward)?;
+        distribution.keeper_reward_amount = reward;
+        state.total_keeper_rewards = state.total_keeper_rewards.saturating_add(reward);
+        distributable -= reward;
+    }
+    // Index rather than clone the weights; the loop body mutates `state`.
+    for i in 0..state.config.distribution.len() {
+        let weight = state.config.distribution[i];
+        let amount = bps_of(distributable, weight.bps as u64)?;
+        match weight.strategy {
+            DistributionStrategy::Burn => {
+                transfer_from_vault(state, accounts, &accounts.burn_token_account, amount)?;
+                distribution.burn_amount += amount;
+            }
+            DistributionStrategy::Lock => {
+                transfer_from_vault(state, accounts, &accounts.timelock_token_account, amount)?;
+                distribution.lock_amount += amount;
+            }
+            DistributionStrategy::AddLiquidity => {
+                // Held in the vault token account until paired with SOL.
+                state.liquidity_reserve_tokens = state
+                    .liquidity_reserve_tokens
+                    .checked_add(amount)
+                    .ok_or(VaultError::MathOverflow)?;
+                distribution.liquidity_amount += amount;
+            }
+            DistributionStrategy::StakeRewards => {
+                let to = strategy_destination(
+                    &accounts.stake_rewards_token_account,
+                    state.config.stake_rewards_token_account,
+                )?;
+                transfer_from_vault(state, accounts, to, amount)?;
+                distribution.stake_rewards_amount += amount;
+            }
+            DistributionStrategy::Treasury => {
+                let to = strategy_destination(
+                    &accounts.treasury_token_account,
+                    state.config.treasury_token_account,
+                )?;
+                transfer_from_vault(state, accounts, to, amount)?;
+                distribution.treasury_amount += amount;
+            }
+            DistributionStrategy::Retain => {
+                state.retained_tokens = state
+                    .retained_tokens
+                    .checked_add(amount)
+                    .ok_or(VaultError::MathOverflow)?;
+                distribution.retained_amount += amount;
+            }
+            DistributionStrategy::HolderRewards => {
+                state.holder_reward_tokens = state
+                    .holder_reward_tokens
+                    .checked_add(amount)
+                    .ok_or(VaultError::MathOverflow)?;
+                distribution.holder_rewards_amount += amount;
+            }
+            DistributionStrategy::StakeForFees => {
+                state.fee_stake_tokens = state
+                    .fee_stake_tokens
+                    .checked_add(amount)
+                    .ok_or(VaultError::MathOverflow)?;
+                distribution.fee_stake_amount += amount;
+            }
+        }
+    }
+
+    state.add_burned(distribution.burn_amount);
+    state.total_locked = state.total_locked.saturating_add(distribution.lock_amount);
+
+    if let Some(vault_wsol_ata) = &accounts.vault_wsol_ata {
+        close_vault_wsol(
+            state,
+            &accounts.vault,
+            vault_wsol_ata,
+            &accounts.token_program,
+        )?;
+    }
+
+    Ok(distribution)
+}
+
+fn close_vault_wsol<'info>(
+    state: &Account<'info, VaultState>,
+    vault: &AccountInfo<'info>,
+    vault_wsol_ata: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let state_key = state.key();
+    let vault_seeds = &[b"vault".as_ref(), state_key.as_ref(), &[state.vault_bump]];
+    let cpi_close = CpiContext::new_with_signer(
+        token_program.clone(),
+        CloseAccount {
+            account: vault_wsol_ata.clone(),
+            destination: vault.clone(),
+            authority: vault.clone(),
+        },
+        &[&vault_seeds[..]],
+    );
+    token_interface::close_account(cpi_close)
+}
+
+/// Rejects Token-2022 mints with extensions that change what a transfer
+/// moves or who controls the vault's tokens: transfer fees, permanent
+/// delegates, frozen-by-default accounts and the like. Metadata, group and
+/// interest-bearing extensions only affect display and are fine; transfer
+/// hooks get their accounts through `transfer_checked_with_hook`.
+fn check_mint_extensions(mint: &AccountInfo) -> Result<()> {
+    if mint.owner != &Token2022::id() {
+        return Ok(());
+    }
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    for extension in mint.get_extension_types()? {
+        require!(
+            matches!(
+                extension,
+                ExtensionType::MetadataPointer
+                    | ExtensionType::TokenMetadata
+                    | ExtensionType::GroupPointer
+                    | ExtensionType::TokenGroup
+                    | ExtensionType::GroupMemberPointer
+                    | ExtensionType::TokenGroupMember
+                    | ExtensionType::MintCloseAuthority
+                    | ExtensionType::InterestBearingConfig
+                    | ExtensionType::TransferHook
+            ),
+            VaultError::UnsupportedMintExtension
+        );
+    }
+    Ok(())
+}
+
+/// Where `finish_crank` stores a crank's `BuybackRecord`.
+enum RecordSink<'a, 'info> {
+    Account(&'a mut Account<'info, BuybackRecord>),
+    /// A Light Protocol compressed account, with `config.compressed_records`.
+    Compressed(LightRecordAccounts<'info>),
+}
+
+/// Accounts for appending a compressed account to a Light state tree.
+struct LightRecordAccounts<'info> {
+    fee_payer: AccountInfo<'info>,
+    cpi_authority: AccountInfo<'info>,
+    registered_program_pda: AccountInfo<'info>,
+    noop_program: AccountInfo<'info>,
+    account_compression_authority: AccountInfo<'info>,
+    account_compression_program: AccountInfo<'info>,
+    invoking_program: AccountInfo<'info>,
+    light_system_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    state_merkle_tree: AccountInfo<'info>,
+}
+
+/// Appends `record` to the Light state tree as a compressed account owned by
+/// this program, so it costs no rent. A new account with no address needs no
+/// validity proof. Indexers serve it back by owner and discriminator.
+fn compress_record(light: &LightRecordAccounts, record: &BuybackRecord) -> Result<()> {
+    let data = record.try_to_vec()?;
+    // Light hashes into the BN254 field, so the top byte must be zero.
+    let mut data_hash = hashv(&[&data]).to_bytes();
+    data_hash[0] = 0;
+
+    let mut inputs = Vec::with_capacity(100 + data.len());
+    inputs.push(0); // proof: None
+    inputs.extend_from_slice(&0u32.to_le_bytes()); // new addresses
+    inputs.extend_from_slice(&0u32.to_le_bytes()); // input accounts
+    inputs.extend_from_slice(&1u32.to_le_bytes()); // output accounts
+    inputs.extend_from_slice(crate::ID.as_ref());
+    inputs.extend_from_slice(&0u64.to_le_bytes()); // lamports
+    inputs.push(0); // address: None
+    inputs.push(1);
+    inputs.extend_from_slice(BuybackRecord::DISCRIMINATOR);
+    inputs.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    inputs.extend_from_slice(&data);
+    inputs.extend_from_slice(&data_hash);
+    inputs.push(0); // merkle_tree_index: the first remaining account
+                    // relay_fee, compress_or_decompress_lamports, is_compress, cpi_context
+    inputs.extend_from_slice(&[0, 0, 0, 0]);
+
+    let mut ix_data = Vec::with_capacity(12 + inputs.len());
+    ix_data.extend_from_slice(&LIGHT_INVOKE_CPI);
+    ix_data.extend_from_slice(&(inputs.len() as u32).to_le_bytes());
+    ix_data.extend_from_slice(&inputs);
+
+    let (cpi_authority, bump) =
+        Pubkey::find_program_address(&[LIGHT_CPI_AUTHORITY_SEED], &crate::ID);
+    require_keys_eq!(
+        light.cpi_authority.key(),
+        cpi_authority,
+        VaultError::InvalidRoute
+    );
+    // Unused optional accounts are passed as the Light system program.
+    let ix = Instruction {
+        program_id: LIGHT_SYSTEM_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(light.fee_payer.key(), true),
+            AccountMeta::new_readonly(cpi_authority, true),
+            AccountMeta::new_readonly(light.registered_program_pda.key(), false),
+            AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+            AccountMeta::new_readonly(light.account_compression_authority.key(), false),
+            AccountMeta::new_readonly(ACCOUNT_COMPRESSION_PROGRAM_ID, false),
+            AccountMeta::new_readonly(crate::ID, false),
+            AccountMeta::new_readonly(LIGHT_SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new_readonly(LIGHT_SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new_readonly(System::id(), false),
+            AccountMeta::new_readonly(LIGHT_SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new(light.state_merkle_tree.key(), false),
+        ],
+        data: ix_data,
+    };
+    invoke_signed(
+        &ix,
+        &[
+            light.fee_payer.clone(),
+            light.cpi_authority.clone(),
+            light.registered_program_pda.clone(),
+            light.noop_program.clone(),
+            light.account_compression_authority.clone(),
+            light.account_compression_program.clone(),
+            light.invoking_program.clone(),
+            light.light_system_program.clone(),
+            light.system_program.clone(),
+            light.state_merkle_tree.clone(),
+        ],
+        &[&[LIGHT_CPI_AUTHORITY_SEED, &[bump]]],
+    )?;
+    Ok(())
+}
+
+/// Distributes the bought tokens, opens a `LockTranche` for the locked leg
+/// and records the crank in its `BuybackRecord`. Returns the event for the
+/// caller to emit, since `emit_cpi!` needs the instruction context.
+fn finish_crank<'info>(
+    state: &mut Account<'info, VaultState>,
+    accounts: &DistributionAccounts<'info>,
+    record_sink: RecordSink<'_, 'info>,
+    history: Option<&AccountLoader<'info, BuybackHistory>>,
+    lock_tranche: Option<&mut Account<'info, LockTranche>>,
+    profit_lamports: u64,
+    tokens_bought: u64,
+    now: i64,
+) -> Result<BuybackEvent> {
+    let oracle_price = match &accounts.price_oracle {
+        Some(oracle) => {
+            require!(
+                state.config.price_oracle == Some(oracle.key()),
+                VaultError::InvalidOracle
+            );
+            Some(read_oracle_price(oracle)?)
+        }
+        None => None,
+    };
+    if let Some(guard) = &state.config.oracle_guard {
+        check_oracle_guard(
+            guard,
+            accounts,
+            oracle_price,
+            profit_lamports,
+            tokens_bought,
+            now,
+        )?;
+    }
+    let vault_amount = token::accessor::amount(&accounts.vault_token_account)?;
+    let distribution = distribute(state, accounts, vault_amount, now)?;
+    if let Some(memo_program) = &accounts.memo_program {
+        buyback_memo(memo_program, &distribution, profit_lamports)?;
+    }
+    open_lock_tranche(
+        state,
+        lock_tranche,
+        distribution.lock_amount,
+        accounts.keeper,
+        now,
+    )?;
+
+    let record = BuybackRecord {
+        state: state.key(),
+        index: state.crank_count,
+        profit_lamports,
+        tokens_bought,
+        execution_price: execution_price(profit_lamports, tokens_bought)?,
+        burn_amount: distribution.burn_amount,
+        lock_amount: distribution.lock_amount,
+        liquidity_amount: distribution.liquidity_amount,
+        stake_rewards_amount: distribution.stake_rewards_amount,
+        treasury_amount: distribution.treasury_amount,
+        retained_amount: distribution.retained_amount,
+        keeper_reward_amount: distribution.keeper_reward_amount,
+        keeper: accounts.keeper,
+        timestamp: now,
+        tip_claimed: false,
+    };
+    state.add_lock_cost(distribution.lock_amount, record.execution_price);
+    match record_sink {
+        RecordSink::Account(account) => account.set_inner(record.clone()),
+        RecordSink::Compressed(light) => {
+            require!(
+                state.config.compressed_records,
+                VaultError::MissingBuybackRecord
+            );
+            compress_record(&light, &record)?;
+        }
+    }
+    if let Some(history) = history {
+        let entry = HistoryEntry {
+            timestamp: now,
+            profit_lamports,
+            tokens_bought,
+            execution_price: record.execution_price,
+            burn_amount: distribution.burn_amount,
+            lock_amount: distribution.lock_amount,
+        };
+        let mut history = history.load_mut()?;
+        history.add_to_epoch(Clock::get()?.epoch, &entry);
+        history.push(entry);
+    }
+
+    state.last_keeper = accounts.keeper;
+    record_crank(state, profit_lamports, tokens_bought, now)?;
+
+    Ok(BuybackEvent {
+        version: EVENT_VERSION,
+        seq: state.next_event_seq(),
+        vault_id: state.vault_id,
+        crank_index: Some(record.index),
+        profit_lamports,
+        burn_amount: distribution.burn_amount,
+        lock_amount: distribution.lock_amount,
+        liquidity_amount: distribution.liquidity_amount,
+        stake_rewards_amount: distribution.stake_rewards_amount,
+        treasury_amount: distribution.treasury_amount,
+        retained_amount: distribution.retained_amount,
+        holder_rewards_amount: distribution.holder_rewards_amount,
+        fee_stake_amount: distribution.fee_stake_amount,
+        keeper_reward_amount: distribution.keeper_reward_amount,
+        keeper: accounts.keeper,
+        burn_address: accounts.burn_token_account.key(),
+        timelock_account: accounts.timelock_token_account.key(),
+        execution_price: record.execution_price,
+        oracle_price,
+        burned_bps_of_supply: state.burned_bps_of_supply,
+    })
+}
+
+/// Logs "burned X, locked Y, spent Z SOL" through the SPL Memo program so
+/// explorers show the buyback without decoding events. Token amounts are in
+/// base units.
+fn buyback_memo(
+    memo_program: &AccountInfo,
+    distribution: &Distribution,
+    profit_lamports: u64,
+) -> Result<()> {
+    let memo = format!(
+        "burned {}, locked {}, spent {}.{:09} SOL",
+        distribution.burn_amount,
+        distribution.lock_amount,
+        profit_lamports / 1_000_000_000,
+        profit_lamports % 1_000_000_000,
+    );
+    let ix = Instruction {
+        program_id: SPL_MEMO_PROGRAM_ID,
+        accounts: vec![],
+        data: memo.into_bytes(),
+    };
+    invoke(&ix, &[memo_program.clone()])?;
+    Ok(())
+}
+
+/// Lamports paid per token base unit, scaled by `PRICE_SCALE`.
+fn execution_price(lamports: u64, tokens: u64) -> Result<u64> {
+    u64::try_from(
+        (lamports as u128)
+            .checked_mul(PRICE_SCALE as u128)
+            .ok_or(VaultError::MathOverflow)?
+            / tokens.max(1) as u128,
+    )
+    .map_err(|_| error!(VaultError::MathOverflow))
+}
+
+/// Reads the latest price from whichever oracle owns `oracle`, so the
+/// callers never care which feed a vault was configured with.
+fn read_oracle_price(oracle: &AccountInfo) -> Result<OraclePrice> {
+    match *oracle.owner {
+        PYTH_RECEIVER_PROGRAM_ID => read_pyth_price(oracle),
+        SWITCHBOARD_ON_DEMAND_PROGRAM_ID => read_switchboard_price(oracle),
+        CHAINLINK_STORE_PROGRAM_ID => read_chainlink_price(oracle),
+        _ => err!(VaultError::InvalidOracle),
+    }
+}
+
+/// Reads the latest price from a Pyth receiver `PriceUpdateV2` account.
+fn read_pyth_price(oracle: &AccountInfo) -> Result<OraclePrice> {
+    require_keys_eq!(
+        *oracle.owner,
+        PYTH_RECEIVER_PROGRAM_ID,
+        VaultError::InvalidOracle
+    );
+    let data = oracle.try_borrow_data()?;
+    require!(
+        data.get(..8) == Some(&PYTH_PRICE_UPDATE_V2[..]),
+        VaultError::InvalidOracle
+    );
+    // Discriminator and write authority, then the verification level:
+    // `Partial { num_signatures }` is two bytes, `Full` is one.
+    let message = match data.get(40) {
+        Some(0) => 42,
+        Some(1) => 41,
+        _ => return err!(VaultError::InvalidOracle),
+    };
+    // The message starts with the 32-byte feed id.
+    let field = |at: usize, len: usize| {
+        data.get(message + 32 + at..message + 32 + at + len)
+            .ok_or_else(|| error!(VaultError::InvalidOracle))
+    };
+    Ok(OraclePrice {
+        price: i64::from_le_bytes(field(0, 8)?.try_into().unwrap()),
+        conf: u64::from_le_bytes(field(8, 8)?.try_into().unwrap()),
+        expo: i32::from_le_bytes(field(16, 4)?.try_into().unwrap()),
+        publish_time: i64::from_le_bytes(field(20, 8)?.try_into().unwrap()),
+    })
+}
+
+/// Reads the current result of a Switchboard on-demand `PullFeedAccountData`
+/// account. Its 18-decimal values are cut down to Pyth's usual `expo` of -8
+/// so they fit an `OraclePrice`; the standard deviation stands in for the
+/// confidence interval.
+fn read_switchboard_price(oracle: &AccountInfo) -> Result<OraclePrice> {
+    require_keys_eq!(
+        *oracle.owner,
+        SWITCHBOARD_ON_DEMAND_PROGRAM_ID,
+        VaultError::InvalidOracle
+    );
+    let data = oracle.try_borrow_data()?;
+    require!(
+        data.get(..8) == Some(&SWITCHBOARD_PULL_FEED[..]),
+        VaultError::InvalidOracle
+    );
+    // Discriminator, 32 submissions of 64 bytes, then the feed header up to
+    // `last_update_timestamp`; `result` follows `lut_slot` and 32 reserved
+    // bytes.
+    let field = |at: usize, len: usize| {
+        data.get(8 + at..8 + at + len)
+            .ok_or_else(|| error!(VaultError::InvalidOracle))
+    };
+    let value = i128::from_le_bytes(field(2256, 16)?.try_into().unwrap());
+    let std_dev = i128::from_le_bytes(field(2272, 16)?.try_into().unwrap());
+    let rescale = |v: i128| v / 10i128.pow(10);
+    Ok(OraclePrice {
+        price: i64::try_from(rescale(value)).map_err(|_| error!(VaultError::InvalidOracle))?,
+        conf: u64::try_from(rescale(std_dev)).map_err(|_| error!(VaultError::InvalidOracle))?,
+        expo: -8,
+        publish_time: i64::from_le_bytes(field(2208, 8)?.try_into().unwrap()),
+    })
+}
+
+/// Reads the latest round of a Chainlink OCR2 feed (`Transmissions`
+/// account). The answer carries the feed's `decimals`, which become the
+/// `expo`; Chainlink publishes no confidence interval, so `conf` is zero.
+fn read_chainlink_price(oracle: &AccountInfo) -> Result<OraclePrice> {
+    require_keys_eq!(
+        *oracle.owner,
+        CHAINLINK_STORE_PROGRAM_ID,
+        VaultError::InvalidOracle
+    );
+    let data = oracle.try_borrow_data()?;
+    require!(
+        data.get(..8) == Some(&CHAINLINK_TRANSMISSIONS[..]),
+        VaultError::InvalidOracle
+    );
+    let field = |at: usize, len: usize| {
+        data.get(8 + at..8 + at + len)
+            .ok_or_else(|| error!(VaultError::InvalidOracle))
+    };
+    // Version, state, three authorities and the description precede
+    // `decimals`; the live buffer's length and write cursor follow the
+    // round id and granularity.
+    let decimals = field(130, 1)?[0];
+    let live_length = u32::from_le_bytes(field(140, 4)?.try_into().unwrap()) as usize;
+    let live_cursor = u32::from_le_bytes(field(144, 4)?.try_into().unwrap()) as usize;
+    require!(live_length > 0, VaultError::InvalidOracle);
+    let latest = (live_cursor + live_length - 1) % live_length;
+    let at = CHAINLINK_HEADER_LEN + latest * CHAINLINK_TRANSMISSION_LEN;
+    let timestamp = u32::from_le_bytes(field(at + 8, 4)?.try_into().unwrap());
+    let answer = i128::from_le_bytes(field(at + 16, 16)?.try_into().unwrap());
+    Ok(OraclePrice {
+        price: i64::try_from(answer).map_err(|_| error!(VaultError::InvalidOracle))?,
+        conf: 0,
+        expo: -(decimals as i32),
+        publish_time: timestamp as i64,
+    })
+}
+
+/// Applies `config.oracle_guard` to a finished swap: both feeds must be
+/// fresh and tight, the token must trade under the ceiling, the profit must
+/// be worth `min_profit_usd`, and the tokens bought must be worth the profit
+/// spent less `max_slippage_bps`.
+fn check_oracle_guard(
+    guard: &OracleGuard,
+    accounts: &DistributionAccounts,
+    token_price: Option<OraclePrice>,
+    profit_lamports: u64,
+    tokens_bought: u64,
+    now: i64,
+) -> Result<()> {
+    let token_price = token_price.ok_or(VaultError::InvalidOracle)?;
+    let quote_oracle = accounts
+        .quote_oracle
+        .as_ref()
+        .ok_or(VaultError::InvalidOracle)?;
+    require_keys_eq!(
+        quote_oracle.key(),
+        guard.quote_oracle,
+        VaultError::InvalidOracle
+    );
+    let quote_price = read_oracle_price(quote_oracle)?;
+    guard.check_price(&token_price, now)?;
+    guard.check_price(&quote_price, now)?;
+    if let Some(ceiling) = guard.price_ceiling {
+        require!(token_price.price <= ceiling, VaultError::PriceAboveCeiling);
+    }
+
+    let spent_usd = usd_value(&quote_price, profit_lamports, accounts.profit_decimals)?;
+    require!(
+        spent_usd >= guard.min_profit_usd,
+        VaultError::ProfitBelowUsdThreshold
+    );
+    let bought_usd = usd_value(&token_price, tokens_bought, accounts.mint_decimals)?;
+    require!(
+        bought_usd as u128 * 10_000
+            >= spent_usd as u128 * (10_000 - guard.max_slippage_bps as u128),
+        VaultError::SlippageExceeded
+    );
+    Ok(())
+}
+
+/// USD value of `amount` base units at `price`, scaled by `USD_SCALE`.
+fn usd_value(price: &OraclePrice, amount: u64, decimals: u8) -> Result<u64> {
+    let value = (amount as u128)
+        .checked_mul(price.price.max(0) as u128)
+        .and_then(|v| v.checked_mul(USD_SCALE as u128))
+        .ok_or(VaultError::MathOverflow)?;
+    let expo = price.expo - decimals as i32;
+    let scale = 10u128
+        .checked_pow(expo.unsigned_abs())
+        .ok_or(VaultError::MathOverflow)?;
+    let value = if expo >= 0 {
+        value.checked_mul(scale).ok_or(VaultError::MathOverflow)?
+    } else {
+        value / scale
+    };
+    u64::try_from(value).map_err(|_| error!(VaultError::MathOverflow))
+}
+
+/// Leaf of a distribution tree. Leaves and inner nodes hash with different
+/// prefixes so an inner node can't pass as a leaf.
+fn merkle_leaf(claimant: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[&[0], claimant.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+/// Walks `proof` from `leaf` up to `root`, hashing each pair in sorted order
+/// so proofs carry no left/right flags.
+fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let node = proof.iter().fold(leaf, |node, sibling| {
+        let (a, b) = if node <= *sibling {
+            (node, *sibling)
+        } else {
+            (*sibling, node)
+        };
+        hashv(&[&[1], &a, &b]).to_bytes()
+    });
+    node == root
+}
+
+/// Accounts for moving tokens out of the timelock ATA for one tranche.
+struct ReleaseAccounts<'info> {
+    state: Pubkey,
+    mint: AccountInfo<'info>,
+    mint_decimals: u8,
+    timelock_token_account: AccountInfo<'info>,
+    timelock_authority: AccountInfo<'info>,
+    /// Omitted for burn-at-unlock tranches.
+    destination: Option<AccountInfo<'info>>,
+    token_program: AccountInfo<'info>,
+    /// Accounts for `mint`'s transfer hook, if it has one.
+    transfer_hook_accounts: Vec<AccountInfo<'info>>,
+}
+
+/// Releases `amount` of `tranche` per its `UnlockMode` and records the claim.
+/// Returns the `UnlockEvent` for the caller to emit; closing the tranche once
+/// `tranche_remaining` hits zero is also up to the caller.
+fn release_tranche<'info>(
+    state: &mut VaultState,
+    tranche: &mut Account<'info, LockTranche>,
+    accounts: &ReleaseAccounts<'info>,
+    amount: u64,
+    timelock_seeds: &[&[u8]],
+) -> Result<UnlockEvent> {
+    let signer = &[timelock_seeds];
+    let destination = match tranche.unlock_mode {
+        UnlockMode::Burn => {
+            let cpi_ctx = CpiContext::new_with_signer(
+                accounts.token_program.clone(),
+                Burn {
+                    mint: accounts.mint.clone(),
+                    from: accounts.timelock_token_account.clone(),
+                    authority: accounts.timelock_authority.clone(),
+                },
+                signer,
+            );
+            token_interface::burn(cpi_ctx, amount)?;
+            None
+        }
+        UnlockMode::Release | UnlockMode::StakingRewards { .. } | UnlockMode::Airdrop => {
+            let destination = accounts
+                .destination
+                .as_ref()
+                .ok_or(VaultError::MissingStrategyAccount)?;
+            if let UnlockMode::StakingRewards { escrow } = tranche.unlock_mode {
+                require_keys_eq!(
+                    destination.key(),
+                    escrow,
+                    VaultError::MissingStrategyAccount
+                );
+            }
+            let cpi_ctx = CpiContext::new_with_signer(
+                accounts.token_program.clone(),
+                TransferChecked {
+                    from: accounts.timelock_token_account.clone(),
+                    mint: accounts.mint.clone(),
+                    to: destination.clone(),
+                    authority: accounts.timelock_authority.clone(),
+                },
+                signer,
+            )
+            .with_remaining_accounts(accounts.transfer_hook_accounts.clone());
+            transfer_checked_with_hook(cpi_ctx, amount, accounts.mint_decimals)?;
+            Some(destination.key())
+        }
+        UnlockMode::Streamflow { .. } | UnlockMode::Permanent => {
+            return err!(VaultError::UnlockModeMismatch)
+        }
+    };
+
+    tranche.claimed += amount;
+    state.add_unlocked(tranche.mint, amount, destination.is_none());
+    let tranche_remaining = tranche.amount - tranche.claimed;
+    Ok(UnlockEvent {
+        version: EVENT_VERSION,
+        seq: state.next_event_seq(),
+        state: accounts.state,
+        lock_tranche: tranche.key(),
+        index: tranche.index,
+        mint: tranche.mint,
+        amount,
+        destination,
+        tranche_remaining,
+        timelock_remaining: token::accessor::amount(&accounts.timelock_token_account)?,
+    })
+}
+
+/// Fills the tranche created for a single-mint lock leg. The tranche may be
+/// omitted only when nothing was locked.
+fn open_lock_tranche<'info>(
+    state: &mut Account<'info, VaultState>,
+    lock_tranche: Option<&mut Account<'info, LockTranche>>,
+    amount: u64,
+    payer: Pubkey,
+    now: i64,
+) -> Result<()> {
+    let Some(tranche) = lock_tranche else {
+        require!(amount == 0, VaultError::MissingStrategyAccount);
+        return Ok(());
+    };
+    let index = state.next_lock_index()?;
+    tranche.set_inner(LockTranche::new(
+        state.key(),
+        index,
+        state.mint,
+        amount,
+        payer,
+        now,
+        &state.config,
+    )?);
+    Ok(())
+}
+
+/// Creates a `LockTranche` PDA by hand for lock legs whose tranche account
+/// arrives in `remaining_accounts`.
+fn create_lock_tranche<'info>(
+    payer: &AccountInfo<'info>,
+    tranche_account: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    tranche: &LockTranche,
+) -> Result<()> {
+    let index = tranche.index.to_le_bytes();
+    let (expected, bump) =
+        Pubkey::find_program_address(&[b"lock", tranche.state.as_ref(), &index], &crate::ID);
+    require_keys_eq!(tranche_account.key(), expected, VaultError::InvalidRoute);
+
+    let space = 8 + LockTranche::SIZE;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            &expected,
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            &crate::ID,
+        ),
+        &[
+            payer.clone(),
+            tranche_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[b"lock", tranche.state.as_ref(), &index, &[bump]]],
+    )?;
+    let mut data = tranche_account.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    tranche.try_serialize(&mut writer)?;
+    Ok(())
+}
+
+/// Bitmask of `members` that signed among `signers`.
+fn council_approvals(members: &[Pubkey], signers: &[AccountInfo]) -> u8 {
+    let mut approvals = 0u8;
+    for (i, member) in members.iter().enumerate() {
+        if signers.iter().any(|a| a.is_signer && a.key == member) {
+            approvals |= 1 << i;
+        }
+    }
+    approvals
+}
+
+/// Rules every config change must keep, however it is applied: lock buckets
+/// and the base mint are fixed at creation, a permanent or airdrop lock can't
+/// be relaxed for future tranches, and an installed council only changes with
+/// `rotate_council`.
+fn check_config_change(state: &VaultState, config: &VaultConfig) -> Result<()> {
+    require!(
+        config.lock_buckets == state.config.lock_buckets
+            && config.base_mint == state.config.base_mint,
+        VaultError::InvalidConfig
+    );
+    require!(
+        !matches!(
+            state.config.unlock_mode,
+            UnlockMode::Permanent | UnlockMode::Airdrop
+        ) || config.unlock_mode == state.config.unlock_mode,
+        VaultError::InvalidConfig
+    );
+    require!(
+        state.config.council.is_empty()
+            || (config.council == state.config.council
+                && config.council_threshold == state.config.council_threshold),
+        VaultError::InvalidConfig
+    );
+    Ok(())
+}
+
+/// An emergency council needs two thirds of its members to act; empty, with
+/// a zero threshold, dissolves it.
+fn validate_council(members: &[Pubkey], threshold: u8) -> Result<()> {
+    let len = members.len();
+    if len == 0 {
+        require!(threshold == 0, VaultError::InvalidConfig);
+        return Ok(());
+    }
+    require!(
+        len <= MAX_COUNCIL && (threshold as usize) <= len && (threshold as usize) * 3 >= len * 2,
+        VaultError::InvalidConfig
+    );
+    for (i, member) in members.iter().enumerate() {
+        require!(!members[..i].contains(member), VaultError::InvalidConfig);
+    }
+    Ok(())
+}
+
+/// A veto council needs a majority to act, so a minority can't block
+/// migrations on its own; empty, with a zero threshold, dissolves it.
+fn validate_veto_council(members: &[Pubkey], threshold: u8) -> Result<()> {
+    let len = members.len();
+    if len == 0 {
+        require!(threshold == 0, VaultError::InvalidConfig);
+        return Ok(());
+    }
+    require!(
+        len <= MAX_VETO_COUNCIL && (threshold as usize) <= len && (threshold as usize) * 2 > len,
+        VaultError::InvalidConfig
+    );
+    for (i, member) in members.iter().enumerate() {
+        require!(!members[..i].contains(member), VaultError::InvalidConfig);
+    }
+    Ok(())
+}
+
+fn validate_split_legs(legs: &[SplitLeg]) -> Result<()> {
+    require!(
+        !legs.is_empty() && legs.len() <= MAX_SPLIT_LEGS,
+        VaultError::InvalidConfig
+    );
+    for (i, leg) in legs.iter().enumerate() {
+        require!(
+            legs[..i].iter().all(|l| l.state != leg.state),
+            VaultError::InvalidConfig
+        );
+    }
+    require!(
+        legs.iter().map(|l| l.bps as u64).sum::<u64>() == 10_000,
+        VaultError::InvalidConfig
+    );
+    Ok(())
+}
+
+/// Sends `lamports` of protocol fees from `from`, diverting
+/// `keeper_pool_bps` of them to the `KeeperPool`. Returns the pool's share.
+fn pay_protocol_fee<'info>(
+    from: &AccountInfo<'info>,
+    treasury: &AccountInfo<'info>,
+    keeper_pool: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    lamports: u64,
+    keeper_pool_bps: u16,
+) -> Result<u64> {
+    let pool_lamports = bps_of(lamports, keeper_pool_bps as u64)?;
+    for (to, amount) in [
+        (keeper_pool, pool_lamports),
+        (treasury, lamports - pool_lamports),
+    ] {
+        if amount == 0 {
+            continue;
+        }
+        invoke_signed(
+            &system_instruction::transfer(from.key, to.key, amount),
+            &[from.clone(), to.clone(), system_program.clone()],
+            signer_seeds,
+        )?;
+    }
+    Ok(pool_lamports)
+}
+
+/// Folds a deposit into the depositor's opt-in record and the leaderboard.
+fn record_deposit(
+    state: Pubkey,
+    depositor: Pubkey,
+    record: Option<&mut DepositorRecord>,
+    leaderboard: Option<&mut DonorLeaderboard>,
+    amount: u64,
+) -> Result<()> {
+    let total = match record {
+        Some(record) => {
+            let now = Clock::get()?.unix_timestamp;
+            if record.deposit_count == 0 {
+                record.state = state;
+                record.depositor = depositor;
+                record.first_deposit_ts = now;
+            }
+            record.total_lamports = record
+                .total_lamports
+                .checked_add(amount)
+                .ok_or(VaultError::MathOverflow)?;
+            record.deposit_count += 1;
+            record.last_deposit_ts = now;
+            Some(record.total_lamports)
+        }
+        None => None,
+    };
+    if let Some(leaderboard) = leaderboard {
+        match total {
+            Some(total) => leaderboard.set(depositor, total),
+            None => leaderboard.add(depositor, amount),
+        }
+    }
+    Ok(())
+}
+
+/// Highest `thresholds` tier that a cumulative total moving from `before` to
+/// `after` reaches for the first time.
+fn crossed_badge_tier(thresholds: &[u64], before: u64, after: u64) -> Option<usize> {
+    thresholds
+        .iter()
+        .rposition(|&threshold| before < threshold && threshold <= after)
+}
+
+/// Credits `referrer` with a deposit when its record was passed.
+fn record_referral(
+    state: Pubkey,
+    referrer: Option<Pubkey>,
+    record: Option<&mut ReferrerRecord>,
+    amount: u64,
+) -> Result<()> {
+    let Some(record) = record else {
+        return Ok(());
+    };
+    let referrer = referrer.ok_or(VaultError::InvalidConfig)?;
+    if record.referral_count == 0 {
+        record.state = state;
+        record.referrer = referrer;
+    }
+    record.total_lamports = record
+        .total_lamports
+        .checked_add(amount)
+        .ok_or(VaultError::MathOverflow)?;
+    record.referral_count += 1;
+    record.last_referral_ts = Clock::get()?.unix_timestamp;
+    Ok(())
+}
+
+fn record_crank(
+    state: &mut VaultState,
+    profit_lamports: u64,
+    tokens_bought: u64,
+    now: i64,
+) -> Result<()> {
+    state.total_profit_lamports = state.total_profit_lamports.saturating_add(profit_lamports);
+    state
+        .rolling_7d
+        .add(ROLLING_7D_SECS, now, profit_lamports, tokens_bought);
+    state
+        .rolling_30d
+        .add(ROLLING_30D_SECS, now, profit_lamports, tokens_bought);
+    state.swap_pending = false;
+    state.pending_profit_lamports = 0;
+    state.pending_tokens_bought = 0;
+    state.crank_count = state
+        .crank_count
+        .checked_add(1)
+        .ok_or(VaultError::MathOverflow)?;
+    state.last_crank_ts = now;
+    state.timelock_unlock_ts = now
+        .checked_add(TIMELOCK_SECS)
+        .ok_or(VaultError::MathOverflow)?;
+    Ok(())
+}
+
+fn invariant(ok: bool, what: &str) -> Result<()> {
+    if !ok {
+        msg!("Invariant violated: {}", what);
+        return err!(VaultError::InvariantViolated);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitProtocol<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProtocolConfig::SIZE,
+        seeds = [b"protocol"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + KeeperPool::SIZE,
+        seeds = [b"keeper_pool"],
+        bump
+    )]
+    pub keeper_pool: Account<'info, KeeperPool>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::BurnflipVault>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ VaultError::NotProtocolAdmin
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateProtocol<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        has_one = admin @ VaultError::NotProtocolAdmin,
+        seeds = [b"protocol"],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(starting_balance_lamports: u64, burn_address: Pubkey)]
+pub struct CreateVault<'info> {
+    pub authority: Signer<'info>,
+    /// Pays the creation fee and rent, so a multisig or governance PDA can
+    /// be the authority without holding SOL.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(seeds = [b"protocol"], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    /// CHECK: Receives the creation fee; checked against the protocol config.
+    #[account(mut, address = protocol_config.settings.treasury)]
+    pub treasury: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"keeper_pool"], bump = keeper_pool.bump)]
+    pub keeper_pool: Box<Account<'info, KeeperPool>>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + VaultState::SIZE,
+        seeds = [b"state", mint.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: Timelock PDA authority
+    #[account(
+        seeds = [b"timelock", state.key().as_ref()],
+        bump
+    )]
+    pub timelock_authority: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + VaultRegistry::SIZE,
+        seeds = [b"registry"],
+        bump
+    )]
+    pub registry: Box<Account<'info, VaultRegistry>>,
+    /// The page the new vault lands on; whoever fills a page pays for the
+    /// next one.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + RegistryPage::SIZE,
+        seeds = [
+            b"registry_page",
+            &(registry.vault_count / REGISTRY_PAGE_LEN as u64).to_le_bytes()
+        ],
+        bump
+    )]
+    pub registry_page: Box<Account<'info, RegistryPage>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+    #[account(seeds = [b"protocol"], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct VetoCouncil<'info> {
+    #[account(
+        mut,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct EmergencyCouncil<'info> {
+    #[account(
+        mut,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+}
+
+impl<'info> EmergencyCouncil<'info> {
+    /// Returns the approvals bitmask once `config.council_threshold` members
+    /// signed.
+    fn check_approved(&self, signers: &[AccountInfo]) -> Result<u8> {
+        let config = &self.state.config;
+        let approvals = council_approvals(&config.council, signers);
+        require!(
+            !config.council.is_empty() && approvals.count_ones() >= config.council_threshold as u32,
+            VaultError::CouncilNotApproved
+        );
+        Ok(approvals)
+    }
+}
+
+impl<'info> VetoCouncil<'info> {
+    /// Returns the approvals bitmask once `veto_threshold` members signed.
+    fn check_approved(&self, signers: &[AccountInfo]) -> Result<u8> {
+        let state = &self.state;
+        let approvals = council_approvals(&state.veto_council, signers);
+        require!(
+            !state.veto_council.is_empty() && approvals.count_ones() >= state.veto_threshold as u32,
+            VaultError::CouncilNotApproved
+        );
+        Ok(approvals)
+    }
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateMint<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+    #[account(
+        constraint = state.pending_mint == Some(new_mint.key()) @ VaultError::NoMintMigration
+    )]
+    pub new_mint: InterfaceAccount<'info, Mint>,
+    /// Rent payer for whichever of the ATAs below don't exist yet.
+    #[account(mut)]
+    pub payer: Option<Signer<'info>>,
+    /// CHECK: `state`'s ATA for `new_mint`, created here when missing
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &state.key(),
+            &new_mint.key(),
+            &token_program.key()
+        )
+    )]
+    pub vault_token_account: UncheckedAccount<'info>,
+    /// CHECK: The timelock's ATA for `new_mint`, created here when missing
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &timelock_authority.key(),
+            &new_mint.key(),
+            &token_program.key()
+        )
+    )]
+    pub timelock_token_account: UncheckedAccount<'info>,
+    /// CHECK: Timelock PDA authority
+    #[account(
+        seeds = [b"timelock", state.key().as_ref()],
+        bump = state.timelock_bump
+    )]
+    pub timelock_authority: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateMint<'info> {
+    /// Creates the vault and timelock ATAs for `new_mint` that don't exist
+    /// yet, at `payer`'s expense; without a payer they must already exist.
+    fn create_token_accounts(&self) -> Result<()> {
+        for (ata, owner) in [
+            (&self.vault_token_account, self.state.to_account_info()),
+            (
+                &self.timelock_token_account,
+                self.timelock_authority.to_account_info(),
+            ),
+        ] {
+            if ata.owner == &self.token_program.key() {
+                continue;
+            }
+            let payer = self
+                .payer
+                .as_ref()
+                .ok_or(ErrorCode::AccountNotInitialized)?;
+            associated_token::create(CpiContext::new(
+                self.associated_token_program.to_account_info(),
+                associated_token::Create {
+                    payer: payer.to_account_info(),
+                    associated_token: ata.to_account_info(),
+                    authority: owner,
+                    mint: self.new_mint.to_account_info(),
+                    system_program: self.system_program.to_account_info(),
+                    token_program: self.token_program.to_account_info(),
+                },
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(lamports: u64, referrer: Option<Pubkey>)]
 pub struct Deposit<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
     #[account(
         mut,
-        seeds = [b"state", mint.key().as_ref()],
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, has_one = state)]
+    pub leaderboard: Option<Account<'info, DonorLeaderboard>>,
+    /// Opt-in contribution history for the depositor.
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + DepositorRecord::SIZE,
+        seeds = [b"depositor", state.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Option<Account<'info, DepositorRecord>>,
+    /// Opt-in totals for the deposit's `referrer`.
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ReferrerRecord::SIZE,
+        seeds = [
+            b"referrer",
+            state.key().as_ref(),
+            referrer.unwrap_or_default().as_ref()
+        ],
+        bump
+    )]
+    pub referrer_record: Option<Account<'info, ReferrerRecord>>,
+    /// CHECK: New MPL-Core badge at `[b"badge", state, authority, tier]`,
+    /// checked in `mint_donor_badge`; required when a threshold is crossed.
+    #[account(mut)]
+    pub badge_asset: Option<UncheckedAccount<'info>>,
+    /// CHECK: MPL-Core program is invoked via CPI.
+    #[account(address = MPL_CORE_PROGRAM_ID)]
+    pub mpl_core_program: Option<UncheckedAccount<'info>>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Deposit<'info> {
+    /// Mints the depositor a frozen MPL-Core asset for badge `tier`. The
+    /// permanent freeze delegate belongs to the state as update authority,
+    /// which never thaws it, so the badge can't be transferred.
+    fn mint_donor_badge(&self, tier: u8, threshold_lamports: u64) -> Result<Pubkey> {
+        let (Some(asset), Some(mpl_core_program)) = (&self.badge_asset, &self.mpl_core_program)
+        else {
+            return err!(VaultError::InvalidBadgeAsset);
+        };
+        let state_key = self.state.key();
+        let depositor = self.authority.key();
+        let (asset_key, bump) = Pubkey::find_program_address(
+            &[b"badge", state_key.as_ref(), depositor.as_ref(), &[tier]],
+            &crate::ID,
+        );
+        require_keys_eq!(asset.key(), asset_key, VaultError::InvalidBadgeAsset);
+
+        let name = format!("Donor Tier {}", tier + 1);
+        let mut data = vec![MPL_CORE_CREATE_V1, 0]; // data_state: AccountState
+        for field in [name.as_str(), ""] {
+            data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            data.extend_from_slice(field.as_bytes());
+        }
+        data.push(1); // plugins: Some
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[MPL_CORE_PERMANENT_FREEZE_PLUGIN, 1]); // frozen
+        data.push(0); // plugin authority: default
+        let attributes = [
+            ("tier", (tier as u64 + 1).to_string()),
+            ("threshold_lamports", threshold_lamports.to_string()),
+        ];
+        data.push(MPL_CORE_ATTRIBUTES_PLUGIN);
+        data.extend_from_slice(&(attributes.len() as u32).to_le_bytes());
+        for (key, value) in &attributes {
+            for field in [*key, value.as_str()] {
+                data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+                data.extend_from_slice(field.as_bytes());
+            }
+        }
+        data.push(0); // plugin authority: default
+
+        // Absent optional accounts are passed as the MPL-Core program id.
+        let ix = Instruction {
+            program_id: MPL_CORE_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(asset_key, true),
+                AccountMeta::new_readonly(MPL_CORE_PROGRAM_ID, false),
+                AccountMeta::new_readonly(MPL_CORE_PROGRAM_ID, false),
+                AccountMeta::new(depositor, true),
+                AccountMeta::new_readonly(depositor, false),
+                AccountMeta::new_readonly(state_key, false),
+                AccountMeta::new_readonly(System::id(), false),
+                AccountMeta::new_readonly(MPL_CORE_PROGRAM_ID, false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &ix,
+            &[
+                asset.to_account_info(),
+                self.authority.to_account_info(),
+                self.state.to_account_info(),
+                self.system_program.to_account_info(),
+                mpl_core_program.to_account_info(),
+            ],
+            &[&[
+                b"badge",
+                state_key.as_ref(),
+                depositor.as_ref(),
+                &[tier],
+                &[bump],
+            ]],
+        )?;
+        Ok(asset_key)
+    }
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(amount: u64, referrer: Option<Pubkey>)]
+pub struct DepositToken<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump,
+        constraint = state.config.base_mint == Some(base_mint.key()) @ VaultError::InvalidProfitMint
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA only owns the base token ATA here.
+    pub vault: UncheckedAccount<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub base_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = base_mint, token::authority = authority)]
+    pub depositor_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = base_mint,
+        associated_token::authority = vault,
+        associated_token::token_program = token_program
+    )]
+    pub vault_base_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, has_one = state)]
+    pub leaderboard: Option<Account<'info, DonorLeaderboard>>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + DepositorRecord::SIZE,
+        seeds = [b"depositor", state.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Option<Account<'info, DepositorRecord>>,
+    /// Opt-in totals for the deposit's `referrer`.
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ReferrerRecord::SIZE,
+        seeds = [
+            b"referrer",
+            state.key().as_ref(),
+            referrer.unwrap_or_default().as_ref()
+        ],
+        bump
+    )]
+    pub referrer_record: Option<Account<'info, ReferrerRecord>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AttestBuyback<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(has_one = state)]
+    pub buyback_record: Account<'info, BuybackRecord>,
+    /// CHECK: Core bridge config; read for the message fee.
+    #[account(mut, seeds = [b"Bridge"], bump, seeds::program = WORMHOLE_CORE_PROGRAM_ID)]
+    pub bridge: UncheckedAccount<'info>,
+    /// CHECK: New message account, created by the CPI.
+    #[account(
+        mut,
+        seeds = [b"wormhole_message", buyback_record.key().as_ref()],
+        bump
+    )]
+    pub message: UncheckedAccount<'info>,
+    /// CHECK: The vault's emitter PDA, signs the message.
+    #[account(seeds = [b"emitter", state.key().as_ref()], bump)]
+    pub emitter: UncheckedAccount<'info>,
+    /// CHECK: The emitter's sequence tracker, owned by the core bridge.
+    #[account(
+        mut,
+        seeds = [b"Sequence", emitter.key().as_ref()],
+        bump,
+        seeds::program = WORMHOLE_CORE_PROGRAM_ID
+    )]
+    pub sequence: UncheckedAccount<'info>,
+    /// CHECK: Receives the bridge fee.
+    #[account(
+        mut,
+        seeds = [b"fee_collector"],
+        bump,
+        seeds::program = WORMHOLE_CORE_PROGRAM_ID
+    )]
+    pub fee_collector: UncheckedAccount<'info>,
+    /// CHECK: Clock sysvar, required by the core bridge.
+    #[account(address = sysvar::clock::ID)]
+    pub clock: UncheckedAccount<'info>,
+    /// CHECK: Rent sysvar, required by the core bridge.
+    #[account(address = sysvar::rent::ID)]
+    pub rent: UncheckedAccount<'info>,
+    /// CHECK: Wormhole core bridge is invoked via CPI.
+    #[account(address = WORMHOLE_CORE_PROGRAM_ID)]
+    pub wormhole_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitSplitter<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + RevenueSplitter::SIZE,
+        seeds = [b"splitter", authority.key().as_ref()],
+        bump
+    )]
+    pub splitter: Account<'info, RevenueSplitter>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateSplitter<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"splitter", authority.key().as_ref()],
+        bump = splitter.bump
+    )]
+    pub splitter: Account<'info, RevenueSplitter>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SplitRevenue<'info> {
+    #[account(
+        mut,
+        seeds = [b"splitter", splitter.authority.as_ref()],
+        bump = splitter.bump
+    )]
+    pub splitter: Account<'info, RevenueSplitter>,
+}
+
+/// Operator-pass NFT of the crank payer, required with
+/// `config.operator_collection`.
+#[derive(Accounts)]
+pub struct OperatorPass<'info> {
+    /// Payer's token account holding the pass.
+    pub token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Metaplex metadata of `token_account`'s mint, parsed by hand.
+    pub metadata: Option<UncheckedAccount<'info>>,
+}
+
+/// Accounts shared by every instruction that runs `distribute`: the vault,
+/// the buyback mint and the strategy destinations it pays into.
+#[derive(Accounts)]
+pub struct DistributionContext<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// Writable so `crank` can auto-unlock burn-at-unlock tranches.
+    #[account(mut)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = state,
+        associated_token::token_program = mint_token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = burn_authority,
+        associated_token::token_program = mint_token_program
+    )]
+    pub burn_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Recreated here if `unlock` closed it after draining.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = timelock_authority,
+        associated_token::token_program = mint_token_program
+    )]
+    pub timelock_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Burn address is a known public key (e.g., Incinerator)
+    #[account(address = state.burn_address)]
+    pub burn_authority: UncheckedAccount<'info>,
+    /// CHECK: Timelock PDA that can later unlock
+    #[account(
+        seeds = [b"timelock", state.key().as_ref()],
+        bump = state.timelock_bump
+    )]
+    pub timelock_authority: UncheckedAccount<'info>,
+    #[account(mut, token::mint = mint)]
+    pub stake_rewards_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, token::mint = mint)]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Keeper's token account for `config.keeper_reward_bps`; omit to skip
+    /// the reward.
+    #[account(mut, token::mint = mint, token::authority = payer)]
+    pub keeper_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Pyth, Switchboard or Chainlink feed matching
+    /// `config.price_oracle`, parsed by hand.
+    pub price_oracle: Option<UncheckedAccount<'info>>,
+    /// CHECK: Pyth, Switchboard or Chainlink feed matching
+    /// `oracle_guard.quote_oracle`, parsed by hand; required with
+    /// `config.oracle_guard`.
+    pub quote_oracle: Option<UncheckedAccount<'info>>,
+    /// CHECK: Pass to log a readable buyback summary memo.
+    #[account(address = SPL_MEMO_PROGRAM_ID)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + LockTranche::SIZE,
+        seeds = [b"lock", state.key().as_ref(), &state.lock_count.to_le_bytes()],
+        bump
+    )]
+    pub lock_tranche: Option<Account<'info, LockTranche>>,
+    pub mint_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Crank<'info> {
+    pub distribution: DistributionContext<'info>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused @ VaultError::ProtocolPaused
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    pub operator_pass: OperatorPass<'info>,
+    #[account(
+        init_if_needed,
+        payer = distribution.payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = distribution.vault
+    )]
+    pub vault_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: Swap program of `config.aggregator`, checked before the CPI.
+    pub aggregator_program: UncheckedAccount<'info>,
+    /// Omit with `config.compressed_records` to store the record in the
+    /// Light state tree instead.
+    #[account(
+        init,
+        payer = distribution.payer,
+        space = 8 + BuybackRecord::SIZE,
+        seeds = [
+            b"record",
+            distribution.state.key().as_ref(),
+            &distribution.state.crank_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub buyback_record: Option<Box<Account<'info, BuybackRecord>>>,
+    /// CHECK: Light system program, for compressed records.
+    #[account(address = LIGHT_SYSTEM_PROGRAM_ID)]
+    pub light_system_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: This program's Light CPI signer PDA.
+    #[account(seeds = [LIGHT_CPI_AUTHORITY_SEED], bump)]
+    pub light_cpi_authority: Option<UncheckedAccount<'info>>,
+    /// CHECK: This program's registration with Light, validated by Light.
+    pub registered_program_pda: Option<UncheckedAccount<'info>>,
+    /// CHECK: Noop program Light logs compressed accounts through.
+    #[account(address = NOOP_PROGRAM_ID)]
+    pub noop_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: Validated by Light.
+    pub account_compression_authority: Option<UncheckedAccount<'info>>,
+    /// CHECK: Light account compression program.
+    #[account(address = ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub account_compression_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: Light state tree the record is appended to.
+    #[account(mut, owner = ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub state_merkle_tree: Option<UncheckedAccount<'info>>,
+    /// Recent-buyback ring buffer; written when passed.
+    #[account(mut, constraint = buyback_history.load()?.state == distribution.state.key())]
+    pub buyback_history: Option<AccountLoader<'info, BuybackHistory>>,
+    /// Route written ahead of time for automation; replaces the swap data.
+    #[account(
+        constraint = approved_route.state == distribution.state.key(),
+        seeds = [b"route", distribution.state.key().as_ref()],
+        bump = approved_route.bump
+    )]
+    pub approved_route: Option<Box<Account<'info, ApprovedRoute>>>,
+    /// Matured tranche released after distribution when
+    /// `config.auto_unlock` is set.
+    #[account(
+        mut,
+        constraint = matured_tranche.state == distribution.state.key(),
+        constraint = matured_tranche.mint == distribution.mint.key()
+    )]
+    pub matured_tranche: Option<Box<Account<'info, LockTranche>>>,
+    /// Authority-owned token account for `UnlockMode::Release`, or the
+    /// escrow for `UnlockMode::StakingRewards`.
+    #[account(mut, token::mint = distribution.mint)]
+    pub unlock_destination: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Receives the matured tranche's rent once it is fully claimed;
+    /// checked against `matured_tranche.payer`.
+    #[account(mut)]
+    pub matured_tranche_payer: Option<UncheckedAccount<'info>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CrankTwoHop<'info> {
+    pub distribution: DistributionContext<'info>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused @ VaultError::ProtocolPaused
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    pub operator_pass: OperatorPass<'info>,
+    #[account(
+        init_if_needed,
+        payer = distribution.payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = distribution.vault
+    )]
+    pub vault_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        constraint = distribution.state.config.intermediate_mint == Some(intermediate_mint.key())
+            @ VaultError::PoolNotConfigured
+    )]
+    pub intermediate_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = distribution.payer,
+        associated_token::mint = intermediate_mint,
+        associated_token::authority = distribution.vault
+    )]
+    pub vault_intermediate_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Swap program of `config.aggregator`, checked before the CPI.
+    pub aggregator_program: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = distribution.payer,
+        space = 8 + BuybackRecord::SIZE,
+        seeds = [
+            b"record",
+            distribution.state.key().as_ref(),
+            &distribution.state.crank_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub buyback_record: Box<Account<'info, BuybackRecord>>,
+    /// Recent-buyback ring buffer; written when passed.
+    #[account(mut, constraint = buyback_history.load()?.state == distribution.state.key())]
+    pub buyback_history: Option<AccountLoader<'info, BuybackHistory>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CrankMulti<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused @ VaultError::ProtocolPaused
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    pub operator_pass: OperatorPass<'info>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: Timelock PDA that can later unlock
+    #[account(
+        seeds = [b"timelock", state.key().as_ref()],
+        bump = state.timelock_bump
+    )]
+    pub timelock_authority: UncheckedAccount<'info>,
+    /// CHECK: Swap program of `config.aggregator`, checked before the CPI.
+    pub aggregator_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CrankSplProfit<'info> {
+    pub distribution: DistributionContext<'info>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused @ VaultError::ProtocolPaused
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    pub operator_pass: OperatorPass<'info>,
+    #[account(
+        constraint = profit_mint.key() != distribution.mint.key()
+            && profit_mint.key() != token::spl_token::native_mint::ID
+            @ VaultError::InvalidProfitMint
+    )]
+    pub profit_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = profit_mint,
+        associated_token::authority = distribution.vault
+    )]
+    pub vault_profit_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Swap program of `config.aggregator`, checked before the CPI.
+    pub aggregator_program: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = distribution.payer,
+        space = 8 + BuybackRecord::SIZE,
+        seeds = [
+            b"record",
+            distribution.state.key().as_ref(),
+            &distribution.state.crank_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub buyback_record: Box<Account<'info, BuybackRecord>>,
+    /// Recent-buyback ring buffer; written when passed.
+    #[account(mut, constraint = buyback_history.load()?.state == distribution.state.key())]
+    pub buyback_history: Option<AccountLoader<'info, BuybackHistory>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PrepareSwap<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused @ VaultError::ProtocolPaused
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    pub operator_pass: OperatorPass<'info>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = state,
+        associated_token::token_program = mint_token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Swap program of `config.aggregator`, checked before the CPI.
+    pub aggregator_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub mint_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct OpenDca<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused @ VaultError::ProtocolPaused
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    pub operator_pass: OperatorPass<'info>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// Jupiter DCA v2 only takes SPL Token mints.
+    #[account(mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// The DCA's input account, which Jupiter returns unspent WSOL to.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = state
+    )]
+    pub state_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Where Jupiter sends the bought tokens.
+    #[account(
+        associated_token::mint = mint,
+        associated_token::authority = state
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: New DCA order, created by the CPI.
+    #[account(
+        mut,
+        seeds = [
+            b"dca",
+            state.key().as_ref(),
+            wsol_mint.key().as_ref(),
+            mint.key().as_ref(),
+            &state.crank_count.to_le_bytes()
+        ],
+        bump,
+        seeds::program = JUPITER_DCA_PROGRAM_ID
+    )]
+    pub dca: UncheckedAccount<'info>,
+    /// CHECK: DCA's WSOL ATA, validated by Jupiter.
+    #[account(mut)]
+    pub dca_in_ata: UncheckedAccount<'info>,
+    /// CHECK: DCA's token ATA, validated by Jupiter.
+    #[account(mut)]
+    pub dca_out_ata: UncheckedAccount<'info>,
+    /// CHECK: Jupiter DCA's event authority PDA.
+    #[account(seeds = [b"__event_authority"], bump, seeds::program = JUPITER_DCA_PROGRAM_ID)]
+    pub dca_event_authority: UncheckedAccount<'info>,
+    /// CHECK: Jupiter DCA program is invoked via CPI.
+    #[account(address = JUPITER_DCA_PROGRAM_ID)]
+    pub dca_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SettleDca<'info> {
+    #[account(
+        mut,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// Jupiter DCA v2 only takes SPL Token mints.
+    #[account(mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = state
+    )]
+    pub state_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        associated_token::mint = mint,
+        associated_token::authority = state
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Checked against `state.dca`; must have been closed by Jupiter.
+    pub dca: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FinalizeDistribution<'info> {
+    pub distribution: DistributionContext<'info>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused @ VaultError::ProtocolPaused
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    #[account(
+        mut,
+        associated_token::mint = token::spl_token::native_mint::ID,
+        associated_token::authority = distribution.vault
+    )]
+    pub vault_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = distribution.payer,
+        space = 8 + BuybackRecord::SIZE,
+        seeds = [
+            b"record",
+            distribution.state.key().as_ref(),
+            &distribution.state.crank_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub buyback_record: Box<Account<'info, BuybackRecord>>,
+    /// Recent-buyback ring buffer; written when passed.
+    #[account(mut, constraint = buyback_history.load()?.state == distribution.state.key())]
+    pub buyback_history: Option<AccountLoader<'info, BuybackHistory>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CrankMany<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused @ VaultError::ProtocolPaused
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    pub operator_pass: OperatorPass<'info>,
+    /// CHECK: Swap program of `config.aggregator`, checked before the CPI.
+    pub aggregator_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DistributePending<'info> {
+    pub distribution: DistributionContext<'info>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused @ VaultError::ProtocolPaused
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CrankRaydium<'info> {
+    pub distribution: DistributionContext<'info>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused @ VaultError::ProtocolPaused
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    pub operator_pass: OperatorPass<'info>,
+    #[account(
+        init_if_needed,
+        payer = distribution.payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = distribution.vault
+    )]
+    pub vault_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: Raydium CPMM program is invoked via CPI.
+    #[account(address = RAYDIUM_CPMM_PROGRAM_ID)]
+    pub raydium_program: UncheckedAccount<'info>,
+    /// CHECK: Pool vault authority, validated by Raydium.
+    pub raydium_authority: UncheckedAccount<'info>,
+    /// CHECK: Pool AMM config, validated by Raydium.
+    pub raydium_amm_config: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.raydium_pool`.
+    #[account(mut, owner = RAYDIUM_CPMM_PROGRAM_ID)]
+    pub raydium_pool_state: UncheckedAccount<'info>,
+    /// CHECK: Pool WSOL vault, validated by Raydium.
+    #[account(mut)]
+    pub raydium_input_vault: UncheckedAccount<'info>,
+    /// CHECK: Pool token vault, validated by Raydium.
+    #[account(mut)]
+    pub raydium_output_vault: UncheckedAccount<'info>,
+    /// CHECK: Pool observation state, validated by Raydium.
+    #[account(mut)]
+    pub raydium_observation_state: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = distribution.payer,
+        space = 8 + BuybackRecord::SIZE,
+        seeds = [
+            b"record",
+            distribution.state.key().as_ref(),
+            &distribution.state.crank_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub buyback_record: Box<Account<'info, BuybackRecord>>,
+    /// Recent-buyback ring buffer; written when passed.
+    #[account(mut, constraint = buyback_history.load()?.state == distribution.state.key())]
+    pub buyback_history: Option<AccountLoader<'info, BuybackHistory>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CrankWhirlpool<'info> {
+    pub distribution: DistributionContext<'info>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused @ VaultError::ProtocolPaused
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    pub operator_pass: OperatorPass<'info>,
+    #[account(
+        init_if_needed,
+        payer = distribution.payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = distribution.vault
+    )]
+    pub vault_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: Whirlpool program is invoked via CPI.
+    #[account(address = WHIRLPOOL_PROGRAM_ID)]
+    pub whirlpool_program: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.whirlpool`.
+    #[account(mut, owner = WHIRLPOOL_PROGRAM_ID)]
+    pub whirlpool: UncheckedAccount<'info>,
+    /// CHECK: Whirlpool token A vault, validated by Orca.
+    #[account(mut)]
+    pub whirlpool_token_vault_a: UncheckedAccount<'info>,
+    /// CHECK: Whirlpool token B vault, validated by Orca.
+    #[account(mut)]
+    pub whirlpool_token_vault_b: UncheckedAccount<'info>,
+    /// CHECK: Tick array, validated by Orca.
+    #[account(mut)]
+    pub tick_array_0: UncheckedAccount<'info>,
+    /// CHECK: Tick array, validated by Orca.
+    #[account(mut)]
+    pub tick_array_1: UncheckedAccount<'info>,
+    /// CHECK: Tick array, validated by Orca.
+    #[account(mut)]
+    pub tick_array_2: UncheckedAccount<'info>,
+    /// CHECK: Whirlpool oracle PDA, validated by Orca.
+    #[account(mut)]
+    pub whirlpool_oracle: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = distribution.payer,
+        space = 8 + BuybackRecord::SIZE,
+        seeds = [
+            b"record",
+            distribution.state.key().as_ref(),
+            &distribution.state.crank_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub buyback_record: Box<Account<'info, BuybackRecord>>,
+    /// Recent-buyback ring buffer; written when passed.
+    #[account(mut, constraint = buyback_history.load()?.state == distribution.state.key())]
+    pub buyback_history: Option<AccountLoader<'info, BuybackHistory>>,
+    /// Whirlpool `swap` only takes SPL Token mints.
+    #[account(address = distribution.mint_token_program.key())]
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitOpenBookOrders<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        has_one = authority,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+    #[account(
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA owns the open orders account and signs for it.
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: New OpenBook open orders indexer, created by the CPI.
+    #[account(
+        mut,
+        seeds = [b"OpenOrdersIndexer", vault.key().as_ref()],
+        bump,
+        seeds::program = OPENBOOK_V2_PROGRAM_ID
+    )]
+    pub open_orders_indexer: UncheckedAccount<'info>,
+    /// CHECK: New OpenBook open orders account, created by the CPI.
+    #[account(
+        mut,
+        seeds = [
+            b"OpenOrders",
+            vault.key().as_ref(),
+            &OPENBOOK_ACCOUNT_NUM.to_le_bytes()
+        ],
+        bump,
+        seeds::program = OPENBOOK_V2_PROGRAM_ID
+    )]
+    pub open_orders_account: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.openbook_bids`.
+    #[account(
+        owner = OPENBOOK_V2_PROGRAM_ID,
+        constraint = state.config.openbook_bids.is_some_and(|b| b.market == market.key())
+            @ VaultError::PoolNotConfigured
+    )]
+    pub market: UncheckedAccount<'info>,
+    /// CHECK: OpenBook program is invoked via CPI.
+    #[account(address = OPENBOOK_V2_PROGRAM_ID)]
+    pub openbook_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CrankOpenBook<'info> {
+    pub distribution: DistributionContext<'info>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused @ VaultError::ProtocolPaused
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    pub operator_pass: OperatorPass<'info>,
+    /// Holds the unfilled bid between cranks.
+    #[account(
+        init_if_needed,
+        payer = distribution.payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = distribution.vault
+    )]
+    pub vault_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: OpenBook program is invoked via CPI.
+    #[account(address = OPENBOOK_V2_PROGRAM_ID)]
+    pub openbook_program: UncheckedAccount<'info>,
+    /// CHECK: Vault's open orders account, from `init_openbook_orders`.
+    #[account(
+        mut,
+        seeds = [
+            b"OpenOrders",
+            distribution.vault.key().as_ref(),
+            &OPENBOOK_ACCOUNT_NUM.to_le_bytes()
+        ],
+        bump,
+        seeds::program = OPENBOOK_V2_PROGRAM_ID
+    )]
+    pub open_orders_account: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.openbook_bids`.
+    #[account(mut, owner = OPENBOOK_V2_PROGRAM_ID)]
+    pub market: UncheckedAccount<'info>,
+    /// CHECK: Market authority PDA, validated by OpenBook.
+    pub market_authority: UncheckedAccount<'info>,
+    /// CHECK: Validated by OpenBook.
+    #[account(mut)]
+    pub bids: UncheckedAccount<'info>,
+    /// CHECK: Validated by OpenBook.
+    #[account(mut)]
+    pub asks: UncheckedAccount<'info>,
+    /// CHECK: Validated by OpenBook.
+    #[account(mut)]
+    pub event_heap: UncheckedAccount<'info>,
+    /// CHECK: Validated by OpenBook.
+    #[account(mut)]
+    pub market_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Validated by OpenBook.
+    #[account(mut)]
+    pub market_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: The market's own oracles, when it has them; validated by
+    /// OpenBook.
+    pub market_oracle_a: Option<UncheckedAccount<'info>>,
+    /// CHECK: Validated by OpenBook.
+    pub market_oracle_b: Option<UncheckedAccount<'info>>,
+    /// CHECK: SOL/USD feed matching `openbook_bids.sol_oracle`, parsed by
+    /// hand.
+    pub sol_oracle: UncheckedAccount<'info>,
+    /// Required when the crank settles fills.
+    #[account(
+        init,
+        payer = distribution.payer,
+        space = 8 + BuybackRecord::SIZE,
+        seeds = [
+            b"record",
+            distribution.state.key().as_ref(),
+            &distribution.state.crank_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub buyback_record: Option<Box<Account<'info, BuybackRecord>>>,
+    /// Recent-buyback ring buffer; written when passed.
+    #[account(mut, constraint = buyback_history.load()?.state == distribution.state.key())]
+    pub buyback_history: Option<AccountLoader<'info, BuybackHistory>>,
+    /// OpenBook v2 only takes SPL Token mints.
+    #[account(address = distribution.mint_token_program.key())]
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CrankPumpAmm<'info> {
+    pub distribution: DistributionContext<'info>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused @ VaultError::ProtocolPaused
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    pub operator_pass: OperatorPass<'info>,
+    #[account(
+        init_if_needed,
+        payer = distribution.payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = distribution.vault
+    )]
+    pub vault_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: pump.fun AMM program is invoked via CPI.
+    #[account(address = PUMP_AMM_PROGRAM_ID)]
+    pub pump_program: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.pump_pool`.
+    #[account(mut, owner = PUMP_AMM_PROGRAM_ID)]
+    pub pump_pool: UncheckedAccount<'info>,
+    /// CHECK: pump.fun AMM global config, validated by pump.
+    pub pump_global_config: UncheckedAccount<'info>,
+    /// CHECK: Pool base (token) vault, validated by pump.
+    #[account(mut)]
+    pub pump_pool_base_token_account: UncheckedAccount<'info>,
+    /// CHECK: Pool quote (WSOL) vault, validated by pump.
+    #[account(mut)]
+    pub pump_pool_quote_token_account: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = distribution.payer,
+        space = 8 + BuybackRecord::SIZE,
+        seeds = [
+            b"record",
+            distribution.state.key().as_ref(),
+            &distribution.state.crank_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub buyback_record: Box<Account<'info, BuybackRecord>>,
+    /// Recent-buyback ring buffer; written when passed.
+    #[account(mut, constraint = buyback_history.load()?.state == distribution.state.key())]
+    pub buyback_history: Option<AccountLoader<'info, BuybackHistory>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AddLiquidity<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = state
+    )]
+    pub state_wsol_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = state,
+        associated_token::token_program = mint_token_program
+    )]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Burn address is a known public key (e.g., Incinerator)
+    #[account(address = state.burn_address)]
+    pub burn_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = lp_mint,
+        associated_token::authority = burn_authority
+    )]
+    pub burn_lp_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Raydium CPMM program is invoked via CPI.
+    #[account(address = RAYDIUM_CPMM_PROGRAM_ID)]
+    pub raydium_program: UncheckedAccount<'info>,
+    /// CHECK: Pool vault authority, validated by Raydium.
+    pub raydium_authority: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.raydium_pool`.
+    #[account(mut, owner = RAYDIUM_CPMM_PROGRAM_ID)]
+    pub raydium_pool_state: UncheckedAccount<'info>,
+    /// CHECK: Pool token 0 vault, validated by Raydium.
+    #[account(mut)]
+    pub raydium_token_0_vault: UncheckedAccount<'info>,
+    /// CHECK: Pool token 1 vault, validated by Raydium.
+    #[account(mut)]
+    pub raydium_token_1_vault: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub token_2022_program: Program<'info, Token2022>,
+    pub mint_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct BootstrapRaydiumPool<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = state,
+        associated_token::token_program = mint_token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = vault,
+        associated_token::token_program = mint_token_program
+    )]
+    pub vault_mint_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: LP mint PDA, created by Raydium.
+    #[account(mut)]
+    pub lp_mint: UncheckedAccount<'info>,
+    /// CHECK: Creator LP ATA, created by Raydium; the LP is burned from it.
+    #[account(mut)]
+    pub vault_lp_token_account: UncheckedAccount<'info>,
+    /// CHECK: Raydium CPMM program is invoked via CPI.
+    #[account(address = RAYDIUM_CPMM_PROGRAM_ID)]
+    pub raydium_program: UncheckedAccount<'info>,
+    /// CHECK: Pool AMM config, validated by Raydium.
+    #[account(owner = RAYDIUM_CPMM_PROGRAM_ID)]
+    pub raydium_amm_config: UncheckedAccount<'info>,
+    /// CHECK: Pool vault authority, validated by Raydium.
+    pub raydium_authority: UncheckedAccount<'info>,
+    /// CHECK: Pool PDA, created and validated by Raydium.
+    #[account(mut)]
+    pub raydium_pool_state: UncheckedAccount<'info>,
+    /// CHECK: Pool token 0 vault, created by Raydium.
+    #[account(mut)]
+    pub raydium_token_0_vault: UncheckedAccount<'info>,
+    /// CHECK: Pool token 1 vault, created by Raydium.
+    #[account(mut)]
+    pub raydium_token_1_vault: UncheckedAccount<'info>,
+    /// CHECK: Pool creation fee receiver, validated by Raydium.
+    #[account(mut)]
+    pub raydium_create_pool_fee: UncheckedAccount<'info>,
+    /// CHECK: Pool observation state, created by Raydium.
+    #[account(mut)]
+    pub raydium_observation_state: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub mint_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Rent sysvar, required by Raydium's `initialize`.
+    #[account(address = sysvar::rent::ID)]
+    pub rent: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct HarvestPumpFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: pump.fun creator vault PDA for the vault as creator.
+    #[account(
+        mut,
+        seeds = [b"creator-vault", vault.key().as_ref()],
+        bump,
+        seeds::program = PUMP_FUN_PROGRAM_ID
+    )]
+    pub pump_creator_vault: UncheckedAccount<'info>,
+    /// CHECK: pump.fun event authority PDA.
+    #[account(
+        seeds = [b"__event_authority"],
+        bump,
+        seeds::program = PUMP_FUN_PROGRAM_ID
+    )]
+    pub pump_event_authority: UncheckedAccount<'info>,
+    /// CHECK: pump.fun program is invoked via CPI.
+    #[account(address = PUMP_FUN_PROGRAM_ID)]
+    pub pump_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct HarvestRaydiumFees<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_wsol_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = state,
+        associated_token::token_program = mint_token_program
+    )]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Holds the position NFT; the vault PDA must own it.
+    #[account(token::authority = vault)]
+    pub position_nft_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Raydium CLMM program is invoked via CPI.
+    #[account(address = RAYDIUM_CLMM_PROGRAM_ID)]
+    pub raydium_program: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.raydium_position`.
+    #[account(mut, owner = RAYDIUM_CLMM_PROGRAM_ID)]
+    pub raydium_personal_position: UncheckedAccount<'info>,
+    /// CHECK: Validated by Raydium.
+    #[account(mut, owner = RAYDIUM_CLMM_PROGRAM_ID)]
+    pub raydium_pool_state: UncheckedAccount<'info>,
+    /// CHECK: Validated by Raydium.
+    #[account(mut)]
+    pub raydium_protocol_position: UncheckedAccount<'info>,
+    /// CHECK: Validated by Raydium.
+    #[account(mut)]
+    pub raydium_token_vault_0: UncheckedAccount<'info>,
+    /// CHECK: Validated by Raydium.
+    #[account(mut)]
+    pub raydium_token_vault_1: UncheckedAccount<'info>,
+    /// CHECK: Validated by Raydium.
+    #[account(mut)]
+    pub tick_array_lower: UncheckedAccount<'info>,
+    /// CHECK: Validated by Raydium.
+    #[account(mut)]
+    pub tick_array_upper: UncheckedAccount<'info>,
+    /// CHECK: SPL Memo program, required by Raydium.
+    #[account(address = SPL_MEMO_PROGRAM_ID)]
+    pub memo_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub token_2022_program: Program<'info, Token2022>,
+    pub mint_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct HarvestMeteoraFees<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// Meteora dynamic pools only take SPL Token mints.
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_wsol_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = state
+    )]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Meteora DLMM program is invoked via CPI.
+    #[account(address = METEORA_DLMM_PROGRAM_ID)]
+    pub meteora_program: UncheckedAccount<'info>,
+    /// CHECK: Validated by Meteora.
+    #[account(mut, owner = METEORA_DLMM_PROGRAM_ID)]
+    pub meteora_lb_pair: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.meteora_position`; Meteora checks the vault owns it.
+    #[account(mut, owner = METEORA_DLMM_PROGRAM_ID)]
+    pub meteora_position: UncheckedAccount<'info>,
+    /// CHECK: Validated by Meteora.
+    #[account(mut)]
+    pub bin_array_lower: UncheckedAccount<'info>,
+    /// CHECK: Validated by Meteora.
+    #[account(mut)]
+    pub bin_array_upper: UncheckedAccount<'info>,
+    /// CHECK: Validated by Meteora.
+    #[account(mut)]
+    pub meteora_reserve_x: UncheckedAccount<'info>,
+    /// CHECK: Validated by Meteora.
+    #[account(mut)]
+    pub meteora_reserve_y: UncheckedAccount<'info>,
+    /// CHECK: Meteora event authority PDA.
+    #[account(
+        seeds = [b"__event_authority"],
+        bump,
+        seeds::program = METEORA_DLMM_PROGRAM_ID
+    )]
+    pub meteora_event_authority: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct OpenWhirlpoolPosition<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    /// CHECK: Whirlpool program is invoked via CPI.
+    #[account(address = WHIRLPOOL_PROGRAM_ID)]
+    pub whirlpool_program: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.whirlpool`.
+    #[account(owner = WHIRLPOOL_PROGRAM_ID)]
+    pub whirlpool: UncheckedAccount<'info>,
+    /// CHECK: Position PDA, created by Orca.
+    #[account(
+        mut,
+        seeds = [b"position", position_mint.key().as_ref()],
+        bump,
+        seeds::program = WHIRLPOOL_PROGRAM_ID
+    )]
+    pub position: UncheckedAccount<'info>,
+    /// New keypair for the position NFT mint.
+    #[account(mut)]
+    pub position_mint: Signer<'info>,
+    /// CHECK: State-owned position NFT ATA, created by Orca.
+    #[account(mut)]
+    pub position_token_account: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Rent sysvar, required by Orca's `open_position`.
+    #[account(address = sysvar::rent::ID)]
+    pub rent: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct IncreaseWhirlpoolLiquidity<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// Whirlpool v1 position instructions only take SPL Token mints.
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = state
+    )]
+    pub state_wsol_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = state
+    )]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Holds the position NFT; the state PDA must own it.
+    #[account(token::authority = state)]
+    pub position_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Whirlpool program is invoked via CPI.
+    #[account(address = WHIRLPOOL_PROGRAM_ID)]
+    pub whirlpool_program: UncheckedAccount<'info>,
+    /// CHECK: Validated by Orca.
+    #[account(mut, owner = WHIRLPOOL_PROGRAM_ID)]
+    pub whirlpool: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.whirlpool_position`.
+    #[account(mut, owner = WHIRLPOOL_PROGRAM_ID)]
+    pub position: UncheckedAccount<'info>,
+    /// CHECK: Whirlpool token A vault, validated by Orca.
+    #[account(mut)]
+    pub whirlpool_token_vault_a: UncheckedAccount<'info>,
+    /// CHECK: Whirlpool token B vault, validated by Orca.
+    #[account(mut)]
+    pub whirlpool_token_vault_b: UncheckedAccount<'info>,
+    /// CHECK: Validated by Orca.
+    #[account(mut)]
+    pub tick_array_lower: UncheckedAccount<'info>,
+    /// CHECK: Validated by Orca.
+    #[account(mut)]
+    pub tick_array_upper: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct HarvestWhirlpoolFees<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// Whirlpool v1 position instructions only take SPL Token mints.
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_wsol_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = state
+    )]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Holds the position NFT; the state PDA must own it.
+    #[account(token::authority = state)]
+    pub position_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Whirlpool program is invoked via CPI.
+    #[account(address = WHIRLPOOL_PROGRAM_ID)]
+    pub whirlpool_program: UncheckedAccount<'info>,
+    /// CHECK: Validated by Orca.
+    #[account(mut, owner = WHIRLPOOL_PROGRAM_ID)]
+    pub whirlpool: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.whirlpool_position`.
+    #[account(mut, owner = WHIRLPOOL_PROGRAM_ID)]
+    pub position: UncheckedAccount<'info>,
+    /// CHECK: Whirlpool token A vault, validated by Orca.
+    #[account(mut)]
+    pub whirlpool_token_vault_a: UncheckedAccount<'info>,
+    /// CHECK: Whirlpool token B vault, validated by Orca.
+    #[account(mut)]
+    pub whirlpool_token_vault_b: UncheckedAccount<'info>,
+    /// CHECK: Validated by Orca.
+    pub tick_array_lower: UncheckedAccount<'info>,
+    /// CHECK: Validated by Orca.
+    pub tick_array_upper: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitFeeStakeEscrow<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    /// CHECK: M3M3 program is invoked via CPI.
+    #[account(address = M3M3_PROGRAM_ID)]
+    pub m3m3_program: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.m3m3_vault`.
+    #[account(mut, owner = M3M3_PROGRAM_ID)]
+    pub m3m3_vault: UncheckedAccount<'info>,
+    /// CHECK: State PDA's stake escrow, created by M3M3.
+    #[account(
+        mut,
+        seeds = [b"escrow", m3m3_vault.key().as_ref(), state.key().as_ref()],
+        bump,
+        seeds::program = M3M3_PROGRAM_ID
+    )]
+    pub stake_escrow: UncheckedAccount<'info>,
+    /// CHECK: Validated by M3M3.
+    #[account(mut)]
+    pub full_balance_list: UncheckedAccount<'info>,
+    /// CHECK: Validated by M3M3.
+    #[account(mut)]
+    pub top_staker_list: UncheckedAccount<'info>,
+    /// CHECK: M3M3 event authority PDA.
+    #[account(
+        seeds = [b"__event_authority"],
+        bump,
+        seeds::program = M3M3_PROGRAM_ID
+    )]
+    pub m3m3_event_authority: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FeeStake<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// M3M3 only takes SPL Token mints.
+    #[account(mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = state
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Receives claimed fees; closed back into the vault.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_wsol_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: M3M3 program is invoked via CPI.
+    #[account(address = M3M3_PROGRAM_ID)]
+    pub m3m3_program: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.m3m3_vault`.
+    #[account(mut, owner = M3M3_PROGRAM_ID)]
+    pub m3m3_vault: UncheckedAccount<'info>,
+    /// CHECK: State PDA's stake escrow, from `init_fee_stake_escrow`.
+    #[account(
+        mut,
+        seeds = [b"escrow", m3m3_vault.key().as_ref(), state.key().as_ref()],
+        bump,
+        seeds::program = M3M3_PROGRAM_ID
+    )]
+    pub stake_escrow: UncheckedAccount<'info>,
+    /// CHECK: Lowest top staker, which M3M3 needs when the escrow joins the
+    /// full top staker list.
+    #[account(mut, owner = M3M3_PROGRAM_ID)]
+    pub smallest_stake_escrow: Option<UncheckedAccount<'info>>,
+    /// CHECK: Validated by M3M3.
+    #[account(mut)]
+    pub stake_token_vault: UncheckedAccount<'info>,
+    /// CHECK: Validated by M3M3.
+    #[account(mut)]
+    pub quote_token_vault: UncheckedAccount<'info>,
+    /// CHECK: Validated by M3M3.
+    #[account(mut)]
+    pub full_balance_list: UncheckedAccount<'info>,
+    /// CHECK: Validated by M3M3.
+    #[account(mut)]
+    pub top_staker_list: UncheckedAccount<'info>,
+    /// CHECK: M3M3 event authority PDA.
+    #[account(
+        seeds = [b"__event_authority"],
+        bump,
+        seeds::program = M3M3_PROGRAM_ID
+    )]
+    pub m3m3_event_authority: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StakeIdle<'info> {
+    pub authority: Signer<'info>,
+    /// Rent payer for the vault's LST account.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.stake_pool`.
+    #[account(mut, owner = SPL_STAKE_POOL_PROGRAM_ID)]
+    pub stake_pool: UncheckedAccount<'info>,
+    /// CHECK: Validated by the stake pool program.
+    pub stake_pool_withdraw_authority: UncheckedAccount<'info>,
+    /// CHECK: Validated by the stake pool program.
+    #[account(mut)]
+    pub reserve_stake: UncheckedAccount<'info>,
+    /// CHECK: Validated by the stake pool program.
+    #[account(mut)]
+    pub manager_fee_account: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub pool_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = pool_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_lst_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: SPL stake pool program is invoked via CPI.
+    #[account(address = SPL_STAKE_POOL_PROGRAM_ID)]
+    pub stake_pool_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnstakeIdle<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: Must match `config.stake_pool`.
+    #[account(mut, owner = SPL_STAKE_POOL_PROGRAM_ID)]
+    pub stake_pool: UncheckedAccount<'info>,
+    /// CHECK: Validated by the stake pool program.
+    pub stake_pool_withdraw_authority: UncheckedAccount<'info>,
+    /// CHECK: Validated by the stake pool program.
+    #[account(mut)]
+    pub reserve_stake: UncheckedAccount<'info>,
+    /// CHECK: Validated by the stake pool program.
+    #[account(mut)]
+    pub manager_fee_account: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub pool_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = pool_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_lst_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: SPL stake pool program is invoked via CPI.
+    #[account(address = SPL_STAKE_POOL_PROGRAM_ID)]
+    pub stake_pool_program: UncheckedAccount<'info>,
+    /// CHECK: Clock sysvar, required by the stake pool.
+    #[account(address = sysvar::clock::ID)]
+    pub clock: UncheckedAccount<'info>,
+    /// CHECK: Stake history sysvar, required by the stake pool.
+    #[account(address = sysvar::stake_history::ID)]
+    pub stake_history: UncheckedAccount<'info>,
+    /// CHECK: Native stake program, required by the stake pool.
+    #[account(address = stake::program::ID)]
+    pub stake_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitYieldAccount<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// New keypair for the backend account.
+    #[account(mut)]
+    pub yield_account: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositIdle<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_wsol_ata: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawIdle<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_wsol_ata: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CollectProtocolFee<'info> {
+    #[account(
+        mut,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    #[account(seeds = [b"protocol"], bump = protocol_config.bump)]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    /// CHECK: Checked against the protocol config.
+    #[account(mut, address = protocol_config.settings.treasury)]
+    pub treasury: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"keeper_pool"], bump = keeper_pool.bump)]
+    pub keeper_pool: Account<'info, KeeperPool>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimKeeperTip<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(mut, has_one = state, has_one = keeper)]
+    pub buyback_record: Account<'info, BuybackRecord>,
+    #[account(seeds = [b"protocol"], bump = protocol_config.bump)]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(mut, seeds = [b"keeper_pool"], bump = keeper_pool.bump)]
+    pub keeper_pool: Account<'info, KeeperPool>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct BurnSol<'info> {
+    #[account(
+        mut,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: Burn address is a known public key (e.g., Incinerator)
+    #[account(mut, address = state.burn_address)]
+    pub burn_authority: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseRetained<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = state,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = mint)]
+    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PublishDistribution<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        has_one = mint,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = state,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + MerkleDistribution::SIZE,
+        seeds = [
+            b"distribution",
+            state.key().as_ref(),
+            &state.distribution_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub distribution: Box<Account<'info, MerkleDistribution>>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = distribution,
+        associated_token::token_program = token_program
+    )]
+    pub distribution_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimDistribution<'info> {
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, VaultState>>,
+    #[account(
+        mut,
+        has_one = state,
+        seeds = [
+            b"distribution",
+            state.key().as_ref(),
+            &distribution.index.to_le_bytes()
+        ],
+        bump = distribution.bump
+    )]
+    pub distribution: Box<Account<'info, MerkleDistribution>>,
+    #[account(address = distribution.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = distribution,
+        associated_token::token_program = token_program
+    )]
+    pub distribution_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, token::mint = mint)]
+    pub claimant_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = claimant,
+        space = 8 + ClaimStatus::SIZE,
+        seeds = [b"claim", distribution.key().as_ref(), claimant.key().as_ref()],
+        bump
+    )]
+    pub claim_status: Account<'info, ClaimStatus>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AirdropTranche<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, has_one = authority)]
+    pub state: Box<Account<'info, VaultState>>,
+    #[account(mut, has_one = state)]
+    pub lock_tranche: Box<Account<'info, LockTranche>>,
+    #[account(address = lock_tranche.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = timelock_authority,
+        associated_token::token_program = token_program
+    )]
+    pub timelock_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Timelock PDA signer
+    #[account(
+        seeds = [b"timelock", state.key().as_ref()],
+        bump = state.timelock_bump
+    )]
+    pub timelock_authority: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + MerkleDistribution::SIZE,
+        seeds = [
+            b"distribution",
+            state.key().as_ref(),
+            &state.distribution_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub distribution: Box<Account<'info, MerkleDistribution>>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = distribution,
+        associated_token::token_program = token_program
+    )]
+    pub distribution_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Only receives the closed tranche's rent.
+    #[account(mut, address = lock_tranche.payer)]
+    pub rent_receiver: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Unlock<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut, has_one = authority)]
+    pub state: Account<'info, VaultState>,
+    #[account(mut, has_one = state)]
+    pub lock_tranche: Account<'info, LockTranche>,
+    #[account(mut, address = lock_tranche.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = timelock_authority,
+        associated_token::token_program = token_program
+    )]
+    pub timelock_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Omitted for burn-at-unlock tranches.
+    #[account(mut, token::mint = mint)]
+    pub destination_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Timelock PDA signer
+    #[account(
+        seeds = [b"timelock", state.key().as_ref()],
+        bump = state.timelock_bump
+    )]
+    pub timelock_authority: UncheckedAccount<'info>,
+    /// CHECK: Only receives the closed tranche's rent.
+    #[account(mut, address = lock_tranche.payer)]
+    pub rent_receiver: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct StreamLock<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut, has_one = authority)]
+    pub state: Account<'info, VaultState>,
+    #[account(mut, has_one = state)]
+    pub lock_tranche: Account<'info, LockTranche>,
+    #[account(address = lock_tranche.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = timelock_authority,
+        associated_token::token_program = token_program
+    )]
+    pub timelock_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Timelock PDA; signs as the stream's sender and pays its rent.
+    #[account(
+        mut,
+        seeds = [b"timelock", state.key().as_ref()],
+        bump = state.timelock_bump
+    )]
+    pub timelock_authority: UncheckedAccount<'info>,
+    /// CHECK: Checked against the tranche's `UnlockMode::Streamflow`.
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+    /// CHECK: Recipient's ATA; created by Streamflow when missing.
+    #[account(mut)]
+    pub recipient_token_account: UncheckedAccount<'info>,
+    /// CHECK: Only receives the closed tranche's rent.
+    #[account(mut, address = lock_tranche.payer)]
+    pub rent_receiver: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RequestEarlyUnlock<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub state: Account<'info, VaultState>,
+    #[account(has_one = state)]
+    pub lock_tranche: Account<'info, LockTranche>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + EarlyUnlock::SIZE,
+        seeds = [b"early_unlock", lock_tranche.key().as_ref()],
+        bump
+    )]
+    pub early_unlock: Account<'info, EarlyUnlock>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct EarlyUnlockRelease<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut, has_one = authority)]
+    pub state: Account<'info, VaultState>,
+    #[account(mut, has_one = state)]
+    pub lock_tranche: Account<'info, LockTranche>,
+    /// Closed to `authority` once the tranche is drained.
+    #[account(mut, has_one = state, has_one = lock_tranche)]
+    pub early_unlock: Account<'info, EarlyUnlock>,
+    #[account(address = lock_tranche.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = timelock_authority,
+        associated_token::token_program = token_program
+    )]
+    pub timelock_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// The council's `early_unlock.destination`; omitted for burn-at-unlock
+    /// tranches.
+    #[account(
+        mut,
+        token::mint = mint,
+        constraint = early_unlock.destination == Some(destination_token_account.key())
+            @ VaultError::MissingStrategyAccount
+    )]
+    pub destination_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Timelock PDA signer
+    #[account(
+        seeds = [b"timelock", state.key().as_ref()],
+        bump = state.timelock_bump
+    )]
+    pub timelock_authority: UncheckedAccount<'info>,
+    /// CHECK: Only receives the closed tranche's rent.
+    #[account(mut, address = lock_tranche.payer)]
+    pub rent_receiver: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Relock<'info> {
+    pub authority: Signer<'info>,
+    /// Pays the tranche's rent and gets it back when the tranche closes.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, has_one = authority)]
+    pub state: Account<'info, VaultState>,
+    /// Only the current mint, so the lock totals stay in one token.
+    #[account(address = state.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint, token::authority = authority)]
+    pub source_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Recreate it through the ATA program first if `unlock` closed it.
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = timelock_authority,
+        associated_token::token_program = token_program
+    )]
+    pub timelock_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Timelock PDA that owns the locked tokens
+    #[account(
+        seeds = [b"timelock", state.key().as_ref()],
+        bump = state.timelock_bump
+    )]
+    pub timelock_authority: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + LockTranche::SIZE,
+        seeds = [b"lock", state.key().as_ref(), &state.lock_count.to_le_bytes()],
+        bump
+    )]
+    pub lock_tranche: Account<'info, LockTranche>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintLockReceipt<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(has_one = authority)]
+    pub state: Account<'info, VaultState>,
+    #[account(has_one = state)]
+    pub lock_tranche: Account<'info, LockTranche>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"receipt", lock_tranche.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = state,
+        mint::freeze_authority = state
+    )]
+    pub receipt_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = receipt_mint,
+        associated_token::authority = authority
+    )]
+    pub authority_receipt_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Metaplex metadata PDA for `receipt_mint`, checked in the handler.
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: Metaplex Token Metadata program is invoked via CPI.
+    #[account(address = TOKEN_METADATA_PROGRAM_ID)]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintBuybackProof<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(has_one = state)]
+    pub buyback_record: Account<'info, BuybackRecord>,
+    /// CHECK: New MPL-Core asset, created by the CPI.
+    #[account(
+        mut,
+        seeds = [b"proof", buyback_record.key().as_ref()],
+        bump
+    )]
+    pub asset: UncheckedAccount<'info>,
+    /// CHECK: Checked against `config.proof_recipient`.
+    pub recipient: UncheckedAccount<'info>,
+    /// CHECK: MPL-Core program is invoked via CPI.
+    #[account(address = MPL_CORE_PROGRAM_ID)]
+    pub mpl_core_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitLeaderboard<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + DonorLeaderboard::SIZE,
+        seeds = [b"leaderboard", state.key().as_ref()],
+        bump
+    )]
+    pub leaderboard: Account<'info, DonorLeaderboard>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitApprovedRoute<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ApprovedRoute::SIZE,
+        seeds = [b"route", state.key().as_ref()],
+        bump
+    )]
+    pub approved_route: Account<'info, ApprovedRoute>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetApprovedRoute<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
         bump = state.bump
     )]
     pub state: Account<'info, VaultState>,
     #[account(
         mut,
+        has_one = state,
+        seeds = [b"route", state.key().as_ref()],
+        bump = approved_route.bump
+    )]
+    pub approved_route: Account<'info, ApprovedRoute>,
+}
+
+#[derive(Accounts)]
+pub struct InitHistory<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + BuybackHistory::SIZE,
+        seeds = [b"history", state.key().as_ref()],
+        bump
+    )]
+    pub buyback_history: AccountLoader<'info, BuybackHistory>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SimulateCrank<'info> {
+    #[account(
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(
         seeds = [b"vault", state.key().as_ref()],
         bump = state.vault_bump
     )]
     /// CHECK: Vault PDA is owned by the system program and holds SOL only.
     pub vault: UncheckedAccount<'info>,
-    pub mint: Account<'info, Mint>,
-    pub system_program: Program<'info, System>,
+    #[account(seeds = [b"protocol"], bump = protocol_config.bump)]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+pub struct ViewState<'info> {
+    #[account(
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+}
+
+#[derive(Accounts)]
+pub struct AssertInvariants<'info> {
+    #[account(
+        seeds = [b"state", state.seed_mint.as_ref(), state.authority.as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, VaultState>,
+    #[account(address = state.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        seeds = [b"vault", state.key().as_ref()],
+        bump = state.vault_bump
+    )]
+    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: Timelock PDA authority
+    #[account(
+        seeds = [b"timelock", state.key().as_ref()],
+        bump = state.timelock_bump
+    )]
+    pub timelock_authority: UncheckedAccount<'info>,
+    #[account(
+        associated_token::mint = mint,
+        associated_token::authority = state,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        associated_token::mint = mint,
+        associated_token::authority = timelock_authority,
+        associated_token::token_program = token_program
+    )]
+    pub timelock_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(token::mint = mint)]
+    pub burn_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> OperatorPass<'info> {
+    /// With `config.operator_collection` set, only holders of an NFT from
+    /// that collection may crank: `token_account` must be `payer`'s and hold
+    /// it, and `metadata`'s collection must be verified. Without it cranking
+    /// stays permissionless.
+    fn check(&self, config: &VaultConfig, payer: Pubkey) -> Result<()> {
+        let Some(collection) = config.operator_collection else {
+            return Ok(());
+        };
+        let (Some(pass), Some(metadata)) = (&self.token_account, &self.metadata) else {
+            return err!(VaultError::NotOperator);
+        };
+        require!(
+            pass.owner == payer && pass.amount == 1,
+            VaultError::NotOperator
+        );
+        require!(
+            metadata_verified_collection(metadata, &pass.mint)? == Some(collection),
+            VaultError::NotOperator
+        );
+        Ok(())
+    }
+}
+
+impl<'info> DistributionContext<'info> {
+    /// `vault_wsol_ata` is closed back to the vault with `token_program`
+    /// after distribution; `profit_decimals` is what the crank's profit
+    /// counts in. `transfer_hook_accounts` is searched for `mint`'s hook
+    /// accounts.
+    fn accounts(
+        &self,
+        vault_wsol_ata: Option<AccountInfo<'info>>,
+        profit_decimals: u8,
+        token_program: AccountInfo<'info>,
+        transfer_hook_accounts: &[AccountInfo<'info>],
+    ) -> DistributionAccounts<'info> {
+        DistributionAccounts {
+            vault: self.vault.to_account_info(),
+            vault_wsol_ata,
+            vault_token_account: self.vault_token_account.to_account_info(),
+            burn_token_account: self.burn_token_account.to_account_info(),
+            timelock_token_account: self.timelock_token_account.to_account_info(),
+            stake_rewards_token_account: self
+                .stake_rewards_token_account
+                .as_ref()
+                .map(|a| a.to_account_info()),
+            treasury_token_account: self
+                .treasury_token_account
+                .as_ref()
+                .map(|a| a.to_account_info()),
+            keeper_token_account: self
+                .keeper_token_account
+                .as_ref()
+                .map(|a| a.to_account_info()),
+            keeper: self.payer.key(),
+            price_oracle: self.price_oracle.as_ref().map(|a| a.to_account_info()),
+            quote_oracle: self.quote_oracle.as_ref().map(|a| a.to_account_info()),
+            mint: self.mint.to_account_info(),
+            mint_decimals: self.mint.decimals,
+            profit_decimals,
+            memo_program: self.memo_program.as_ref().map(|a| a.to_account_info()),
+            token_program,
+            mint_token_program: self.mint_token_program.to_account_info(),
+            transfer_hook_accounts: transfer_hook_accounts.to_vec(),
+        }
+    }
+}
+
+impl<'info> Crank<'info> {
+    /// The Light accounts for a compressed record, if all were passed.
+    fn light_record_accounts(&self) -> Option<LightRecordAccounts<'info>> {
+        Some(LightRecordAccounts {
+            fee_payer: self.distribution.payer.to_account_info(),
+            cpi_authority: self.light_cpi_authority.as_ref()?.to_account_info(),
+            registered_program_pda: self.registered_program_pda.as_ref()?.to_account_info(),
+            noop_program: self.noop_program.as_ref()?.to_account_info(),
+            account_compression_authority: self
+                .account_compression_authority
+                .as_ref()?
+                .to_account_info(),
+            account_compression_program: self
+                .account_compression_program
+                .as_ref()?
+                .to_account_info(),
+            invoking_program: self.program.to_account_info(),
+            light_system_program: self.light_system_program.as_ref()?.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            state_merkle_tree: self.state_merkle_tree.as_ref()?.to_account_info(),
+        })
+    }
+
+    /// Releases whatever has vested in `matured_tranche`, if one was passed.
+    /// Release-mode tokens may only go to an authority-owned account, since
+    /// the keeper picks the destination; Streamflow and permanent tranches
+    /// are left alone. Returns the `UnlockEvent` of any release.
+    fn auto_unlock(
+        &mut self,
+        now: i64,
+        transfer_hook_accounts: &[AccountInfo<'info>],
+    ) -> Result<Option<UnlockEvent>> {
+        let Some(tranche) = self.matured_tranche.as_mut() else {
+            return Ok(None);
+        };
+        if matches!(
+            tranche.unlock_mode,
+            UnlockMode::Streamflow { .. } | UnlockMode::Permanent | UnlockMode::Airdrop
+        ) {
+            return Ok(None);
+        }
+        let mut amount = tranche.vested(now).saturating_sub(tranche.claimed);
+        if tranche.unlock_mode != UnlockMode::Burn {
+            self.distribution.state.roll_unlock_window(now);
+            amount = amount.min(self.distribution.state.unlock_allowance(now));
+            self.distribution.state.unlock_window_amount += amount;
+        }
+        if amount == 0 {
+            return Ok(None);
+        }
+        if tranche.unlock_mode == UnlockMode::Release {
+            let destination = self
+                .unlock_destination
+                .as_ref()
+                .ok_or(VaultError::MissingStrategyAccount)?;
+            require_keys_eq!(
+                destination.owner,
+                self.distribution.state.authority,
+                VaultError::MissingStrategyAccount
+            );
+        }
+
+        let state_key = self.distribution.state.key();
+        let seeds = &[
+            b"timelock".as_ref(),
+            state_key.as_ref(),
+            &[self.distribution.state.timelock_bump],
+        ];
+        let accounts = ReleaseAccounts {
+            state: state_key,
+            mint: self.distribution.mint.to_account_info(),
+            mint_decimals: self.distribution.mint.decimals,
+            timelock_token_account: self.distribution.timelock_token_account.to_account_info(),
+            timelock_authority: self.distribution.timelock_authority.to_account_info(),
+            destination: self
+                .unlock_destination
+                .as_ref()
+                .map(|a| a.to_account_info()),
+            token_program: self.distribution.mint_token_program.to_account_info(),
+            transfer_hook_accounts: transfer_hook_accounts.to_vec(),
+        };
+        let event = release_tranche(
+            &mut self.distribution.state,
+            tranche,
+            &accounts,
+            amount,
+            &seeds[..],
+        )?;
+        if event.tranche_remaining == 0 {
+            if let Some(payer) = self
+                .matured_tranche_payer
+                .as_ref()
+                .filter(|p| p.key() == tranche.payer)
+            {
+                tranche.close(payer.to_account_info())?;
+            }
+        }
+        Ok(Some(event))
+    }
+}
+
+#[account]
+pub struct VaultState {
+    /// Seeds the state PDA with `mint`, so each authority can run its own
+    /// vault for the same token. Never reassigned. May be a PDA such as a
+    /// Realms governance account or a Squads vault, which signs admin
+    /// instructions through CPI. Admin instructions never create accounts
+    /// with `init_if_needed`, and those that must create one take rent
+    /// from a separate `payer`, so the authority never needs to hold SOL.
+    pub authority: Pubkey,
+    /// Position in the `VaultRegistry`, assigned in creation order.
+    pub vault_id: u64,
+    /// Buyback mint, which `migrate_mint` can replace. SPL Token or
+    /// Token-2022; `check_mint_extensions` rejects the extensions that would
+    /// break burn and lock transfers.
+    pub mint: Pubkey,
+    /// Mint the vault was created for; seeds the state PDA.
+    pub seed_mint: Pubkey,
+    pub burn_address: Pubkey,
+    pub starting_balance_lamports: u64,
+    pub last_crank_ts: i64,
+    pub timelock_unlock_ts: i64,
+    pub bump: u8,
+    pub vault_bump: u8,
+    pub timelock_bump: u8,
+    pub config: VaultConfig,
+    pub swap_pending: bool,
+    pub pending_profit_lamports: u64,
+    pub twap_budget_lamports: u64,
+    pub twap_tranches_left: u8,
+    pub liquidity_reserve_tokens: u64,
+    /// Tokens kept by the `Retain` strategy, excluded from distribution.
+    pub retained_tokens: u64,
+    pub liquidity_reserve_lamports: u64,
+    pub staked_lamports: u64,
+    pub staked_pool_tokens: u64,
+    pub lent_lamports: u64,
+    /// SOL withheld by `config.sol_burn_bps`, waiting for `burn_sol`.
+    pub sol_burn_reserve_lamports: u64,
+    /// Cranks recorded so far; seeds the next `BuybackRecord` and doubles as
+    /// the lifetime crank total.
+    pub crank_count: u64,
+    /// Lock tranches opened so far; seeds the next `LockTranche`.
+    pub lock_count: u64,
+    pub pending_tokens_bought: u64,
+    /// Current and previous `config.unlock_rate_limit` windows.
+    pub unlock_window_start: i64,
+    pub unlock_window_amount: u64,
+    pub unlock_prev_window_amount: u64,
+    /// Lifetime totals for frontends, in lamports and `mint` tokens.
+    /// `total_unlocked` counts everything that left the timelock, including
+    /// burn-at-unlock tranches, which also count towards `total_burned`.
+    pub total_profit_lamports: u64,
+    pub total_burned: u64,
+    pub total_locked: u64,
+    pub total_unlocked: u64,
+    /// Set by `set_paused`; blocks every crank path.
+    pub paused: bool,
+    /// Stamped onto every event so consumers can spot gaps and order them.
+    pub event_seq: u64,
+    /// Volume-weighted average price paid for the tokens still locked, in
+    /// lamports per token base unit scaled by `PRICE_SCALE`.
+    pub lock_cost_basis: u64,
+    /// Mint supply when the vault was initialized.
+    pub initial_supply: u64,
+    /// `total_burned` as bps of `initial_supply`.
+    pub burned_bps_of_supply: u64,
+    /// Payer of the most recent crank.
+    pub last_keeper: Pubkey,
+    /// Tokens paid out through `config.keeper_reward_bps`.
+    pub total_keeper_rewards: u64,
+    /// Buyback run-rate over the last 7 and 30 days. Multi-mint cranks count
+    /// only tokens of `mint`.
+    pub rolling_7d: RollingTotals,
+    pub rolling_30d: RollingTotals,
+    /// Share of each crank's swap input owed to the protocol, fixed at
+    /// `create_vault`, and the SOL withheld for `collect_protocol_fee`.
+    pub protocol_fee_bps: u16,
+    pub protocol_fee_reserve_lamports: u64,
+    /// Minimum time between cranks, from the protocol default at creation.
+    pub crank_interval_secs: i64,
+    /// Mint proposed by `propose_mint_migration`, which `migrate_mint` may
+    /// switch to from `mint_migration_ts`.
+    pub pending_mint: Option<Pubkey>,
+    pub mint_migration_ts: i64,
+    /// Tokens kept by the `HolderRewards` strategy for the next
+    /// `publish_distribution`, excluded from distribution.
+    pub holder_reward_tokens: u64,
+    /// Merkle distributions published so far; seeds the next one.
+    pub distribution_count: u64,
+    /// Epoch of the latest distribution, which allows one per epoch.
+    pub last_distribution_epoch: u64,
+    /// Tokens kept by the `StakeForFees` strategy for the next
+    /// `stake_for_fees`, excluded from distribution.
+    pub fee_stake_tokens: u64,
+    /// Tokens staked into `config.m3m3_vault` so far. They are never
+    /// unstaked.
+    pub staked_fee_tokens: u64,
+    /// Members who can veto a pending mint migration or config change. Kept
+    /// out of `VaultConfig` so `apply_config` can't replace them.
+    pub veto_council: Vec<Pubkey>,
+    /// Veto council signatures required, a majority of the council.
+    pub veto_threshold: u8,
+    /// WSOL the resting `crank_openbook` bid took, to price its fills by.
+    pub openbook_bid_lamports: u64,
+    /// Open Jupiter DCA order from `open_dca`, and the vault token balance
+    /// before it, which `settle_dca` counts the bought tokens from.
+    pub dca: Option<Pubkey>,
+    pub dca_tokens_before: u64,
+    /// Mint the vault bought before its last `migrate_mint`, and how much of
+    /// it open tranches still hold. Those unlocks count here instead of
+    /// towards `total_unlocked` and `total_burned`.
+    pub legacy_mint: Option<Pubkey>,
+    pub legacy_locked: u64,
+    /// Config queued by `propose_config`, which `apply_config` may switch to
+    /// from `config_change_ts`.
+    pub pending_config: Option<VaultConfig>,
+    pub config_change_ts: i64,
+}
+
+impl VaultState {
+    pub const SIZE: usize = 32
+        + 8
+        + 32
+        + 32
+        + 32
+        + 8
+        + 8
+        + 8
+        + 1
+        + 1
+        + 1
+        + VaultConfig::SIZE
+        + 1
+        + 8
+        + 8
+        + 1
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 1
+        + 8
+        + 8
+        + 8
+        + 8
+        + 32
+        + 8
+        + RollingTotals::SIZE
+        + RollingTotals::SIZE
+        + 2
+        + 8
+        + 8
+        + (1 + 32)
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + (4 + MAX_VETO_COUNCIL * 32)
+        + 1
+        + 8
+        + (1 + 32)
+        + 8
+        + (1 + 32)
+        + 8
+        + (1 + VaultConfig::SIZE)
+        + 8;
+
+    /// `last_crank_ts == 0` means the vault has never been cranked, so the
+    /// first crank is allowed immediately. Afterwards the interval is enforced
+    /// with `clock_tolerance_secs` of slack for validator clock drift. Any
+    /// `config.crank_window` applies to every crank, including the first,
+    /// and nothing cranks while the vault is paused.
+    pub fn check_crank_interval(&self, now: i64) -> Result<()> {
+        match self.interval_status(now)? {
+            CrankStatus::Paused => err!(VaultError::Paused),
+            CrankStatus::OutsideCrankWindow => err!(VaultError::OutsideCrankWindow),
+            CrankStatus::ClockSkew => err!(VaultError::ClockSkew),
+            CrankStatus::TooSoon => err!(VaultError::CrankTooSoon),
+            _ => Ok(()),
+        }
+    }
+
+    /// The timing side of `check_crank_interval`, as a status for
+    /// `check_crank`.
+    pub fn interval_status(&self, now: i64) -> Result<CrankStatus> {
+        if self.paused {
+            return Ok(CrankStatus::Paused);
+        }
+        if let Some(window) = self.config.crank_window {
+            if !window.contains(now) {
+                return Ok(CrankStatus::OutsideCrankWindow);
+            }
+        }
+        if self.last_crank_ts == 0 {
+            return Ok(CrankStatus::Ready);
+        }
+        let elapsed = now
+            .checked_sub(self.last_crank_ts)
+            .ok_or(VaultError::MathOverflow)?;
+        let tolerance = self.config.clock_tolerance_secs;
+        Ok(if elapsed < -tolerance {
+            CrankStatus::ClockSkew
+        } else if elapsed.saturating_add(tolerance) < self.crank_interval_secs {
+            CrankStatus::TooSoon
+        } else {
+            CrankStatus::Ready
+        })
+    }
+
+    /// Keeper reward rate, escalating linearly once the vault is overdue
+    /// (past `last_crank_ts + crank_interval_secs`), up to the configured cap.
+    pub fn keeper_reward_bps(&self, now: i64) -> u64 {
+        let base = self.config.keeper_reward_bps as u64;
+        if self.last_crank_ts == 0 {
+            return base;
+        }
+        let due = self.last_crank_ts.saturating_add(self.crank_interval_secs);
+        let overdue_secs = now.saturating_sub(due).max(0) as u64;
+        let escalation = overdue_secs
+            .saturating_mul(self.config.keeper_reward_escalation_bps_per_hour as u64)
+            / 3600;
+        base.saturating_add(escalation)
+            .min(self.config.keeper_reward_max_bps as u64)
+    }
+
+    /// Lamports in the vault that are not profit: the principal plus SOL
+    /// set aside for liquidity, SOL burns and protocol fees, less principal
+    /// currently staked or lent out.
+    pub fn reserved_lamports(&self) -> Result<u64> {
+        Ok(self
+            .starting_balance_lamports
+            .checked_add(self.liquidity_reserve_lamports)
+            .and_then(|v| v.checked_add(self.sol_burn_reserve_lamports))
+            .and_then(|v| v.checked_add(self.protocol_fee_reserve_lamports))
+            .ok_or(VaultError::MathOverflow)?
+            .saturating_sub(self.staked_lamports)
+            .saturating_sub(self.lent_lamports))
+    }
+
+    /// Advances the unlock windows to the one containing `now`.
+    pub fn roll_unlock_window(&mut self, now: i64) {
+        let Some(limit) = self.config.unlock_rate_limit else {
+            return;
+        };
+        let period = limit.period_secs as i64;
+        let elapsed = now.saturating_sub(self.unlock_window_start);
+        if elapsed >= 2 * period {
+            self.unlock_window_start = now;
+            self.unlock_prev_window_amount = 0;
+            self.unlock_window_amount = 0;
+        } else if elapsed >= period {
+            self.unlock_window_start += period;
+            self.unlock_prev_window_amount = self.unlock_window_amount;
+            self.unlock_window_amount = 0;
+        }
+    }
+
+    /// Tokens `unlock` may still release under `config.unlock_rate_limit`.
+    /// The previous window counts in proportion to how much of it still
+    /// overlaps the trailing period, approximating a rolling window.
+    pub fn unlock_allowance(&self, now: i64) -> u64 {
+        let Some(limit) = self.config.unlock_rate_limit else {
+            return u64::MAX;
+        };
+        let period = limit.period_secs as u128;
+        let into_window = now.saturating_sub(self.unlock_window_start).max(0) as u128;
+        let overlap = self.unlock_prev_window_amount as u128 * period.saturating_sub(into_window)
+            / period.max(1);
+        let used = (overlap as u64).saturating_add(self.unlock_window_amount);
+        limit.max_amount.saturating_sub(used)
+    }
+
+    /// Folds `amount` newly locked tokens bought at `price` into
+    /// `lock_cost_basis`; call after `total_locked` includes them. Unlocks
+    /// leave the average unchanged, and a zero price (profit paid in another
+    /// token) is skipped.
+    pub fn add_lock_cost(&mut self, amount: u64, price: u64) {
+        if amount == 0 || price == 0 {
+            return;
+        }
+        let held = self
+            .total_locked
+            .saturating_sub(self.total_unlocked)
+            .saturating_sub(amount) as u128;
+        let amount = amount as u128;
+        self.lock_cost_basis = ((self.lock_cost_basis as u128 * held + price as u128 * amount)
+            / (held + amount)) as u64;
+    }
+
+    /// Counts `amount` tokens of `mint` leaving the timelock, `burned` for
+    /// burn-at-unlock tranches. Tranches of the pre-migration mint only draw
+    /// down `legacy_locked`; other `buyback_mints` legs count towards neither.
+    pub fn add_unlocked(&mut self, mint: Pubkey, amount: u64, burned: bool) {
+        if mint == self.mint {
+            self.total_unlocked = self.total_unlocked.saturating_add(amount);
+            if burned {
+                self.add_burned(amount);
+            }
+        } else if self.legacy_mint == Some(mint) {
+            self.legacy_locked = self.legacy_locked.saturating_sub(amount);
+        }
+    }
+
+    /// Counts `amount` towards `total_burned` and `burned_bps_of_supply`.
+    pub fn add_burned(&mut self, amount: u64) {
+        self.total_burned = self.total_burned.saturating_add(amount);
+        self.burned_bps_of_supply = (self.total_burned as u128 * 10_000
+            / self.initial_supply.max(1) as u128)
+            .min(u64::MAX as u128) as u64;
+    }
+
+    /// Claims the next event sequence number.
+    pub fn next_event_seq(&mut self) -> u64 {
+        let seq = self.event_seq;
+        self.event_seq = seq.wrapping_add(1);
+        seq
+    }
+
+    /// Claims the index for the next `LockTranche`.
+    pub fn next_lock_index(&mut self) -> Result<u64> {
+        let index = self.lock_count;
+        self.lock_count = index.checked_add(1).ok_or(VaultError::MathOverflow)?;
+        Ok(index)
+    }
+
+    /// In `TwapMode::Split` the profit seen when a campaign starts becomes the
+    /// budget, spread evenly over the configured number of cranks; new profit
+    /// arriving mid-campaign waits for the next one.
+    pub fn take_tranche(&mut self, profit: u64) -> u64 {
+        match self.config.twap {
+            TwapMode::Off => profit,
+            TwapMode::Fixed { lamports } => profit.min(lamports),
+            TwapMode::Split { tranches } => {
+                if self.twap_tranches_left == 0 || self.twap_budget_lamports == 0 {
+                    self.twap_budget_lamports = profit;
+                    self.twap_tranches_left = tranches;
+                }
+                let tranche = if self.twap_tranches_left == 1 {
+                    self.twap_budget_lamports
+                } else {
+                    self.twap_budget_lamports / self.twap_tranches_left as u64
+                };
+                let spend = tranche.min(profit);
+                self.twap_budget_lamports -= spend;
+                self.twap_tranches_left -= 1;
+                spend
+            }
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TwapMode {
+    #[default]
+    Off,
+    /// Spend each profit snapshot over `tranches` cranks.
+    Split { tranches: u8 },
+    /// Spend at most `lamports` per crank.
+    Fixed { lamports: u64 },
+}
+
+impl TwapMode {
+    pub const SIZE: usize = 1 + 8;
+}
+
+/// Daily UTC window cranks are allowed in. `end_secs` before `start_secs`
+/// wraps past midnight; the weekday is that of the crank's own timestamp.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct CrankWindow {
+    /// Seconds after 00:00 UTC the window opens.
+    pub start_secs: u32,
+    /// Seconds after 00:00 UTC the window closes (exclusive).
+    pub end_secs: u32,
+    /// Allowed weekdays, bit 0 = Sunday through bit 6 = Saturday.
+    pub weekdays: u8,
+}
+
+impl CrankWindow {
+    pub const SIZE: usize = 4 + 4 + 1;
+
+    pub fn contains(&self, now: i64) -> bool {
+        let days = now.div_euclid(86_400);
+        let secs = now.rem_euclid(86_400) as u32;
+        // 1970-01-01 was a Thursday.
+        let weekday = (days + 4).rem_euclid(7) as u8;
+        let in_hours = if self.start_secs <= self.end_secs {
+            secs >= self.start_secs && secs < self.end_secs
+        } else {
+            secs >= self.start_secs || secs < self.end_secs
+        };
+        in_hours && self.weekdays & (1 << weekday) != 0
+    }
+}
+
+/// Durable per-crank history, at `[b"record", state, index]`.
+#[account]
+pub struct BuybackRecord {
+    pub state: Pubkey,
+    pub index: u64,
+    pub profit_lamports: u64,
+    pub tokens_bought: u64,
+    /// Lamports per token base unit, scaled by `PRICE_SCALE`.
+    pub execution_price: u64,
+    pub burn_amount: u64,
+    pub lock_amount: u64,
+    pub liquidity_amount: u64,
+    pub stake_rewards_amount: u64,
+    pub treasury_amount: u64,
+    pub retained_amount: u64,
+    pub keeper_reward_amount: u64,
+    pub keeper: Pubkey,
+    pub timestamp: i64,
+    /// Set by `claim_keeper_tip`.
+    pub tip_claimed: bool,
+}
+
+impl BuybackRecord {
+    pub const SIZE: usize = 32 + 8 * 11 + 32 + 8 + 1;
+}
+
+/// Jupiter route the authority approved for keeperless cranks, at
+/// `[b"route", state]`.
+#[account]
+pub struct ApprovedRoute {
+    pub state: Pubkey,
+    /// Jupiter swap data as quoted; its amounts are rescaled per crank.
+    pub ix_data: Vec<u8>,
+    /// `route_accounts_hash` of the accounts the route must be passed with.
+    pub accounts_hash: [u8; 32],
+    pub expires_ts: i64,
+    pub bump: u8,
+}
+
+impl ApprovedRoute {
+    pub const SIZE: usize = 32 + (4 + MAX_ROUTE_DATA_LEN) + 32 + 8 + 1;
+
+    /// Route data for a crank spending `in_amount`, with the quoted output
+    /// scaled to match so the route's `slippage_bps` still applies.
+    fn replay(&self, route_accounts: &[AccountInfo], in_amount: u64, now: i64) -> Result<Vec<u8>> {
+        require!(now < self.expires_ts, VaultError::RouteExpired);
+        require!(
+            route_accounts_hash(route_accounts) == self.accounts_hash,
+            VaultError::InvalidRoute
+        );
+        let (quoted_in, quoted_out) =
+            jupiter_route_amounts(&self.ix_data).ok_or(VaultError::InvalidRoute)?;
+        let out = u64::try_from(quoted_out as u128 * in_amount as u128 / quoted_in.max(1) as u128)
+            .map_err(|_| error!(VaultError::MathOverflow))?;
+        let mut data = self.ix_data.clone();
+        let tail = data.len() - JUPITER_ROUTE_TAIL_LEN;
+        data[tail..tail + 8].copy_from_slice(&in_amount.to_le_bytes());
+        data[tail + 8..tail + 16].copy_from_slice(&out.to_le_bytes());
+        Ok(data)
+    }
+}
+
+/// Payload of the Wormhole message posted by `attest_buyback`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BuybackAttestation {
+    pub version: u8,
+    pub state: Pubkey,
+    pub mint: Pubkey,
+    /// `BuybackRecord` index.
+    pub index: u64,
+    pub profit_lamports: u64,
+    pub tokens_bought: u64,
+    pub burn_amount: u64,
+    pub lock_amount: u64,
+    pub execution_price: u64,
+    pub timestamp: i64,
+    /// Vault lifetime totals when the message was posted.
+    pub total_burned: u64,
+    pub burned_bps_of_supply: u64,
+}
+
+/// One address's deposits into a vault, at
+/// `[b"depositor", state, depositor]`.
+#[account]
+pub struct DepositorRecord {
+    pub state: Pubkey,
+    pub depositor: Pubkey,
+    pub total_lamports: u64,
+    pub deposit_count: u64,
+    pub first_deposit_ts: i64,
+    pub last_deposit_ts: i64,
+}
+
+impl DepositorRecord {
+    pub const SIZE: usize = 32 + 32 + 8 + 8 + 8 + 8;
+}
+
+/// Deposits credited to one referrer, at `[b"referrer", state, referrer]`.
+#[account]
+pub struct ReferrerRecord {
+    pub state: Pubkey,
+    pub referrer: Pubkey,
+    pub total_lamports: u64,
+    pub referral_count: u64,
+    pub last_referral_ts: i64,
+}
+
+impl ReferrerRecord {
+    pub const SIZE: usize = 32 + 32 + 8 + 8 + 8;
+}
+
+/// Top `LEADERBOARD_LEN` depositors by lamports, at
+/// `[b"leaderboard", state]`, sorted largest first. Deposits that pass a
+/// `DepositorRecord` rank by its lifetime total; otherwise only deposits made
+/// while on the board accumulate.
+#[account]
+#[derive(Default)]
+pub struct DonorLeaderboard {
+    pub state: Pubkey,
+    pub entries: [DonorEntry; LEADERBOARD_LEN],
+}
+
+impl DonorLeaderboard {
+    pub const SIZE: usize = 32 + DonorEntry::SIZE * LEADERBOARD_LEN;
+
+    pub fn add(&mut self, depositor: Pubkey, lamports: u64) {
+        let total = self
+            .entries
+            .iter()
+            .find(|e| e.depositor == depositor)
+            .map_or(lamports, |e| e.lamports.saturating_add(lamports));
+        self.set(depositor, total);
+    }
+
+    /// Ranks `depositor` at `total` lamports.
+    pub fn set(&mut self, depositor: Pubkey, total: u64) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.depositor == depositor) {
+            entry.lamports = total;
+        } else {
+            let last = &mut self.entries[LEADERBOARD_LEN - 1];
+            if total <= last.lamports {
+                return;
+            }
+            *last = DonorEntry {
+                depositor,
+                lamports: total,
+            };
+        }
+        self.entries.sort_by(|a, b| b.lamports.cmp(&a.lamports));
+    }
+}
+
+/// Program-wide count of initialized vaults. Vault `n` is listed on
+/// `RegistryPage` `n / REGISTRY_PAGE_LEN`, so explorers and keepers can page
+/// through every vault without scanning program accounts.
+#[account]
+pub struct VaultRegistry {
+    pub vault_count: u64,
+}
+
+impl VaultRegistry {
+    pub const SIZE: usize = 8;
+}
+
+#[account]
+pub struct RegistryPage {
+    pub page: u64,
+    /// State PDAs in initialization order.
+    pub vaults: Vec<Pubkey>,
+}
+
+impl RegistryPage {
+    pub const SIZE: usize = 8 + 4 + 32 * REGISTRY_PAGE_LEN;
+}
+
+/// Program-wide settings owned by the protocol admin.
+#[account]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,
+    pub settings: ProtocolSettings,
+    /// Set by `set_protocol_paused`; blocks every crank path on every vault.
+    pub paused: bool,
+    /// `seq` for protocol-level events.
+    pub event_seq: u64,
+    pub bump: u8,
+}
+
+impl ProtocolConfig {
+    pub const SIZE: usize = 32 + ProtocolSettings::SIZE + 1 + 8 + 1;
+
+    pub fn next_event_seq(&mut self) -> u64 {
+        let seq = self.event_seq;
+        self.event_seq = seq.wrapping_add(1);
+        seq
+    }
+}
+
+/// Protocol-owned SOL, above rent, for `claim_keeper_tip`.
+#[account]
+pub struct KeeperPool {
+    pub total_paid_lamports: u64,
+    pub bump: u8,
+}
+
+impl KeeperPool {
+    pub const SIZE: usize = 8 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProtocolSettings {
+    /// Receives creation fees and `collect_protocol_fee` payouts.
+    pub treasury: Pubkey,
+    /// One-time fee `create_vault` charges the new vault's authority.
+    pub creation_fee_lamports: u64,
+    /// Ongoing fee on swap input for vaults created from now on.
+    pub protocol_fee_bps: u16,
+    /// Crank interval given to new vaults.
+    pub crank_interval_secs: i64,
+    /// Caps applied to every vault config.
+    pub max_keeper_reward_bps: u16,
+    pub max_liquidity_sol_bps: u16,
+    pub max_sol_burn_bps: u16,
+    /// Share of creation and crank fees diverted to the `KeeperPool`.
+    pub keeper_pool_bps: u16,
+    /// Flat `claim_keeper_tip` payout for cranks that bought with at most
+    /// `keeper_tip_max_profit_lamports`.
+    pub keeper_tip_lamports: u64,
+    pub keeper_tip_max_profit_lamports: u64,
+    /// Route programs any vault may allowlist. Empty allows any.
+    pub allowed_amms: Vec<Pubkey>,
+}
+
+impl ProtocolSettings {
+    pub const SIZE: usize = 32 + 8 + 2 + 8 + 2 + 2 + 2 + 2 + 8 + 8 + 4 + 32 * MAX_AMM_PROGRAMS;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.protocol_fee_bps <= MAX_PROTOCOL_FEE_BPS
+                && self.crank_interval_secs >= MIN_CRANK_INTERVAL_SECS
+                && self.max_keeper_reward_bps <= MAX_KEEPER_REWARD_BPS
+                && self.max_liquidity_sol_bps < 10_000
+                && self.max_sol_burn_bps < 10_000
+                && self.keeper_pool_bps <= 10_000
+                && self.allowed_amms.len() <= MAX_AMM_PROGRAMS,
+            VaultError::InvalidConfig
+        );
+        Ok(())
+    }
+
+    /// Lowers `config`'s bps settings to the protocol caps and narrows its
+    /// AMM allowlist to `allowed_amms`, defaulting an empty one to the full
+    /// protocol list.
+    pub fn clamp(&self, config: &mut VaultConfig) -> Result<()> {
+        config.keeper_reward_max_bps = config.keeper_reward_max_bps.min(self.max_keeper_reward_bps);
+        config.keeper_reward_bps = config.keeper_reward_bps.min(config.keeper_reward_max_bps);
+        config.liquidity_sol_bps = config.liquidity_sol_bps.min(self.max_liquidity_sol_bps);
+        config.sol_burn_bps = config.sol_burn_bps.min(self.max_sol_burn_bps);
+        if !self.allowed_amms.is_empty() {
+            if config.amm_allowlist.is_empty() {
+                config.amm_allowlist = self.allowed_amms.clone();
+            } else {
+                config
+                    .amm_allowlist
+                    .retain(|amm| self.allowed_amms.contains(amm));
+                require!(!config.amm_allowlist.is_empty(), VaultError::AmmNotAllowed);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Receives one income stream for several vaults; SOL sent to this PDA is
+/// forwarded by `split_revenue`.
+#[account]
+pub struct RevenueSplitter {
+    pub authority: Pubkey,
+    pub legs: Vec<SplitLeg>,
+    pub bump: u8,
+}
+
+impl RevenueSplitter {
+    pub const SIZE: usize = 32 + (4 + MAX_SPLIT_LEGS * SplitLeg::SIZE) + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct SplitLeg {
+    /// Vault state PDA receiving this leg.
+    pub state: Pubkey,
+    pub bps: u16,
+}
+
+impl SplitLeg {
+    pub const SIZE: usize = 32 + 2;
+}
+
+/// Tokens committed to a merkle root of holder allocations; claimed one
+/// holder at a time through `claim_distribution`.
+#[account]
+pub struct MerkleDistribution {
+    pub state: Pubkey,
+    pub index: u64,
+    pub mint: Pubkey,
+    pub root: [u8; 32],
+    pub total_amount: u64,
+    pub claimed_amount: u64,
+    pub epoch: u64,
+    /// Tranche airdropped by `airdrop_tranche`; `None` for holder rewards.
+    pub lock_tranche: Option<Pubkey>,
+    pub bump: u8,
+}
+
+impl MerkleDistribution {
+    pub const SIZE: usize = 32 + 8 + 32 + 32 + 8 + 8 + 8 + (1 + 32) + 1;
+}
+
+/// Marks one claimant's allocation in a `MerkleDistribution` as paid.
+#[account]
+pub struct ClaimStatus {
+    pub distribution: Pubkey,
+    pub claimant: Pubkey,
+    pub amount: u64,
+    pub claimed_ts: i64,
+}
+
+impl ClaimStatus {
+    pub const SIZE: usize = 32 + 32 + 8 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct DonorEntry {
+    pub depositor: Pubkey,
+    pub lamports: u64,
+}
+
+impl DonorEntry {
+    pub const SIZE: usize = 32 + 8;
+}
+
+/// The last `HISTORY_LEN` buybacks, at `[b"history", state]`. Entry
+/// `head % HISTORY_LEN` is overwritten next; `head` counts every write.
+/// `epochs` keeps per-epoch totals for the last `EPOCH_HISTORY_LEN`
+/// epochs (roughly a month), for weekly or monthly buyback reports.
+#[account(zero_copy)]
+pub struct BuybackHistory {
+    pub state: Pubkey,
+    pub head: u64,
+    pub entries: [HistoryEntry; HISTORY_LEN],
+    pub epochs: [EpochTotals; EPOCH_HISTORY_LEN],
+}
+
+impl BuybackHistory {
+    pub const SIZE: usize =
+        32 + 8 + HistoryEntry::SIZE * HISTORY_LEN + EpochTotals::SIZE * EPOCH_HISTORY_LEN;
+
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.entries[(self.head % HISTORY_LEN as u64) as usize] = entry;
+        self.head += 1;
+    }
+
+    /// Adds a buyback to its epoch's bucket, starting the bucket over when
+    /// it last held an older epoch.
+    pub fn add_to_epoch(&mut self, epoch: u64, entry: &HistoryEntry) {
+        let totals = &mut self.epochs[(epoch % EPOCH_HISTORY_LEN as u64) as usize];
+        if totals.epoch != epoch || totals.cranks == 0 {
+            *totals = EpochTotals {
+                epoch,
+                ..Default::default()
+            };
+        }
+        totals.cranks += 1;
+        totals.profit_lamports = totals.profit_lamports.saturating_add(entry.profit_lamports);
+        totals.tokens_bought = totals.tokens_bought.saturating_add(entry.tokens_bought);
+        totals.burn_amount = totals.burn_amount.saturating_add(entry.burn_amount);
+        totals.lock_amount = totals.lock_amount.saturating_add(entry.lock_amount);
+    }
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct EpochTotals {
+    pub epoch: u64,
+    pub cranks: u64,
+    pub profit_lamports: u64,
+    pub tokens_bought: u64,
+    pub burn_amount: u64,
+    pub lock_amount: u64,
+}
+
+impl EpochTotals {
+    pub const SIZE: usize = 8 * 6;
+}
+
+#[zero_copy]
+pub struct HistoryEntry {
+    pub timestamp: i64,
+    pub profit_lamports: u64,
+    pub tokens_bought: u64,
+    /// Lamports per token base unit, scaled by `PRICE_SCALE`.
+    pub execution_price: u64,
+    pub burn_amount: u64,
+    pub lock_amount: u64,
+}
+
+impl HistoryEntry {
+    pub const SIZE: usize = 8 * 6;
+}
+
+/// Tokens locked by one crank leg, at `[b"lock", state, index]`. Each
+/// tranche matures on its own instead of sharing a global unlock time.
+#[account]
+#[derive(Default)]
+pub struct LockTranche {
+    pub state: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub amount: u64,
+    pub unlock_ts: i64,
+    /// Paid the tranche's rent; refunded when it is fully unlocked.
+    pub payer: Pubkey,
+    pub start_ts: i64,
+    /// Fixed at lock time so later config changes don't alter the schedule.
+    pub release: LockRelease,
+    pub claimed: u64,
+    /// Snapshot of `config.unlock_mode`.
+    pub unlock_mode: UnlockMode,
+    /// Per-bucket split of `amount` from `config.lock_buckets`; zero buckets
+    /// means the whole amount follows `unlock_ts`.
+    pub bucket_count: u8,
+    pub bucket_amounts: [u64; MAX_LOCK_BUCKETS],
+    pub bucket_unlock_ts: [i64; MAX_LOCK_BUCKETS],
+}
+
+impl LockTranche {
+    pub const SIZE: usize = 32
+        + 32
+        + 8
+        + 8
+        + 8
+        + 32
+        + 8
+        + LockRelease::SIZE
+        + 8
+        + UnlockMode::SIZE
+        + 1
+        + 8 * MAX_LOCK_BUCKETS
+        + 8 * MAX_LOCK_BUCKETS;
+
+    pub fn new(
+        state: Pubkey,
+        index: u64,
+        mint: Pubkey,
+        amount: u64,
+        payer: Pubkey,
+        now: i64,
+        config: &VaultConfig,
+    ) -> Result<Self> {
+        let release = config.lock_release;
+        let mut tranche = Self {
+            state,
+            mint,
+            index,
+            amount,
+            unlock_ts: now
+                .checked_add(release.duration_secs())
+                .ok_or(VaultError::MathOverflow)?,
+            payer,
+            start_ts: now,
+            release,
+            claimed: 0,
+            unlock_mode: config.unlock_mode,
+            ..Default::default()
+        };
+        if !config.lock_buckets.is_empty() {
+            let mut remaining = amount;
+            let last = config.lock_buckets.len() - 1;
+            for (i, bucket) in config.lock_buckets.iter().enumerate() {
+                let share = if i == last {
+                    remaining
+                } else {
+                    bps_of(amount, bucket.bps as u64)?
+                };
+                remaining -= share;
+                tranche.bucket_amounts[i] = share;
+                tranche.bucket_unlock_ts[i] = now
+                    .checked_add(bucket.duration_secs as i64)
+                    .ok_or(VaultError::MathOverflow)?;
+            }
+            tranche.bucket_count = config.lock_buckets.len() as u8;
+            tranche.unlock_ts = tranche.bucket_unlock_ts[..=last]
+                .iter()
+                .copied()
+                .max()
+                .unwrap_or(tranche.unlock_ts);
+        }
+        Ok(tranche)
+    }
+
+    /// Amount released by `now`, summed over the buckets when there are any.
+    pub fn vested(&self, now: i64) -> u64 {
+        if self.bucket_count == 0 {
+            return self.vested_part(self.amount, self.unlock_ts, now);
+        }
+        (0..self.bucket_count as usize)
+            .map(|i| self.vested_part(self.bucket_amounts[i], self.bucket_unlock_ts[i], now))
+            .sum()
+    }
+
+    /// Released share of `amount` maturing at `unlock_ts`: all of it then for
+    /// a cliff, linearly from `start_ts` when vesting, or linearly from the
+    /// end of the cliff when streaming.
+    fn vested_part(&self, amount: u64, unlock_ts: i64, now: i64) -> u64 {
+        if now >= unlock_ts {
+            return amount;
+        }
+        match self.release {
+            LockRelease::Cliff => 0,
+            LockRelease::Linear => {
+                let elapsed = now.saturating_sub(self.start_ts).max(0) as u128;
+                let duration = unlock_ts.saturating_sub(self.start_ts).max(1) as u128;
+                (amount as u128 * elapsed / duration) as u64
+            }
+            LockRelease::CliffStream {
+                cliff_secs,
+                stream_secs,
+            } => {
+                let streamed = now
+                    .saturating_sub(self.start_ts)
+                    .saturating_sub(cliff_secs as i64)
+                    .max(0) as u128;
+                (amount as u128 * streamed / (stream_secs.max(1) as u128)) as u64
+            }
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LockRelease {
+    /// Everything unlocks at `unlock_ts`.
+    #[default]
+    Cliff,
+    /// Tokens vest linearly over the lock duration.
+    Linear,
+    /// Nothing for `cliff_secs`, then per-second streaming over
+    /// `stream_secs`. Replaces the default lock duration.
+    CliffStream { cliff_secs: u32, stream_secs: u32 },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct LockBucket {
+    pub bps: u16,
+    pub duration_secs: u32,
+}
+
+impl LockBucket {
+    pub const SIZE: usize = 2 + 4;
+}
+
+/// Buyback totals for the current and previous fixed window of one period.
+/// Like the unlock rate limit, the trailing total counts the previous window
+/// in proportion to how much of it still overlaps the period.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RollingTotals {
+    pub window_start: i64,
+    pub profit_lamports: u64,
+    pub tokens_bought: u64,
+    pub prev_profit_lamports: u64,
+    pub prev_tokens_bought: u64,
+}
+
+impl RollingTotals {
+    pub const SIZE: usize = 8 + 8 + 8 + 8 + 8;
+
+    fn roll(&mut self, period: i64, now: i64) {
+        let elapsed = now.saturating_sub(self.window_start);
+        if elapsed >= 2 * period {
+            *self = RollingTotals {
+                window_start: now,
+                ..Default::default()
+            };
+        } else if elapsed >= period {
+            self.window_start += period;
+            self.prev_profit_lamports = self.profit_lamports;
+            self.prev_tokens_bought = self.tokens_bought;
+            self.profit_lamports = 0;
+            self.tokens_bought = 0;
+        }
+    }
+
+    pub fn add(&mut self, period: i64, now: i64, profit_lamports: u64, tokens_bought: u64) {
+        self.roll(period, now);
+        self.profit_lamports = self.profit_lamports.saturating_add(profit_lamports);
+        self.tokens_bought = self.tokens_bought.saturating_add(tokens_bought);
+    }
+
+    /// Profit and tokens over the `period` ending at `now`.
+    pub fn trailing(&self, period: i64, now: i64) -> (u64, u64) {
+        let mut totals = *self;
+        totals.roll(period, now);
+        let period = period.max(1) as u128;
+        let remaining =
+            period.saturating_sub(now.saturating_sub(totals.window_start).max(0) as u128);
+        let overlap = |prev: u64| (prev as u128 * remaining / period) as u64;
+        (
+            totals
+                .profit_lamports
+                .saturating_add(overlap(totals.prev_profit_lamports)),
+            totals
+                .tokens_bought
+                .saturating_add(overlap(totals.prev_tokens_bought)),
+        )
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct UnlockRateLimit {
+    pub max_amount: u64,
+    pub period_secs: u32,
+}
+
+impl UnlockRateLimit {
+    pub const SIZE: usize = 8 + 4;
+}
+
+/// Oracle checks on each crank's swap. USD figures are scaled by `USD_SCALE`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct OracleGuard {
+    /// USD price of what the vault spends: SOL, or the SPL profit mint.
+    pub quote_oracle: Pubkey,
+    /// Oldest `publish_time` accepted from either feed.
+    pub max_staleness_secs: u32,
+    /// Widest confidence interval accepted, as bps of the price.
+    pub max_conf_bps: u16,
+    /// How far below the USD value spent the tokens bought may come in.
+    pub max_slippage_bps: u16,
+    /// Token price, in `price_oracle`'s own units, above which cranks fail.
+    pub price_ceiling: Option<i64>,
+    /// Smallest profit worth buying with.
+    pub min_profit_usd: u64,
+}
+
+impl OracleGuard {
+    pub const SIZE: usize = 32 + 4 + 2 + 2 + (1 + 8) + 8;
+
+    fn check_price(&self, price: &OraclePrice, now: i64) -> Result<()> {
+        require!(price.price > 0, VaultError::InvalidOracle);
+        require!(
+            now.saturating_sub(price.publish_time) <= self.max_staleness_secs as i64,
+            VaultError::OracleStale
+        );
+        require!(
+            price.conf as u128 * 10_000 <= price.price as u128 * self.max_conf_bps as u128,
+            VaultError::OracleConfidence
+        );
+        Ok(())
+    }
+}
+
+/// `crank_openbook` settings: bids rest on an OpenBook v2 market of the
+/// token against WSOL at a discount to the oracle price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct OpenBookBids {
+    pub market: Pubkey,
+    /// How far under the oracle's SOL price bids rest.
+    pub discount_bps: u16,
+    /// SOL/USD feed that converts `price_oracle` to a SOL price.
+    pub sol_oracle: Pubkey,
+    /// Oldest `publish_time` accepted from either feed.
+    pub max_staleness_secs: u32,
+}
+
+impl OpenBookBids {
+    pub const SIZE: usize = 32 + 2 + 32 + 4;
+}
+
+/// `open_dca` settings: each crank's profit buys through a Jupiter DCA order
+/// of `cycles` equal buys `cycle_secs` apart.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct DcaSettings {
+    pub cycles: u16,
+    pub cycle_secs: i64,
+}
+
+impl DcaSettings {
+    pub const SIZE: usize = 2 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnlockMode {
+    /// Released to the authority's chosen token account.
+    #[default]
+    Release,
+    /// Burned, making the lock leg a delayed burn.
+    Burn,
+    /// Sent only to a staking program's rewards escrow token account, so the
+    /// lock leg becomes holder rewards.
+    StakingRewards { escrow: Pubkey },
+    /// Handed to a Streamflow stream for `recipient` by `stream_lock`, so
+    /// the lock shows up in standard vesting UIs.
+    Streamflow { recipient: Pubkey },
+    /// Never released: every unlock path rejects the tranche, including the
+    /// council, and `update_config` can't switch away from it.
+    Permanent,
+    /// Released only by `airdrop_tranche` into a merkle distribution of
+    /// holder balances, so matured locks go back to the community one
+    /// `claim_distribution` at a time. Like `Permanent`, the council can't
+    /// release it and `update_config` can't switch away from it.
+    Airdrop,
+}
+
+impl UnlockMode {
+    pub const SIZE: usize = 1 + 32;
+}
+
+impl LockRelease {
+    pub const SIZE: usize = 1 + 4 + 4;
+
+    /// Seconds from lock until the tranche is fully released.
+    pub fn duration_secs(&self) -> i64 {
+        match *self {
+            LockRelease::Cliff | LockRelease::Linear => TIMELOCK_SECS,
+            LockRelease::CliffStream {
+                cliff_secs,
+                stream_secs,
+            } => cliff_secs as i64 + stream_secs as i64,
+        }
+    }
+}
+
+/// Council-approved emergency release of one tranche, at
+/// `[b"early_unlock", lock_tranche]`.
+#[account]
+pub struct EarlyUnlock {
+    pub state: Pubkey,
+    pub lock_tranche: Pubkey,
+    /// Token account the council approved; `None` for burn-at-unlock.
+    pub destination: Option<Pubkey>,
+    /// Bitmask of approving `config.council` members.
+    pub approvals: u8,
+    pub ready_ts: i64,
+}
+
+impl EarlyUnlock {
+    pub const SIZE: usize = 32 + 32 + (1 + 32) + 1 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct VaultConfig {
+    pub clock_tolerance_secs: i64,
+    /// Upper bound on the vault PDA balance enforced by `deposit`.
+    pub max_vault_lamports: Option<u64>,
+    pub twap: TwapMode,
+    /// Raydium CPMM pool `crank_raydium` is allowed to swap against.
+    pub raydium_pool: Option<Pubkey>,
+    /// Orca Whirlpool `crank_whirlpool` is allowed to swap against.
+    pub whirlpool: Option<Pubkey>,
+    /// pump.fun AMM pool `crank_pump_amm` is allowed to swap against.
+    pub pump_pool: Option<Pubkey>,
+    /// Post-swap split; weights must sum to 10_000 bps.
+    pub distribution: Vec<StrategyWeight>,
+    /// Receives the `StakeRewards` leg, e.g. a `burnflip_staking` pool's
+    /// reward ATA.
+    pub stake_rewards_token_account: Option<Pubkey>,
+    pub treasury_token_account: Option<Pubkey>,
+    /// Share of each crank's spend kept as SOL to pair with the
+    /// `AddLiquidity` token reserve.
+    pub liquidity_sol_bps: u16,
+    /// Share of each crank's spend sent to the burn address as raw SOL
+    /// instead of being swapped; paid out by `burn_sol`.
+    pub sol_burn_bps: u16,
+    /// Share of bought tokens paid to the crank payer before the split.
+    pub keeper_reward_bps: u16,
+    /// Added to `keeper_reward_bps` per hour the vault is overdue.
+    pub keeper_reward_escalation_bps_per_hour: u16,
+    pub keeper_reward_max_bps: u16,
+    /// Target tokens bought per aggregator crank. Routes are then expected to
+    /// be exact-out, with the crank spend as the input bound.
+    pub exact_out_amount: Option<u64>,
+    /// Mints bought by `crank_multi` with their share of the profit. Empty
+    /// means the vault only buys `state.mint` through the single-mint cranks.
+    pub buyback_mints: Vec<MintWeight>,
+    /// Vault-owned Raydium CLMM personal position harvested for fees.
+    pub raydium_position: Option<Pubkey>,
+    /// Vault-owned Meteora DLMM position harvested for fees.
+    pub meteora_position: Option<Pubkey>,
+    /// SPL stake pool idle SOL is staked into.
+    pub stake_pool: Option<Pubkey>,
+    /// Lamports `stake_idle` and `deposit_idle` must leave liquid in the vault.
+    pub working_balance_lamports: u64,
+    pub yield_backend: Option<YieldBackend>,
+    /// Vault-owned account with the yield backend, set by `init_yield_account`.
+    pub yield_account: Option<Pubkey>,
+    /// Mint `crank_two_hop` routes through (e.g. USDC).
+    pub intermediate_mint: Option<Pubkey>,
+    /// Public schedule cranks must fall in; `None` allows any time.
+    pub crank_window: Option<CrankWindow>,
+    /// AMM programs aggregator routes may touch; empty disables the check.
+    pub amm_allowlist: Vec<Pubkey>,
+    /// How new lock tranches release.
+    pub lock_release: LockRelease,
+    /// Where matured locked tokens go. Snapshotted on each tranche, so it
+    /// applies to tranches opened while set.
+    pub unlock_mode: UnlockMode,
+    /// Splits each lock leg across buckets with their own durations; weights
+    /// sum to 10_000 bps. Fixed at `create_vault`.
+    pub lock_buckets: Vec<LockBucket>,
+    /// Emergency council that can approve `request_early_unlock`; empty
+    /// disables early release. `propose_config` installs it; once set, only
+    /// `rotate_council` changes it.
+    pub council: Vec<Pubkey>,
+    /// Council approvals required, at least two thirds of the council.
+    pub council_threshold: u8,
+    /// Lets `crank` release a matured tranche per its unlock mode.
+    pub auto_unlock: bool,
+    /// Caps non-burn unlocks (including auto-unlock) per period, so a
+    /// compromised authority can't release the whole stash at once.
+    pub unlock_rate_limit: Option<UnlockRateLimit>,
+    /// USD price feed of the token, recorded alongside each buyback and
+    /// checked by `oracle_guard`. A Pyth `PriceUpdateV2`, a Switchboard
+    /// on-demand pull feed or a Chainlink OCR2 feed.
+    pub price_oracle: Option<Pubkey>,
+    /// Community wallet that receives `mint_buyback_proof` assets; `None`
+    /// disables them.
+    pub proof_recipient: Option<Pubkey>,
+    /// SPL token the vault earns instead of SOL (e.g. USDC). Deposits go
+    /// through `deposit_token`, `crank_spl_profit` is the only crank, and
+    /// `starting_balance_lamports`, the TWAP amounts and every
+    /// `profit_lamports` figure count this mint's base units. Fixed at
+    /// `create_vault`.
+    pub base_mint: Option<Pubkey>,
+    /// Fails cranks on a stale or uncertain `price_oracle`, or when the swap
+    /// strays from it.
+    pub oracle_guard: Option<OracleGuard>,
+    /// Metaplex collection whose holders alone may crank, so operator
+    /// passes can be handed out as NFTs; `None` leaves cranking open.
+    pub operator_collection: Option<Pubkey>,
+    /// State-owned Orca Whirlpool position, set by `open_whirlpool_position`
+    /// and harvested for fees.
+    pub whirlpool_position: Option<Pubkey>,
+    /// Meteora stake-for-fee vault the `StakeForFees` strategy stakes into.
+    pub m3m3_vault: Option<Pubkey>,
+    /// Aggregator the routed cranks swap through; switching it moves a vault
+    /// off one whose program or quote API is down.
+    pub aggregator: Aggregator,
+    /// Lets `crank` store its `BuybackRecord` as a Light Protocol compressed
+    /// account instead of a rent-paying PDA. Compressed records can't back
+    /// `claim_keeper_tip`, `mint_buyback_proof` or `attest_buyback`, which
+    /// read the PDA.
+    pub compressed_records: bool,
+    /// Ascending cumulative deposit totals, in lamports, that earn a
+    /// depositor a soulbound badge in `deposit`. Totals come from the
+    /// `DepositorRecord`, so deposits without one earn none.
+    pub donor_badges: Vec<u64>,
+    /// Resting-bid buyback mode for `crank_openbook`; the other cranks keep
+    /// market-swapping.
+    pub openbook_bids: Option<OpenBookBids>,
+    /// Lets `open_dca` buy through Jupiter DCA orders instead of one swap.
+    pub dca: Option<DcaSettings>,
+}
+
+impl VaultConfig {
+    /// Whether the fields only `apply_config` may change match: the
+    /// distribution, its payout accounts, the unlock settings and the
+    /// emergency council.
+    pub fn timelocked_eq(&self, other: &VaultConfig) -> bool {
+        self.distribution == other.distribution
+            && self.treasury_token_account == other.treasury_token_account
+            && self.stake_rewards_token_account == other.stake_rewards_token_account
+            && self.unlock_mode == other.unlock_mode
+            && self.unlock_rate_limit == other.unlock_rate_limit
+            && self.council == other.council
+            && self.council_threshold == other.council_threshold
+    }
+
+    pub const SIZE: usize = 8
+        + (1 + 8)
+        + TwapMode::SIZE
+        + (1 + 32)
+        + (1 + 32)
+        + (1 + 32)
+        + (4 + MAX_STRATEGIES * StrategyWeight::SIZE)
+        + (1 + 32)
+        + (1 + 32)
+        + 2
+        + 2
+        + 2
+        + 2
+        + (1 + 8)
+        + (4 + MAX_BUYBACK_MINTS * MintWeight::SIZE)
+        + (1 + 32)
+        + (1 + 32)
+        + (1 + 32)
+        + 8
+        + (1 + 1)
+        + (1 + 32)
+        + (1 + 32)
+        + 2
+        + (1 + CrankWindow::SIZE)
+        + (4 + MAX_AMM_PROGRAMS * 32)
+        + LockRelease::SIZE
+        + UnlockMode::SIZE
+        + (4 + MAX_LOCK_BUCKETS * LockBucket::SIZE)
+        + (4 + MAX_COUNCIL * 32)
+        + 1
+        + 1
+        + (1 + UnlockRateLimit::SIZE)
+        + (1 + 32)
+        + (1 + 32)
+        + (1 + 32)
+        + (1 + OracleGuard::SIZE)
+        + (1 + 32)
+        + (1 + 32)
+        + (1 + 32)
+        + 1
+        + 1
+        + (4 + MAX_DONOR_BADGES * 8)
+        + (1 + OpenBookBids::SIZE)
+        + (1 + DcaSettings::SIZE);
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            (0..=MAX_CLOCK_TOLERANCE_SECS).contains(&self.clock_tolerance_secs),
+            VaultError::InvalidConfig
+        );
+        match self.twap {
+            TwapMode::Off => {}
+            TwapMode::Split { tranches } => require!(tranches > 0, VaultError::InvalidConfig),
+            TwapMode::Fixed { lamports } => require!(lamports > 0, VaultError::InvalidConfig),
+        }
+
+        require!(
+            !self.distribution.is_empty() && self.distribution.len() <= MAX_STRATEGIES,
+            VaultError::InvalidConfig
+        );
+        let mut total_bps: u64 = 0;
+        for (i, weight) in self.distribution.iter().enumerate() {
+            require!(
+                self.distribution[..i]
+                    .iter()
+                    .all(|w| w.strategy != weight.strategy),
+                VaultError::InvalidConfig
+            );
+            total_bps += weight.bps as u64;
+        }
+        require!(total_bps == 10_000, VaultError::InvalidConfig);
+        if self.weight_of(DistributionStrategy::StakeRewards) > 0 {
+            require!(
+                self.stake_rewards_token_account.is_some(),
+                VaultError::InvalidConfig
+            );
+        }
+        if self.weight_of(DistributionStrategy::StakeForFees) > 0 {
+            require!(self.m3m3_vault.is_some(), VaultError::InvalidConfig);
+        }
+        require!(
+            (self.liquidity_sol_bps as u64) + (self.sol_burn_bps as u64) < 10_000,
+            VaultError::InvalidConfig
+        );
+        require!(
+            self.keeper_reward_bps <= self.keeper_reward_max_bps
+                && self.keeper_reward_max_bps <= MAX_KEEPER_REWARD_BPS,
+            VaultError::InvalidConfig
+        );
+        if self.liquidity_sol_bps > 0 {
+            require!(
+                self.weight_of(DistributionStrategy::AddLiquidity) > 0
+                    && self.raydium_pool.is_some(),
+                VaultError::InvalidConfig
+            );
+        }
+        if !self.buyback_mints.is_empty() {
+            require!(
+                self.buyback_mints.len() <= MAX_BUYBACK_MINTS,
+                VaultError::InvalidConfig
+            );
+            let mut total_bps: u64 = 0;
+            for (i, leg) in self.buyback_mints.iter().enumerate() {
+                require!(
+                    self.buyback_mints[..i].iter().all(|l| l.mint != leg.mint),
+                    VaultError::InvalidConfig
+                );
+                total_bps += leg.bps as u64;
+            }
+            require!(total_bps == 10_000, VaultError::InvalidConfig);
+            // Multi-mint legs only split between burn and lock.
+            require!(
+                self.distribution.iter().all(|w| matches!(
+                    w.strategy,
+                    DistributionStrategy::Burn | DistributionStrategy::Lock
+                )),
+                VaultError::InvalidConfig
+            );
+            require!(
+                self.liquidity_sol_bps == 0 && self.exact_out_amount.is_none(),
+                VaultError::InvalidConfig
+            );
+        }
+        if self.weight_of(DistributionStrategy::Treasury) > 0 {
+            require!(
+                self.treasury_token_account.is_some(),
+                VaultError::InvalidConfig
+            );
+        }
+        require!(
+            self.intermediate_mint != Some(token::spl_token::native_mint::ID),
+            VaultError::InvalidConfig
+        );
+        require!(
+            self.amm_allowlist.len() <= MAX_AMM_PROGRAMS,
+            VaultError::InvalidConfig
+        );
+        if let LockRelease::CliffStream { stream_secs, .. } = self.lock_release {
+            require!(stream_secs > 0, VaultError::InvalidConfig);
+        }
+        if let Some(limit) = self.unlock_rate_limit {
+            require!(
+                limit.max_amount > 0 && limit.period_secs > 0,
+                VaultError::InvalidConfig
+            );
+        }
+        if !self.council.is_empty() {
+            validate_council(&self.council, self.council_threshold)?;
+        }
+        if !self.lock_buckets.is_empty() {
+            // Buckets carry their own durations, which a cliff-stream
+            // schedule would override and a single stream can't follow.
+            require!(
+                self.lock_buckets.len() <= MAX_LOCK_BUCKETS
+                    && !matches!(self.lock_release, LockRelease::CliffStream { .. })
+                    && !matches!(self.unlock_mode, UnlockMode::Streamflow { .. }),
+                VaultError::InvalidConfig
+            );
+            require!(
+                self.lock_buckets.iter().all(|b| b.duration_secs > 0)
+                    && self.lock_buckets.iter().map(|b| b.bps as u64).sum::<u64>() == 10_000,
+                VaultError::InvalidConfig
+            );
+        }
+        if let Some(base_mint) = self.base_mint {
+            // The SOL-only legs have nothing to draw on.
+            require!(
+                base_mint != token::spl_token::native_mint::ID
+                    && self.liquidity_sol_bps == 0
+                    && self.sol_burn_bps == 0
+                    && self.buyback_mints.is_empty()
+                    && self.yield_backend.is_none()
+                    && self.stake_pool.is_none(),
+                VaultError::InvalidConfig
+            );
+        }
+        require!(
+            self.donor_badges.len() <= MAX_DONOR_BADGES
+                && self.donor_badges.first() != Some(&0)
+                && self.donor_badges.windows(2).all(|w| w[0] < w[1]),
+            VaultError::InvalidConfig
+        );
+        if let Some(bids) = self.openbook_bids {
+            // Fills settle cranks after their bid was priced, which the
+            // per-swap oracle guard can't judge.
+            require!(
+                bids.discount_bps < 10_000
+                    && bids.max_staleness_secs > 0
+                    && self.price_oracle.is_some()
+                    && self.oracle_guard.is_none()
+                    && self.base_mint.is_none()
+                    && self.buyback_mints.is_empty(),
+                VaultError::InvalidConfig
+            );
+        }
+        if let Some(dca) = self.dca {
+            // The guard would judge days of fills against one price.
+            require!(
+                dca.cycles > 0
+                    && dca.cycle_secs > 0
+                    && self.oracle_guard.is_none()
+                    && self.base_mint.is_none()
+                    && self.buyback_mints.is_empty(),
+                VaultError::InvalidConfig
+            );
+        }
+        if let Some(guard) = self.oracle_guard {
+            require!(
+                self.price_oracle.is_some()
+                    && guard.max_staleness_secs > 0
+                    && guard.max_conf_bps <= 10_000
+                    && guard.max_slippage_bps <= 10_000
+                    && guard.price_ceiling.unwrap_or(1) > 0,
+                VaultError::InvalidConfig
+            );
+        }
+        if let Some(window) = self.crank_window {
+            require!(
+                window.start_secs < 86_400
+                    && window.end_secs < 86_400
+                    && window.start_secs != window.end_secs
+                    && window.weekdays & 0x7f != 0,
+                VaultError::InvalidConfig
+            );
+        }
+        Ok(())
+    }
+
+    pub fn weight_of(&self, strategy: DistributionStrategy) -> u16 {
+        self.distribution
+            .iter()
+            .find(|w| w.strategy == strategy)
+            .map_or(0, |w| w.bps)
+    }
 }
 
-#[derive(Accounts)]
-pub struct Crank<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(mut)]
-    pub state: Account<'info, VaultState>,
-    #[account(
-        mut,
-        seeds = [b"vault", state.key().as_ref()],
-        bump = state.vault_bump
-    )]
-    /// CHECK: Vault PDA is owned by the system program and holds SOL only.
-    pub vault: UncheckedAccount<'info>,
-    pub mint: Account<'info, Mint>,
-    #[account(
-        init_if_needed,
-        payer = payer,
-        associated_token::mint = wsol_mint,
-        associated_token::authority = vault
-    )]
-    pub vault_wsol_ata: Account<'info, TokenAccount>,
-    pub wsol_mint: Account<'info, Mint>,
-    #[account(
-        mut,
-        associated_token::mint = mint,
-        associated_token::authority = state
-    )]
-    pub vault_token_account: Account<'info, TokenAccount>,
-    #[account(
-        mut,
-        associated_token::mint = mint,
-        associated_token::authority = burn_authority
-    )]
-    pub burn_token_account: Account<'info, TokenAccount>,
-    #[account(
-        mut,
-        associated_token::mint = mint,
-        associated_token::authority = timelock_authority
-    )]
-    pub timelock_token_account: Account<'info, TokenAccount>,
-    /// CHECK: Burn address is a known public key (e.g., Incinerator)
-    pub burn_authority: UncheckedAccount<'info>,
-    /// CHECK: Timelock PDA that can later unlock
-    #[account(
-        seeds = [b"timelock", state.key().as_ref()],
-        bump = state.timelock_bump
-    )]
-    pub timelock_authority: UncheckedAccount<'info>,
-    /// CHECK: Jupiter program is invoked via CPI.
-    pub jupiter_program: UncheckedAccount<'info>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum DistributionStrategy {
+    /// Sent to the burn address ATA.
+    Burn,
+    /// Sent to the timelock ATA.
+    Lock,
+    /// Kept in the vault token account as the liquidity reserve.
+    AddLiquidity,
+    /// Sent to `config.stake_rewards_token_account`.
+    StakeRewards,
+    /// Sent to `config.treasury_token_account`.
+    Treasury,
+    /// Kept in the vault token account for the authority to release later
+    /// via `release_retained`.
+    Retain,
+    /// Kept in the vault token account until `publish_distribution` commits
+    /// it to a merkle root of holder allocations.
+    HolderRewards,
+    /// Kept in the vault token account until `stake_for_fees` stakes it into
+    /// `config.m3m3_vault`.
+    StakeForFees,
 }
 
-#[derive(Accounts)]
-pub struct Unlock<'info> {
-    #[account(mut)]
-    pub state: Account<'info, VaultState>,
-    pub mint: Account<'info, Mint>,
-    #[account(mut)]
-    pub timelock_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub destination_token_account: Account<'info, TokenAccount>,
-    /// CHECK: Timelock PDA signer
-    #[account(
-        seeds = [b"timelock", state.key().as_ref()],
-        bump = state.timelock_bump
-    )]
-    pub timelock_authority: UncheckedAccount<'info>,
-    pub token_program: Program<'info, Token>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct StrategyWeight {
+    pub strategy: DistributionStrategy,
+    pub bps: u16,
 }
 
-#[account]
-pub struct VaultState {
+impl StrategyWeight {
+    pub const SIZE: usize = 1 + 2;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum YieldBackend {
+    Marginfi,
+}
+
+/// Swap program the aggregator cranks route through.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregator {
+    Jupiter,
+    DFlow,
+}
+
+impl Aggregator {
+    pub fn program_id(&self) -> Pubkey {
+        match self {
+            Aggregator::Jupiter => JUPITER_PROGRAM_ID,
+            Aggregator::DFlow => DFLOW_PROGRAM_ID,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct MintWeight {
+    pub mint: Pubkey,
+    pub bps: u16,
+}
+
+impl MintWeight {
+    pub const SIZE: usize = 32 + 2;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MultiMintRoute {
+    pub data: Vec<u8>,
+    pub account_count: u8,
+    /// Transfer hook accounts for the leg's mint after the route; only
+    /// `crank_multi` reads them.
+    pub hook_account_count: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CrankStatus {
+    Ready,
+    ProtocolPaused,
+    Paused,
+    SwapPending,
+    /// `crank_multi` is the only crank for vaults with `buyback_mints`.
+    MultiMintVault,
+    /// `crank_spl_profit` is the only crank for vaults with `base_mint`.
+    BaseMintVault,
+    OutsideCrankWindow,
+    ClockSkew,
+    TooSoon,
+    NoProfit,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CrankPreview {
+    pub profit_lamports: u64,
+    pub swap_lamports: u64,
+    pub liquidity_lamports: u64,
+    pub sol_burn_lamports: u64,
+    pub protocol_fee_lamports: u64,
+    pub keeper_reward_lamports: u64,
+    pub burn_lamports: u64,
+    pub lock_lamports: u64,
+    /// No swap is pending and the crank interval has elapsed.
+    pub interval_ok: bool,
+    /// There is profit left to swap after the tranche and liquidity leg.
+    pub profit_ok: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VaultStats {
+    pub vault_id: u64,
+    pub total_profit_lamports: u64,
+    pub total_burned: u64,
+    pub burned_bps_of_supply: u64,
+    pub total_locked: u64,
+    pub total_unlocked: u64,
+    pub total_cranks: u64,
+    pub lock_tranches: u64,
+    pub last_crank_ts: i64,
+    pub paused: bool,
+    pub profit_lamports_7d: u64,
+    pub tokens_bought_7d: u64,
+    pub profit_lamports_30d: u64,
+    pub tokens_bought_30d: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LockScheduleEntry {
+    pub lock_tranche: Pubkey,
+    pub index: u64,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub claimed: u64,
+    /// Released by the schedule so far, claimed or not.
+    pub vested: u64,
+    pub unlock_ts: i64,
+    pub matured: bool,
+}
+
+#[event]
+pub struct VaultInitializedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub vault_id: u64,
     pub authority: Pubkey,
     pub mint: Pubkey,
     pub burn_address: Pubkey,
     pub starting_balance_lamports: u64,
-    pub last_crank_ts: i64,
-    pub timelock_unlock_ts: i64,
-    pub bump: u8,
-    pub vault_bump: u8,
-    pub timelock_bump: u8,
 }
 
-impl VaultState {
-    pub const SIZE: usize = 32 + 32 + 32 + 8 + 8 + 8 + 1 + 1 + 1;
+#[event]
+pub struct DepositEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub depositor: Pubkey,
+    pub lamports: u64,
+    /// Vault balance after the deposit.
+    pub vault_lamports: u64,
+    pub referrer: Option<Pubkey>,
+    /// Solana Pay reference keys passed with the deposit.
+    pub references: Vec<Pubkey>,
+}
+
+#[event]
+pub struct DcaOpenedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub dca: Pubkey,
+    pub in_lamports: u64,
+    pub cycles: u16,
+    pub cycle_secs: i64,
+}
+
+#[event]
+pub struct DcaSettledEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub dca: Pubkey,
+    pub spent_lamports: u64,
+    pub tokens_bought: u64,
+}
+
+#[event]
+pub struct BidPlacedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub market: Pubkey,
+    pub price_lots: i64,
+    /// WSOL the new bid took.
+    pub bid_lamports: u64,
+    /// WSOL the previous bid spent on `tokens_bought`.
+    pub filled_lamports: u64,
+    pub tokens_bought: u64,
+}
+
+#[event]
+pub struct DonorBadgeMintedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub depositor: Pubkey,
+    pub asset: Pubkey,
+    /// Index into `config.donor_badges`.
+    pub tier: u8,
+    pub threshold_lamports: u64,
+}
+
+#[event]
+pub struct ConfigUpdatedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct CouncilSetEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub members: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct VetoCouncilSetEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub members: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct MintMigrationVetoedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub new_mint: Pubkey,
+    /// Bitmask of vetoing `veto_council` members.
+    pub approvals: u8,
+}
+
+#[event]
+pub struct ConfigChangeProposedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    /// Zero when the proposal was cancelled.
+    pub ready_ts: i64,
+}
+
+#[event]
+pub struct ConfigChangeVetoedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    /// Bitmask of vetoing `veto_council` members.
+    pub approvals: u8,
+}
+
+#[event]
+pub struct MintMigrationProposedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub new_mint: Option<Pubkey>,
+    pub ready_ts: i64,
+}
+
+#[event]
+pub struct MintMigratedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub old_mint: Pubkey,
+    pub new_mint: Pubkey,
+}
+
+#[event]
+pub struct ProtocolPausedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub admin: Pubkey,
+    pub paused: bool,
+}
+
+#[event]
+pub struct PausedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub paused: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct OraclePrice {
+    /// `price * 10^expo` in the feed's quote currency.
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: i64,
 }
 
 #[event]
 pub struct BuybackEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub vault_id: u64,
+    /// `BuybackRecord` index of the crank; `None` for `distribute_pending`.
+    pub crank_index: Option<u64>,
     pub profit_lamports: u64,
     pub burn_amount: u64,
     pub lock_amount: u64,
+    pub liquidity_amount: u64,
+    pub stake_rewards_amount: u64,
+    pub treasury_amount: u64,
+    pub retained_amount: u64,
+    pub holder_rewards_amount: u64,
+    pub fee_stake_amount: u64,
+    pub keeper_reward_amount: u64,
+    /// Crank payer, who received `keeper_reward_amount`.
+    pub keeper: Pubkey,
     pub burn_address: Pubkey,
     pub timelock_account: Pubkey,
+    /// Lamports per token base unit, scaled by `PRICE_SCALE`.
+    pub execution_price: u64,
+    /// `config.price_oracle` as published, when it was passed to the crank.
+    pub oracle_price: Option<OraclePrice>,
+    /// Lifetime burn as bps of the supply at `create_vault`.
+    pub burned_bps_of_supply: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FeeSource {
+    PumpFun,
+    RaydiumClmm,
+    MeteoraDlmm,
+    OrcaWhirlpool,
+    MeteoraStakeForFee,
+}
+
+#[event]
+pub struct ProtocolFeeCollectedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub lamports: u64,
+    pub treasury: Pubkey,
+    /// Part of `lamports` sent to the `KeeperPool` instead.
+    pub keeper_pool_lamports: u64,
+}
+
+#[event]
+pub struct KeeperTipPaidEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub keeper: Pubkey,
+    pub lamports: u64,
+}
+
+#[event]
+pub struct DistributionPublishedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub distribution: Pubkey,
+    pub index: u64,
+    pub root: [u8; 32],
+    pub amount: u64,
+}
+
+#[event]
+pub struct DistributionClaimedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub distribution: Pubkey,
+    pub claimant: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct BuybackAttestedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub buyback_record: Pubkey,
+    pub emitter: Pubkey,
+    /// Wormhole sequence of the posted message.
+    pub sequence: u64,
+}
+
+#[event]
+pub struct ApprovedRouteSetEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub accounts_hash: [u8; 32],
+    pub expires_ts: i64,
+}
+
+#[event]
+pub struct SolBurnedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub lamports: u64,
+    pub burn_address: Pubkey,
+}
+
+#[event]
+pub struct UnlockEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub lock_tranche: Pubkey,
+    pub index: u64,
+    pub mint: Pubkey,
+    pub amount: u64,
+    /// `None` when the tranche burns at unlock.
+    pub destination: Option<Pubkey>,
+    pub tranche_remaining: u64,
+    pub timelock_remaining: u64,
+}
+
+#[event]
+pub struct LockStreamedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub lock_tranche: Pubkey,
+    /// Streamflow metadata account of the new stream.
+    pub stream: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub end_ts: i64,
+}
+
+#[event]
+pub struct EarlyUnlockRequestedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub lock_tranche: Pubkey,
+    pub destination: Option<Pubkey>,
+    pub approvals: u8,
+    pub ready_ts: i64,
+}
+
+#[event]
+pub struct EarlyUnlockEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub state: Pubkey,
+    pub lock_tranche: Pubkey,
+    pub amount: u64,
+    /// `None` when the tranche burns at unlock.
+    pub destination: Option<Pubkey>,
+    pub tranche_remaining: u64,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct RelockEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub lock_tranche: Pubkey,
+    pub amount: u64,
+    pub unlock_ts: i64,
+}
+
+#[event]
+pub struct FeesHarvestedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub source: FeeSource,
+    pub lamports: u64,
+    pub token_amount: u64,
+}
+
+#[event]
+pub struct SplProfitSwappedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub profit_mint: Pubkey,
+    pub amount_in: u64,
+}
+
+#[event]
+pub struct LiquidityAddedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub pool: Pubkey,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    pub lp_burned: u64,
+    pub burn_lp_account: Pubkey,
+}
+
+#[event]
+pub struct WhirlpoolPositionOpenedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub whirlpool: Pubkey,
+    pub position: Pubkey,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+}
+
+#[event]
+pub struct PositionIncreasedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub whirlpool: Pubkey,
+    pub position: Pubkey,
+    pub liquidity: u128,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+}
+
+#[event]
+pub struct FeeStakeEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub m3m3_vault: Pubkey,
+    pub amount: u64,
+    pub staked_fee_tokens: u64,
+}
+
+#[event]
+pub struct PoolBootstrappedEvent {
+    pub version: u8,
+    pub seq: u64,
+    pub pool: Pubkey,
+    pub lp_mint: Pubkey,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    pub lp_burned: u64,
 }
 
 #[error_code]
@@ -395,4 +11965,108 @@ pub enum VaultError {
     NoTokens,
     #[msg("Timelock is still active.")]
     TimelockActive,
+    #[msg("Vault has never been cranked.")]
+    NeverCranked,
+    #[msg("Clock is behind the last crank.")]
+    ClockSkew,
+    #[msg("Arithmetic overflow.")]
+    MathOverflow,
+    #[msg("Invalid config.")]
+    InvalidConfig,
+    #[msg("Deposit would exceed the vault cap.")]
+    VaultCapExceeded,
+    #[msg("Vault invariant violated.")]
+    InvariantViolated,
+    #[msg("A swap is awaiting distribution.")]
+    SwapPending,
+    #[msg("No swap is awaiting distribution.")]
+    NoSwapPending,
+    #[msg("Pool does not match the vault config.")]
+    PoolNotConfigured,
+    #[msg("Strategy destination account is missing or does not match config.")]
+    MissingStrategyAccount,
+    #[msg("Amount exceeds the liquidity reserve.")]
+    InsufficientLiquidityReserve,
+    #[msg("Swap did not buy the exact-out target.")]
+    ExactOutNotMet,
+    #[msg("Vault buys multiple mints; use crank_multi.")]
+    MultiMintVault,
+    #[msg("Vault has no buyback mint list.")]
+    NotMultiMint,
+    #[msg("Route accounts do not match the vault config.")]
+    InvalidRoute,
+    #[msg("Route spent more than its leg's share.")]
+    LegOverspent,
+    #[msg("Profit mint must differ from the buyback mint and WSOL.")]
+    InvalidProfitMint,
+    #[msg("Not enough idle balance.")]
+    InsufficientIdleBalance,
+    #[msg("Not enough retained tokens.")]
+    InsufficientRetainedTokens,
+    #[msg("Crank is outside the configured schedule window.")]
+    OutsideCrankWindow,
+    #[msg("Route references a program that is not on the AMM allowlist.")]
+    AmmNotAllowed,
+    #[msg("Unlock amount exceeds the vested, unclaimed balance.")]
+    UnlockExceedsVested,
+    #[msg("Not enough council members signed.")]
+    CouncilNotApproved,
+    #[msg("The tranche's unlock mode does not allow this.")]
+    UnlockModeMismatch,
+    #[msg("Too many lock tranches for one call.")]
+    TooManyTranches,
+    #[msg("Unlock exceeds the rate limit for the current period.")]
+    UnlockRateLimited,
+    #[msg("The vault is paused.")]
+    Paused,
+    #[msg("Price oracle account is missing, unexpected, or malformed.")]
+    InvalidOracle,
+    #[msg("Signer is not the protocol admin.")]
+    NotProtocolAdmin,
+    #[msg("Vault profit is an SPL token; use deposit_token and crank_spl_profit.")]
+    BaseMintVault,
+    #[msg("Route spent more than the available profit.")]
+    ProfitOverspent,
+    #[msg("Cranks are paused protocol-wide.")]
+    ProtocolPaused,
+    #[msg("No mint migration is pending for this mint.")]
+    NoMintMigration,
+    #[msg("No keeper tip is available for this crank.")]
+    KeeperTipUnavailable,
+    #[msg("Oracle price is too old.")]
+    OracleStale,
+    #[msg("Oracle confidence interval is too wide.")]
+    OracleConfidence,
+    #[msg("Swap price strayed too far from the oracle.")]
+    SlippageExceeded,
+    #[msg("Token price is above the configured ceiling.")]
+    PriceAboveCeiling,
+    #[msg("Profit is worth less than the USD threshold.")]
+    ProfitBelowUsdThreshold,
+    #[msg("A distribution was already published this epoch.")]
+    DistributionTooSoon,
+    #[msg("Merkle proof does not match the distribution.")]
+    InvalidMerkleProof,
+    #[msg("The approved route has expired.")]
+    RouteExpired,
+    #[msg("Payer does not hold an operator pass from the configured collection.")]
+    NotOperator,
+    #[msg("A Raydium pool is already configured.")]
+    PoolAlreadyConfigured,
+    #[msg("A Whirlpool position is already open.")]
+    PositionAlreadyOpen,
+    #[msg("Pass a buyback record, or enable compressed records and pass the Light accounts.")]
+    MissingBuybackRecord,
+    #[msg("Pass the donor badge asset PDA for the tier this deposit reaches.")]
+    InvalidBadgeAsset,
+    #[msg("Payment references must be read-only, non-signer accounts.")]
+    InvalidReference,
+    #[msg("Jupiter has not closed the DCA order yet.")]
+    DcaStillOpen,
+    #[msg("The Token-2022 mint has an extension the vault cannot handle.")]
+    UnsupportedMintExtension,
+    #[msg("Distribution, payout, unlock and council settings change only through propose_config.")]
+    ConfigChangeTimelocked,
+    #[msg("No config change is pending.")]
+    NoConfigChange,
 }
@@ -0,0 +1,214 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import {
+  createAssociatedTokenAccount,
+  createMint,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  mintTo,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey } from "@solana/web3.js";
+import { assert } from "chai";
+import { BurnflipVault } from "../target/types/burnflip_vault";
+
+const BPF_UPGRADEABLE_LOADER = new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111");
+const CREATION_FEE_LAMPORTS = LAMPORTS_PER_SOL / 10;
+// `in_amount`, `quoted_out_amount`, `slippage_bps` and `platform_fee_bps`
+// that end Jupiter route data.
+const JUPITER_ROUTE_TAIL_LEN = 8 + 8 + 2 + 1;
+
+function u64Le(value: number | bigint): Buffer {
+  const buf = Buffer.alloc(8);
+  buf.writeBigUInt64LE(BigInt(value));
+  return buf;
+}
+
+// Burn/lock split with every optional feature off.
+function baseConfig() {
+  return {
+    clockToleranceSecs: new BN(0),
+    maxVaultLamports: null,
+    twap: { off: {} },
+    raydiumPool: null,
+    whirlpool: null,
+    pumpPool: null,
+    distribution: [
+      { strategy: { burn: {} }, bps: 5_000 },
+      { strategy: { lock: {} }, bps: 5_000 },
+    ],
+    stakeRewardsTokenAccount: null,
+    treasuryTokenAccount: null,
+    liquiditySolBps: 0,
+    solBurnBps: 0,
+    keeperRewardBps: 0,
+    keeperRewardEscalationBpsPerHour: 0,
+    keeperRewardMaxBps: 0,
+    exactOutAmount: null,
+    buybackMints: [],
+    raydiumPosition: null,
+    meteoraPosition: null,
+    stakePool: null,
+    workingBalanceLamports: new BN(0),
+    yieldBackend: null,
+    yieldAccount: null,
+    intermediateMint: null,
+    crankWindow: null,
+    ammAllowlist: [],
+    lockRelease: { cliff: {} },
+    unlockMode: { release: {} },
+    lockBuckets: [],
+    council: [],
+    councilThreshold: 0,
+    autoUnlock: false,
+    unlockRateLimit: null,
+    priceOracle: null,
+    proofRecipient: null,
+    baseMint: null,
+    oracleGuard: null,
+    operatorCollection: null,
+    whirlpoolPosition: null,
+    m3m3Vault: null,
+    aggregator: { jupiter: {} },
+    compressedRecords: false,
+    donorBadges: [],
+    openbookBids: null,
+    dca: null,
+  };
+}
+
+// A multisig or governance PDA signs through CPI and holds no SOL. Here an
+// unfunded keypair stands in for it: any lamports the program tried to take
+// from the authority would fail the transaction.
+describe("authority separate from payer", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.BurnflipVault as Program<BurnflipVault>;
+  const connection = provider.connection;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+  const authority = Keypair.generate();
+  const treasury = Keypair.generate();
+
+  let mint: PublicKey;
+  let state: PublicKey;
+  let timelockAuthority: PublicKey;
+
+  const pda = (...seeds: Buffer[]) =>
+    PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+
+  before(async () => {
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      BPF_UPGRADEABLE_LOADER,
+    );
+    await program.methods
+      .initProtocol({
+        treasury: treasury.publicKey,
+        creationFeeLamports: new BN(CREATION_FEE_LAMPORTS),
+        protocolFeeBps: 0,
+        crankIntervalSecs: new BN(150),
+        maxKeeperRewardBps: 0,
+        maxLiquiditySolBps: 0,
+        maxSolBurnBps: 0,
+        keeperPoolBps: 0,
+        keeperTipLamports: new BN(0),
+        keeperTipMaxProfitLamports: new BN(0),
+        allowedAmms: [],
+      })
+      .accountsPartial({ admin: payer.publicKey, programData })
+      .rpc();
+
+    mint = await createMint(connection, payer, payer.publicKey, null, 6);
+    state = pda(Buffer.from("state"), mint.toBuffer(), authority.publicKey.toBuffer());
+    timelockAuthority = pda(Buffer.from("timelock"), state.toBuffer());
+  });
+
+  it("create_vault charges the payer", async () => {
+    const registry = pda(Buffer.from("registry"));
+    await program.methods
+      .createVault(new BN(0), Keypair.generate().publicKey, baseConfig())
+      .accountsPartial({
+        authority: authority.publicKey,
+        payer: payer.publicKey,
+        mint,
+        treasury: treasury.publicKey,
+        state,
+        registry,
+        registryPage: pda(Buffer.from("registry_page"), u64Le(0)),
+      })
+      .signers([authority])
+      .rpc();
+
+    const vault = await program.account.vaultState.fetch(state);
+    assert.ok(vault.authority.equals(authority.publicKey));
+    assert.equal(await connection.getBalance(treasury.publicKey), CREATION_FEE_LAMPORTS);
+    assert.equal(await connection.getBalance(authority.publicKey), 0);
+  });
+
+  it("set_approved_route needs only the authority's signature", async () => {
+    const approvedRoute = pda(Buffer.from("route"), state.toBuffer());
+    await program.methods
+      .initApprovedRoute()
+      .accountsPartial({ payer: payer.publicKey, state, approvedRoute })
+      .rpc();
+
+    const tail = Buffer.alloc(JUPITER_ROUTE_TAIL_LEN);
+    tail.writeBigUInt64LE(1_000_000n, 0);
+    tail.writeBigUInt64LE(1_000n, 8);
+    const ixData = Buffer.concat([Buffer.alloc(8), tail]);
+    const expiresTs = new BN(Math.floor(Date.now() / 1000) + 3_600);
+    await program.methods
+      .setApprovedRoute(ixData, Array(32).fill(7), expiresTs)
+      .accountsPartial({ authority: authority.publicKey, state, approvedRoute })
+      .signers([authority])
+      .rpc();
+
+    const route = await program.account.approvedRoute.fetch(approvedRoute);
+    assert.ok(route.expiresTs.eq(expiresTs));
+    assert.equal(await connection.getBalance(authority.publicKey), 0);
+  });
+
+  it("relock takes the tranche rent from the payer", async () => {
+    const amount = 1_000_000;
+    const source = await createAssociatedTokenAccount(
+      connection,
+      payer,
+      mint,
+      authority.publicKey,
+    );
+    await mintTo(connection, payer, mint, source, payer, amount);
+    const timelockTokenAccount = getAssociatedTokenAddressSync(mint, timelockAuthority, true);
+    await createAssociatedTokenAccount(
+      connection,
+      payer,
+      mint,
+      timelockAuthority,
+      undefined,
+      TOKEN_PROGRAM_ID,
+      undefined,
+      true,
+    );
+
+    const lockTranche = pda(Buffer.from("lock"), state.toBuffer(), u64Le(0));
+    await program.methods
+      .relock(new BN(amount), new BN(86_400))
+      .accountsPartial({
+        authority: authority.publicKey,
+        payer: payer.publicKey,
+        state,
+        mint,
+        sourceTokenAccount: source,
+        timelockTokenAccount,
+        lockTranche,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    const tranche = await program.account.lockTranche.fetch(lockTranche);
+    assert.ok(tranche.payer.equals(payer.publicKey));
+    assert.ok(tranche.amount.eqn(amount));
+    assert.equal((await getAccount(connection, timelockTokenAccount)).amount, BigInt(amount));
+    assert.equal(await connection.getBalance(authority.publicKey), 0);
+  });
+});